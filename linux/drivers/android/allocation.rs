@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Buffer allocations inside a process's binder memory mapping.
+
+use kernel::{
+    pages::Pages,
+    prelude::*,
+    sync::Arc,
+    user_ptr::UserSlicePtrReader,
+    PAGE_SIZE,
+};
+
+/// Payloads below this size keep the byte-wise `copy_from_user` path; the
+/// `iov_iter` import costs more than it saves for small transactions.
+const BULK_COPY_THRESHOLD: usize = PAGE_SIZE;
+
+/// Bookkeeping for one allocation inside the mapping.
+pub(crate) struct AllocationInfo {
+    /// The offset of the allocation inside the mapping.
+    pub(crate) offset: usize,
+    /// The size of the allocation in bytes.
+    pub(crate) size: usize,
+    /// Whether the buffer must be zeroed when it is freed, because it
+    /// carried security-sensitive data.
+    pub(crate) clear_on_free: bool,
+}
+
+/// A live allocation inside a process's binder mapping.
+pub(crate) struct Allocation {
+    pub(crate) offset: usize,
+    pub(crate) size: usize,
+    pub(crate) pages: Arc<[Pages<0>]>,
+    /// Bookkeeping, including the clear-on-free marker set for
+    /// security-sensitive transactions.
+    pub(crate) info: Option<AllocationInfo>,
+}
+
+impl Allocation {
+    /// Copies `size` bytes from `reader` into the allocation at `offset`.
+    ///
+    /// Large payloads go through a single `iov_iter` import and
+    /// per-page `copy_page_from_iter` calls; small ones use the bounce
+    /// path, which measures faster below a page (see
+    /// [`BULK_COPY_THRESHOLD`]).
+    pub(crate) fn copy_into(
+        &self,
+        reader: &mut UserSlicePtrReader,
+        offset: usize,
+        size: usize,
+    ) -> Result {
+        if size >= BULK_COPY_THRESHOLD {
+            self.copy_into_bulk(reader, offset, size)
+        } else {
+            self.copy_into_bytewise(reader, offset, size)
+        }
+    }
+
+    /// The original path: bounce through a kernel buffer page by page.
+    fn copy_into_bytewise(
+        &self,
+        reader: &mut UserSlicePtrReader,
+        mut offset: usize,
+        mut size: usize,
+    ) -> Result {
+        let mut buf = [0u8; 256];
+        while size > 0 {
+            let n = size.min(buf.len());
+            reader.read_slice(&mut buf[..n])?;
+            self.write_kernel(&buf[..n], offset)?;
+            offset += n;
+            size -= n;
+        }
+        Ok(())
+    }
+
+    /// The bulk path: one import, page-sized copies straight into the
+    /// backing pages.
+    fn copy_into_bulk(
+        &self,
+        reader: &mut UserSlicePtrReader,
+        mut offset: usize,
+        mut size: usize,
+    ) -> Result {
+        // Import only this region; the reader keeps whatever follows
+        // (e.g. the offsets region after a payload's data region).
+        let mut iter = reader.as_iov_iter(size)?;
+        while size > 0 {
+            let page_index = offset >> PAGE_SIZE.trailing_zeros();
+            let page_offset = offset & (PAGE_SIZE - 1);
+            let n = size.min(PAGE_SIZE - page_offset);
+            let page = self.pages.get(page_index).ok_or(EINVAL)?;
+            page.copy_from_iter(&mut iter, page_offset, n)?;
+            offset += n;
+            size -= n;
+        }
+        Ok(())
+    }
+
+    /// Marks this allocation to be zeroed when it is freed.
+    pub(crate) fn set_info_clear_on_free(&mut self) {
+        match &mut self.info {
+            Some(info) => info.clear_on_free = true,
+            None => {
+                self.info = Some(AllocationInfo {
+                    offset: self.offset,
+                    size: self.size,
+                    clear_on_free: true,
+                });
+            }
+        }
+    }
+
+    /// Zeroes the allocation's bytes through the kernel-side page
+    /// mapping.
+    ///
+    /// Freeing can happen from a process other than the one that mapped
+    /// the buffer (or after the vma is gone), so this must not go
+    /// through a `UserSlicePtr` -- the kernel mapping of the backing
+    /// pages is the only address we can rely on here. (Paths that zero
+    /// while the user mapping is live can use
+    /// `UserSlicePtrWriter::write_zeroed` instead.)
+    fn zero_contents(&self) {
+        let mut offset = self.offset;
+        let mut size = self.size;
+        while size > 0 {
+            let page_index = offset >> PAGE_SIZE.trailing_zeros();
+            let page_offset = offset & (PAGE_SIZE - 1);
+            let n = size.min(PAGE_SIZE - page_offset);
+            if let Some(page) = self.pages.get(page_index) {
+                let _ = page.fill_zero(page_offset, n);
+            }
+            offset += n;
+            size -= n;
+        }
+    }
+
+    /// Writes kernel-side bytes into the allocation's pages.
+    pub(crate) fn write_kernel(&self, data: &[u8], offset: usize) -> Result {
+        let page_index = offset >> PAGE_SIZE.trailing_zeros();
+        let page_offset = offset & (PAGE_SIZE - 1);
+        // Split writes that straddle a page boundary.
+        let first = data.len().min(PAGE_SIZE - page_offset);
+        let page = self.pages.get(page_index).ok_or(EINVAL)?;
+        page.write(&data[..first], page_offset)?;
+        if first < data.len() {
+            self.write_kernel(&data[first..], offset + first)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        if matches!(&self.info, Some(info) if info.clear_on_free) {
+            // Stale transaction data must not survive into the next user
+            // of this range.
+            self.zero_contents();
+        }
+    }
+}
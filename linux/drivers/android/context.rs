@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! The binder context: global state shared by every process that opened the
+//! same binder device node.
+
+use crate::{node::NodeRef, process::Process};
+use alloc::vec::Vec;
+use kernel::{
+    c_str,
+    prelude::*,
+    seq_file::SeqFile,
+    seq_print,
+    sync::{Arc, Mutex},
+};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Transaction statistics for one context.
+///
+/// The counters are incremented with relaxed ordering on the hot paths;
+/// readers (only `debug_print`) therefore see an approximate snapshot,
+/// which is fine for a debug surface and avoids any contention on
+/// delivery.
+pub(crate) struct Stats {
+    /// Transactions submitted.
+    pub(crate) sent: AtomicU64,
+    /// Oneway transactions submitted (a subset of `sent`).
+    pub(crate) oneway: AtomicU64,
+    /// Replies delivered back to the sender.
+    pub(crate) replies: AtomicU64,
+    /// Transactions that could not be delivered.
+    pub(crate) failed: AtomicU64,
+}
+
+impl Stats {
+    const fn new() -> Self {
+        Self {
+            sent: AtomicU64::new(0),
+            oneway: AtomicU64::new(0),
+            replies: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn inc_sent(&self, oneway: bool) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        if oneway {
+            self.oneway.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn inc_replies(&self) {
+        self.replies.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// State guarded by the context mutex.
+pub(crate) struct ContextInner {
+    /// The context manager node, if one has been registered.
+    pub(crate) manager: Option<NodeRef>,
+    /// The euid allowed to (re-)register as context manager.
+    ///
+    /// Recorded on the first successful registration, like the C
+    /// driver's `binder_context_mgr_uid`; later attempts by a different
+    /// euid fail with `EPERM`. Cleared when the manager goes away.
+    pub(crate) manager_uid: Option<u32>,
+    /// Processes attached to this context, tagged with their tgid.
+    ///
+    /// A `Vec` of `(tgid, process)` pairs rather than a map: several
+    /// opens from one thread group are legal and all coexist, looked up
+    /// by tgid (`BINDER_FREEZE` targets every instance) and removed by
+    /// struct identity. The length is the registration count the cap
+    /// checks against, so the two can never diverge.
+    pub(crate) processes: Vec<(i32, Arc<Process>)>,
+}
+
+/// The maximum number of processes that may attach to one context.
+///
+/// This exists to stop fork-bomb-shaped abuse from pinning unbounded
+/// kernel memory, not to size real fleets; the value is far above any
+/// legitimate deployment.
+pub(crate) const MAX_PROCESSES: usize = 4096;
+
+/// A binder context.
+pub(crate) struct Context {
+    pub(crate) inner: Mutex<ContextInner>,
+    pub(crate) stats: Stats,
+}
+
+impl Context {
+    /// Creates a new, empty context.
+    pub(crate) fn new() -> Result<Arc<Self>> {
+        let ctx = Arc::try_new(Self {
+            // SAFETY: `init_once` is called below, before the context is
+            // shared.
+            inner: unsafe {
+                Mutex::new_uninit(ContextInner {
+                    manager: None,
+                    manager_uid: None,
+                    processes: Vec::new(),
+                })
+            },
+            stats: Stats::new(),
+        })?;
+        // SAFETY: The mutex is initialised exactly once, and `ctx` is not
+        // moved because the data lives behind the `Arc` allocation.
+        unsafe { ctx.inner.init_once(c_str!("Context::inner")) };
+        Ok(ctx)
+    }
+
+    /// Records a new process attaching to this context.
+    ///
+    /// Fails with `EAGAIN` at the cap -- the errno fork itself uses for
+    /// limit pressure, so callers see a familiar signal -- and the
+    /// failure surfaces through `open(2)`. Multiple opens from one
+    /// thread group each register their own entry.
+    pub(crate) fn register_process(self: &Arc<Self>, tgid: i32, process: Arc<Process>) -> Result {
+        let mut inner = self.inner.lock();
+        if inner.processes.len() >= MAX_PROCESSES {
+            return Err(EAGAIN);
+        }
+        inner.processes.try_reserve(1).map_err(|_| ENOMEM)?;
+        inner.processes.push((tgid, process));
+        Ok(())
+    }
+
+    /// Records a process detaching from this context, removed by
+    /// identity so duplicate tgids cannot shadow each other.
+    pub(crate) fn deregister_process(&self, process: &Arc<Process>) {
+        self.inner
+            .lock()
+            .processes
+            .retain(|(_, p)| !Arc::ptr_eq(p, process));
+    }
+
+    /// Returns every attached instance belonging to `tgid`.
+    pub(crate) fn processes_by_tgid(&self, tgid: i32) -> Vec<Arc<Process>> {
+        self.inner
+            .lock()
+            .processes
+            .iter()
+            .filter(|(t, _)| *t == tgid)
+            .map(|(_, p)| p.clone())
+            .collect()
+    }
+
+    /// Prints context-wide state for debugfs.
+    pub(crate) fn debug_print(&self, m: &mut SeqFile) {
+        let inner = self.inner.lock();
+        seq_print!(
+            m,
+            "processes: {} (max {})\n",
+            inner.processes.len(),
+            MAX_PROCESSES
+        );
+        drop(inner);
+        seq_print!(
+            m,
+            "transactions sent: {}\n",
+            self.stats.sent.load(Ordering::Relaxed)
+        );
+        seq_print!(
+            m,
+            "oneway transactions: {}\n",
+            self.stats.oneway.load(Ordering::Relaxed)
+        );
+        seq_print!(
+            m,
+            "replies delivered: {}\n",
+            self.stats.replies.load(Ordering::Relaxed)
+        );
+        seq_print!(
+            m,
+            "failed deliveries: {}\n",
+            self.stats.failed.load(Ordering::Relaxed)
+        );
+    }
+}
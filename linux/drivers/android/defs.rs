@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Types and constants shared with the binder UAPI.
+//!
+//! C header: [`include/uapi/linux/android/binder.h`](srctree/include/uapi/linux/android/binder.h)
+
+use kernel::bindings;
+use kernel::user_ptr::{ReadableFromBytes, WritableToBytes};
+
+// The UAPI command values come through bindgen; re-export the ones the
+// driver handles under their familiar names.
+pub(crate) const BINDER_WRITE_READ: u32 = bindings::BINDER_WRITE_READ;
+pub(crate) const BINDER_VERSION: u32 = bindings::BINDER_VERSION;
+pub(crate) const BINDER_SET_MAX_THREADS: u32 = bindings::BINDER_SET_MAX_THREADS;
+pub(crate) const BINDER_SET_CONTEXT_MGR: u32 = bindings::BINDER_SET_CONTEXT_MGR;
+pub(crate) const BINDER_SET_CONTEXT_MGR_EXT: u32 = bindings::BINDER_SET_CONTEXT_MGR_EXT;
+pub(crate) const BINDER_ENABLE_ONEWAY_SPAM_DETECTION: u32 =
+    bindings::BINDER_ENABLE_ONEWAY_SPAM_DETECTION;
+pub(crate) const BINDER_FREEZE: u32 = bindings::BINDER_FREEZE;
+pub(crate) const BINDER_GET_NODE_INFO_FOR_REF: u32 = bindings::BINDER_GET_NODE_INFO_FOR_REF;
+
+pub(crate) const BR_TRANSACTION_COMPLETE: u32 = bindings::binder_driver_return_protocol_BR_TRANSACTION_COMPLETE;
+pub(crate) const BR_ERROR: u32 = bindings::binder_driver_return_protocol_BR_ERROR;
+pub(crate) const BR_FAILED_REPLY: u32 = bindings::binder_driver_return_protocol_BR_FAILED_REPLY;
+pub(crate) const BR_DEAD_BINDER: u32 = bindings::binder_driver_return_protocol_BR_DEAD_BINDER;
+pub(crate) const BR_TRANSACTION: u32 = bindings::binder_driver_return_protocol_BR_TRANSACTION;
+pub(crate) const BR_ONEWAY_SPAM_SUSPECT: u32 =
+    bindings::binder_driver_return_protocol_BR_ONEWAY_SPAM_SUSPECT;
+pub(crate) const BR_FROZEN_BINDER: u32 =
+    bindings::binder_driver_return_protocol_BR_FROZEN_BINDER;
+pub(crate) const BR_CLEAR_FREEZE_NOTIFICATION_DONE: u32 =
+    bindings::binder_driver_return_protocol_BR_CLEAR_FREEZE_NOTIFICATION_DONE;
+pub(crate) const BR_TRANSACTION_PENDING_FROZEN: u32 =
+    bindings::binder_driver_return_protocol_BR_TRANSACTION_PENDING_FROZEN;
+
+pub(crate) const BC_TRANSACTION: u32 = bindings::binder_driver_command_protocol_BC_TRANSACTION;
+pub(crate) const BC_REPLY: u32 = bindings::binder_driver_command_protocol_BC_REPLY;
+pub(crate) const BC_INCREFS_DONE: u32 = bindings::binder_driver_command_protocol_BC_INCREFS_DONE;
+pub(crate) const BC_ACQUIRE_DONE: u32 = bindings::binder_driver_command_protocol_BC_ACQUIRE_DONE;
+pub(crate) const BC_ENTER_LOOPER: u32 = bindings::binder_driver_command_protocol_BC_ENTER_LOOPER;
+pub(crate) const BC_REGISTER_LOOPER: u32 = bindings::binder_driver_command_protocol_BC_REGISTER_LOOPER;
+pub(crate) const BC_REQUEST_FREEZE_NOTIFICATION: u32 =
+    bindings::binder_driver_command_protocol_BC_REQUEST_FREEZE_NOTIFICATION;
+pub(crate) const BC_CLEAR_FREEZE_NOTIFICATION: u32 =
+    bindings::binder_driver_command_protocol_BC_CLEAR_FREEZE_NOTIFICATION;
+pub(crate) const BC_FREEZE_NOTIFICATION_DONE: u32 =
+    bindings::binder_driver_command_protocol_BC_FREEZE_NOTIFICATION_DONE;
+pub(crate) const BC_REQUEST_DEATH_NOTIFICATION: u32 =
+    bindings::binder_driver_command_protocol_BC_REQUEST_DEATH_NOTIFICATION;
+pub(crate) const BC_CLEAR_DEATH_NOTIFICATION: u32 =
+    bindings::binder_driver_command_protocol_BC_CLEAR_DEATH_NOTIFICATION;
+pub(crate) const BC_DEAD_BINDER_DONE: u32 =
+    bindings::binder_driver_command_protocol_BC_DEAD_BINDER_DONE;
+
+/// The binder protocol version spoken by this driver.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct BinderVersion {
+    pub(crate) protocol_version: i32,
+}
+
+impl BinderVersion {
+    pub(crate) const fn current() -> Self {
+        Self {
+            protocol_version: bindings::BINDER_CURRENT_PROTOCOL_VERSION as _,
+        }
+    }
+}
+
+// SAFETY: The struct is `repr(C)` with no padding and no uninit bytes.
+unsafe impl WritableToBytes for BinderVersion {}
+
+/// The native (64-bit pointer) layout of `struct binder_write_read`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct BinderWriteRead {
+    pub(crate) write_size: u64,
+    pub(crate) write_consumed: u64,
+    pub(crate) write_buffer: u64,
+    pub(crate) read_size: u64,
+    pub(crate) read_consumed: u64,
+    pub(crate) read_buffer: u64,
+}
+
+// SAFETY: All bit patterns are valid and there is no padding.
+unsafe impl ReadableFromBytes for BinderWriteRead {}
+// SAFETY: The struct has no uninitialised bytes.
+unsafe impl WritableToBytes for BinderWriteRead {}
+
+/// The 32-bit userspace layout of `struct binder_write_read`.
+///
+/// Buffer pointers and sizes are 32 bits wide when a compat task speaks to
+/// the driver; the compat entry points translate to/from
+/// [`BinderWriteRead`] around the native logic.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct BinderWriteRead32 {
+    pub(crate) write_size: u32,
+    pub(crate) write_consumed: u32,
+    pub(crate) write_buffer: u32,
+    pub(crate) read_size: u32,
+    pub(crate) read_consumed: u32,
+    pub(crate) read_buffer: u32,
+}
+
+// SAFETY: All bit patterns are valid and there is no padding.
+unsafe impl ReadableFromBytes for BinderWriteRead32 {}
+// SAFETY: The struct has no uninitialised bytes.
+unsafe impl WritableToBytes for BinderWriteRead32 {}
+
+impl From<BinderWriteRead32> for BinderWriteRead {
+    fn from(v: BinderWriteRead32) -> Self {
+        // Zero-extension matches `compat_ptr` semantics on every arch that
+        // supports compat tasks.
+        Self {
+            write_size: v.write_size.into(),
+            write_consumed: v.write_consumed.into(),
+            write_buffer: v.write_buffer.into(),
+            read_size: v.read_size.into(),
+            read_consumed: v.read_consumed.into(),
+            read_buffer: v.read_buffer.into(),
+        }
+    }
+}
+
+impl BinderWriteRead32 {
+    /// Narrows the consumed counters back into the 32-bit layout after the
+    /// native logic ran. Only the consumed fields can have changed.
+    pub(crate) fn update_from(&mut self, native: &BinderWriteRead) {
+        self.write_consumed = native.write_consumed as u32;
+        self.read_consumed = native.read_consumed as u32;
+    }
+}
+
+/// The node requests the sender's security context with each
+/// transaction.
+pub(crate) const FLAT_BINDER_FLAG_TXN_SECURITY_CTX: u32 =
+    bindings::flat_binder_flag_FLAT_BINDER_FLAG_TXN_SECURITY_CTX;
+
+/// Payload of `BR_FROZEN_BINDER`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct BinderFrozenStateInfo {
+    pub(crate) cookie: u64,
+    pub(crate) is_frozen: u32,
+    pub(crate) reserved: u32,
+}
+
+// SAFETY: `repr(C)` with explicit padding field; no uninit bytes.
+unsafe impl kernel::user_ptr::WritableToBytes for BinderFrozenStateInfo {}
+
+/// Payload of the freeze-notification `BC_*` commands.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub(crate) struct BinderHandleCookie {
+    pub(crate) handle: u32,
+    pub(crate) cookie: u64,
+}
+
+// SAFETY: Packed `repr(C)`; every bit pattern is valid.
+unsafe impl kernel::user_ptr::ReadableFromBytes for BinderHandleCookie {}
+
+/// Payload of `BINDER_GET_NODE_INFO_FOR_REF`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct BinderNodeInfoForRef {
+    pub(crate) handle: u32,
+    pub(crate) strong_count: u32,
+    pub(crate) weak_count: u32,
+    pub(crate) reserved1: u32,
+    pub(crate) reserved2: u32,
+    pub(crate) reserved3: u32,
+}
+
+// SAFETY: `repr(C)` of plain `u32`s; no padding, all patterns valid.
+unsafe impl kernel::user_ptr::ReadableFromBytes for BinderNodeInfoForRef {}
+// SAFETY: See above; no uninit bytes.
+unsafe impl kernel::user_ptr::WritableToBytes for BinderNodeInfoForRef {}
+
+/// Payload of `BINDER_FREEZE`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(crate) struct BinderFreezeInfo {
+    pub(crate) pid: u32,
+    pub(crate) enable: u32,
+    pub(crate) timeout_ms: u32,
+}
+
+// SAFETY: `repr(C)` with no padding; every bit pattern is valid.
+unsafe impl kernel::user_ptr::ReadableFromBytes for BinderFreezeInfo {}
+
+/// Space reserved at the end of a transaction buffer for the sender's
+/// security context, when the target node requests one.
+pub(crate) const SECCTX_MAX: usize = 256;
+
+/// The wire layout of `struct binder_transaction_data`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct BinderTransactionData {
+    /// Target handle (sends) or pointer (deliveries); the C union's
+    /// widest member.
+    pub(crate) target: u64,
+    pub(crate) cookie: u64,
+    pub(crate) code: u32,
+    pub(crate) flags: u32,
+    pub(crate) sender_pid: i32,
+    pub(crate) sender_euid: u32,
+    pub(crate) data_size: u64,
+    pub(crate) offsets_size: u64,
+    pub(crate) data_buffer: u64,
+    pub(crate) data_offsets: u64,
+}
+
+// SAFETY: `repr(C)` with no padding (all fields naturally aligned); every
+// bit pattern is valid.
+unsafe impl kernel::user_ptr::ReadableFromBytes for BinderTransactionData {}
+// SAFETY: See above; no uninit bytes.
+unsafe impl kernel::user_ptr::WritableToBytes for BinderTransactionData {}
+
+/// The maximum depth of a synchronous transaction stack.
+///
+/// Each nested synchronous call pins a kernel `Transaction` (and a thread
+/// on each side), so an unbounded chain lets a hostile pair of processes
+/// burn kernel memory and stack. 128 comfortably exceeds any legitimate
+/// nesting observed in practice while keeping the worst case bounded.
+pub(crate) const MAX_TRANSACTION_STACK_DEPTH: u32 = 128;
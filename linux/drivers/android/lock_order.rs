@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Debug-only enforcement of the driver's lock ordering.
+//!
+//! The documented order is `node_refs` (rank 0) -> `Process::inner`
+//! (rank 1) -> `Thread::inner` (rank 2): a lock may only be acquired when
+//! every lock already held by the current task has a strictly lower rank.
+//! Violations indicate a deadlock-capable path and are reported with both
+//! ranks; under the debug config they are driver bugs, so the report is a
+//! loud warning rather than a silent counter.
+//!
+//! Everything here compiles away without `CONFIG_ANDROID_BINDER_LOCK_DEBUG`.
+
+#[cfg(CONFIG_ANDROID_BINDER_LOCK_DEBUG)]
+mod imp {
+    use kernel::bindings;
+    use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+    /// Rank of `Process::node_refs`.
+    pub(crate) const RANK_NODE_REFS: u32 = 0;
+    /// Rank of `Process::inner`.
+    pub(crate) const RANK_PROCESS_INNER: u32 = 1;
+    /// Rank of `Thread::inner`.
+    pub(crate) const RANK_THREAD_INNER: u32 = 2;
+
+    /// One slot per task that currently holds binder locks. Collisions
+    /// just mean a task cannot be tracked this acquisition; the table is
+    /// a debug aid, not a correctness mechanism, so that is acceptable
+    /// and noted here rather than hidden.
+    const SLOTS: usize = 64;
+
+    struct Slot {
+        task: AtomicUsize,
+        held: AtomicU64,
+    }
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY_SLOT: Slot = Slot {
+        task: AtomicUsize::new(0),
+        held: AtomicU64::new(0),
+    };
+
+    static TABLE: [Slot; SLOTS] = [EMPTY_SLOT; SLOTS];
+
+    fn current_task() -> usize {
+        // SAFETY: `current` is always valid.
+        unsafe { bindings::get_current() as usize }
+    }
+
+    fn slot_for(task: usize, claim: bool) -> Option<&'static Slot> {
+        let start = (task >> 4) % SLOTS;
+        for i in 0..SLOTS {
+            let slot = &TABLE[(start + i) % SLOTS];
+            let owner = slot.task.load(Ordering::Acquire);
+            if owner == task {
+                return Some(slot);
+            }
+            if owner == 0 && claim {
+                if slot
+                    .task
+                    .compare_exchange(0, task, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    return Some(slot);
+                }
+            }
+        }
+        None
+    }
+
+    /// Records the acquisition of a lock of rank `rank`, warning if a
+    /// lock of equal or higher rank is already held by this task.
+    pub(crate) fn acquire(rank: u32) {
+        let task = current_task();
+        let Some(slot) = slot_for(task, true) else {
+            return; // Table full; skip tracking this acquisition.
+        };
+        let held = slot.held.load(Ordering::Relaxed);
+        // Any held bit at `rank` or above means out-of-order acquisition.
+        if held >> rank != 0 {
+            let highest = 63 - held.leading_zeros();
+            kernel::pr_warn!(
+                "binder: lock order violation: acquiring rank {} while holding rank {}\n",
+                rank,
+                highest,
+            );
+        }
+        slot.held.store(held | (1 << rank), Ordering::Relaxed);
+    }
+
+    /// Records the release of a lock of rank `rank`.
+    pub(crate) fn release(rank: u32) {
+        let task = current_task();
+        let Some(slot) = slot_for(task, false) else {
+            return;
+        };
+        let held = slot.held.load(Ordering::Relaxed) & !(1 << rank);
+        slot.held.store(held, Ordering::Relaxed);
+        if held == 0 {
+            slot.task.store(0, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(not(CONFIG_ANDROID_BINDER_LOCK_DEBUG))]
+mod imp {
+    pub(crate) const RANK_NODE_REFS: u32 = 0;
+    pub(crate) const RANK_PROCESS_INNER: u32 = 1;
+    pub(crate) const RANK_THREAD_INNER: u32 = 2;
+
+    #[inline]
+    pub(crate) fn acquire(_rank: u32) {}
+
+    #[inline]
+    pub(crate) fn release(_rank: u32) {}
+}
+
+pub(crate) use imp::*;
+
+/// A lock guard paired with its rank, releasing the rank bookkeeping when
+/// the lock is dropped.
+pub(crate) struct TrackedGuard<G> {
+    // Field order matters: the lock must be released before the rank is.
+    guard: G,
+    rank: RankToken,
+}
+
+struct RankToken(u32);
+
+impl Drop for RankToken {
+    fn drop(&mut self) {
+        release(self.0);
+    }
+}
+
+impl<G> TrackedGuard<G> {
+    /// Wraps a freshly-acquired guard whose acquisition was recorded with
+    /// [`acquire`] of `rank`.
+    pub(crate) fn new(guard: G, rank: u32) -> Self {
+        Self {
+            guard,
+            rank: RankToken(rank),
+        }
+    }
+}
+
+impl<G: core::ops::Deref> core::ops::Deref for TrackedGuard<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        let _ = &self.rank;
+        &self.guard
+    }
+}
+
+impl<G: core::ops::DerefMut> core::ops::DerefMut for TrackedGuard<G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
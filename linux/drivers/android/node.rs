@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Binder nodes: one per object exposed over binder, owned by the process
+//! that created the object.
+
+use crate::process::Process;
+use kernel::{list::ListLinks, prelude::*, sync::Arc};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// Source of process-wide unique node debug ids.
+static NEXT_DEBUG_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A binder node.
+pub(crate) struct Node {
+    /// A global id identifying this node in debug output.
+    pub(crate) debug_id: u64,
+    /// `FLAT_BINDER_FLAG_*` bits the node was published with.
+    pub(crate) flags: u32,
+    /// The process that owns this node.
+    pub(crate) owner: Arc<Process>,
+    /// A cookie supplied by and returned to userspace.
+    pub(crate) cookie: u64,
+    /// The userspace pointer identifying the object.
+    pub(crate) ptr: u64,
+    /// Outstanding `BC_*_DONE` acknowledgements expected from the owner.
+    pub(crate) active_inc_refs: core::sync::atomic::AtomicU32,
+    /// Total strong references held on this node, across all processes.
+    pub(crate) strong_refs: AtomicUsize,
+    /// Total weak references held on this node, across all processes.
+    pub(crate) weak_refs: AtomicUsize,
+    /// Bytes of async buffer space currently attributed to this node's
+    /// queued oneway transactions.
+    pub(crate) pending_oneway_bytes: AtomicUsize,
+    /// Set once a spam suspicion has been raised for the current pressure
+    /// episode, so the signal fires at most once until pressure clears.
+    oneway_spam_flagged: AtomicBool,
+    pub(crate) links: ListLinks,
+    pub(crate) list_tracker: AtomicBool,
+}
+
+impl Node {
+    pub(crate) fn new(owner: Arc<Process>, ptr: u64, cookie: u64) -> Result<Arc<Self>> {
+        Self::new_with_flags(owner, ptr, cookie, 0)
+    }
+
+    pub(crate) fn new_with_flags(
+        owner: Arc<Process>,
+        ptr: u64,
+        cookie: u64,
+        flags: u32,
+    ) -> Result<Arc<Self>> {
+        Arc::try_new(Self {
+            debug_id: NEXT_DEBUG_ID.fetch_add(1, Ordering::Relaxed),
+            flags,
+            owner,
+            cookie,
+            ptr,
+            active_inc_refs: core::sync::atomic::AtomicU32::new(0),
+            strong_refs: AtomicUsize::new(0),
+            weak_refs: AtomicUsize::new(0),
+            pending_oneway_bytes: AtomicUsize::new(0),
+            oneway_spam_flagged: AtomicBool::new(false),
+            links: ListLinks::new(),
+            list_tracker: AtomicBool::new(false),
+        })
+        .map_err(Error::from)
+    }
+}
+
+impl Node {
+    /// Consumes one expected `BC_INCREFS_DONE`/`BC_ACQUIRE_DONE`
+    /// acknowledgement.
+    ///
+    /// Returns `false` if no acknowledgement was outstanding -- the
+    /// sender acked a ref it never held, a protocol violation the
+    /// caller is expected to surface rather than swallow.
+    pub(crate) fn inc_ref_done_locked(&self) -> bool {
+        let mut current = self.active_inc_refs.load(Ordering::Relaxed);
+        loop {
+            if current == 0 {
+                return false;
+            }
+            match self.active_inc_refs.compare_exchange_weak(
+                current,
+                current - 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(v) => current = v,
+            }
+        }
+    }
+
+    /// Accounts a queued oneway transaction of `size` bytes against this
+    /// node and reports whether the sender should be flagged as a spam
+    /// suspect.
+    ///
+    /// This is the C driver's low-async-space heuristic: while the
+    /// target's free async space is under pressure, a single node holding
+    /// more than a sixteenth of it is suspicious. The answer is one-shot
+    /// per pressure episode (rearmed by [`Node::release_oneway`] once the
+    /// node's share drains), so a flood produces one
+    /// `BR_ONEWAY_SPAM_SUSPECT`, not one per transaction.
+    pub(crate) fn submit_oneway_accounting(&self, size: usize, free_async_space: usize) -> bool {
+        let pending = self.pending_oneway_bytes.fetch_add(size, Ordering::Relaxed) + size;
+        let under_pressure = free_async_space < size.saturating_mul(4);
+        if under_pressure && pending > free_async_space / 16 {
+            // One-shot: only the transition to flagged reports true.
+            !self.oneway_spam_flagged.swap(true, Ordering::Relaxed)
+        } else {
+            false
+        }
+    }
+
+    /// Releases the accounting of a delivered/freed oneway transaction.
+    pub(crate) fn release_oneway(&self, size: usize) {
+        let pending = self.pending_oneway_bytes.fetch_sub(size, Ordering::Relaxed) - size;
+        if pending == 0 {
+            self.oneway_spam_flagged.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A reference (strong and/or weak) held by one process to a node owned by
+/// another.
+pub(crate) struct NodeRef {
+    pub(crate) node: Arc<Node>,
+    /// Number of strong references this ref contributes to the node.
+    pub(crate) strong_count: usize,
+    /// Number of weak references this ref contributes to the node.
+    pub(crate) weak_count: usize,
+    /// Whether a death notification is registered on this ref.
+    pub(crate) death_registered: bool,
+    /// The cookie delivered with `BR_DEAD_BINDER` for this ref.
+    pub(crate) death_cookie: Option<u64>,
+}
+
+impl NodeRef {
+    pub(crate) fn new(node: Arc<Node>, strong_count: usize, weak_count: usize) -> Self {
+        node.strong_refs.fetch_add(strong_count, Ordering::Relaxed);
+        node.weak_refs.fetch_add(weak_count, Ordering::Relaxed);
+        Self {
+            node,
+            strong_count,
+            weak_count,
+            death_registered: false,
+            death_cookie: None,
+        }
+    }
+
+    /// Fills `strong`/`weak` with the node's total reference counts.
+    pub(crate) fn populate_counts(&self, strong: &mut u32, weak: &mut u32) {
+        *strong = self.node.strong_refs.load(Ordering::Relaxed) as u32;
+        *weak = self.node.weak_refs.load(Ordering::Relaxed) as u32;
+    }
+}
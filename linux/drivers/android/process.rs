@@ -0,0 +1,624 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Binder processes: one per open file of the binder device.
+//!
+//! Lock ordering in this driver: `node_refs` -> `inner` -> `Thread::inner`.
+
+use crate::{
+    context::Context,
+    defs::*,
+    lock_order::{self, TrackedGuard},
+    node::Node,
+    thread::Thread,
+};
+use kernel::{
+    bindings, c_str,
+    file::File,
+    prelude::*,
+    sync::{Arc, ArcBorrow, Mutex, SpinLock},
+    user_ptr::UserSlicePtr,
+};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// State guarded by the process spinlock.
+pub(crate) struct ProcessInner {
+    /// Whether this process is the context manager.
+    pub(crate) is_manager: bool,
+    /// Whether the process is shutting down.
+    pub(crate) is_dead: bool,
+    /// The maximum number of looper threads userspace promised to spawn.
+    pub(crate) max_threads: u32,
+    /// Whether `BINDER_ENABLE_ONEWAY_SPAM_DETECTION` armed spam
+    /// detection for this process's sends.
+    pub(crate) oneway_spam_detection_enabled: bool,
+    /// Whether this process is frozen (`BINDER_FREEZE`).
+    pub(crate) is_frozen: bool,
+    /// Freeze listeners watching *this* process, registered by other
+    /// processes via `BC_REQUEST_FREEZE_NOTIFICATION` on a handle to one
+    /// of our nodes.
+    pub(crate) freeze_listeners: Vec<FreezeListener>,
+    /// `BR_FROZEN_BINDER` payloads queued for delivery *to* this
+    /// process.
+    pub(crate) pending_frozen: Vec<(u64, bool)>,
+    /// `BR_CLEAR_FREEZE_NOTIFICATION_DONE` cookies queued for delivery
+    /// to this process.
+    pub(crate) pending_clear_done: Vec<u64>,
+    /// Death notifications delivered but not yet acknowledged with
+    /// `BC_DEAD_BINDER_DONE`, by cookie.
+    pub(crate) delivered_deaths: Vec<u64>,
+    /// Transactions awaiting a thread, when none was ready at submit.
+    pub(crate) pending_work: Vec<crate::thread::TransactionWork>,
+    /// `BR_DEAD_BINDER` cookies queued for delivery to this process.
+    pub(crate) pending_deaths: Vec<u64>,
+    /// Userspace protocol violations observed (e.g. spurious
+    /// `BC_ACQUIRE_DONE`), surfaced in `debug_print`.
+    pub(crate) protocol_violations: u32,
+    /// Edge trigger for the soft-cap warning: set when the cap is
+    /// crossed upward, cleared when the list shrinks below it, so a
+    /// process hovering at the cap logs once per excursion rather than
+    /// per delivery.
+    delivered_deaths_warned: bool,
+    /// Threads of this process, by userspace thread id.
+    pub(crate) threads: BTreeMap<i32, Arc<Thread>>,
+    /// Threads waiting for work, in round-robin order: work is taken
+    /// from the front, and a thread returning to readiness goes to the
+    /// *back*.
+    ///
+    /// The back-insertion is the fairness mechanism -- and a deliberate
+    /// departure from C binder, whose LIFO reuse keeps the hottest
+    /// thread's caches warm at the cost of starving the rest. Rotating
+    /// spreads work across all ready threads, trading some cache warmth
+    /// for bounded per-thread latency; the deliveries counters in
+    /// `debug_print` exist to measure exactly that trade.
+    pub(crate) ready_threads: Vec<Arc<Thread>>,
+}
+
+/// References to other processes' nodes, keyed by handle.
+pub(crate) struct ProcessNodeRefs {
+    pub(crate) by_handle: BTreeMap<u32, crate::node::NodeRef>,
+}
+
+/// Soft cap on undelivered death acknowledgements.
+///
+/// Deaths must always be delivered, so crossing the cap cannot hard-fail;
+/// but a process sitting on this many unacked BR_DEAD_BINDERs has stopped
+/// running its binder loop, which is worth telling the log about.
+pub(crate) const DELIVERED_DEATHS_SOFT_CAP: usize = 256;
+
+/// The async buffer budget assumed per process until the Rust allocator
+/// provides the real free-space figure (half the default 1 MiB mapping,
+/// matching the C driver's split).
+pub(crate) const ASYNC_SPACE_BUDGET: usize = 512 * 1024;
+
+/// A process using the binder device.
+pub(crate) struct Process {
+    pub(crate) ctx: Arc<Context>,
+    pub(crate) inner: SpinLock<ProcessInner>,
+    pub(crate) node_refs: Mutex<ProcessNodeRefs>,
+    /// Nodes owned by this process, keyed by their userspace pointer.
+    pub(crate) nodes: Mutex<BTreeMap<u64, Arc<Node>>>,
+}
+
+impl Process {
+    /// Creates the per-open state when the device is opened.
+    pub(crate) fn open(ctx: Arc<Context>, _file: &File) -> Result<Arc<Self>> {
+        let process = Arc::try_new(Self {
+            ctx: ctx.clone(),
+            // SAFETY: All three locks are initialised below, before the
+            // arc is shared, and the data behind the `Arc` does not move.
+            inner: unsafe {
+                SpinLock::new_uninit(ProcessInner {
+                    is_manager: false,
+                    is_dead: false,
+                    max_threads: 0,
+                    oneway_spam_detection_enabled: false,
+                    is_frozen: false,
+                    freeze_listeners: Vec::new(),
+                    pending_frozen: Vec::new(),
+                    pending_clear_done: Vec::new(),
+                    delivered_deaths: Vec::new(),
+                    pending_work: Vec::new(),
+                    pending_deaths: Vec::new(),
+                    protocol_violations: 0,
+                    delivered_deaths_warned: false,
+                    threads: BTreeMap::new(),
+                    ready_threads: Vec::new(),
+                })
+            },
+            node_refs: unsafe {
+                Mutex::new_uninit(ProcessNodeRefs {
+                    by_handle: BTreeMap::new(),
+                })
+            },
+            nodes: unsafe { Mutex::new_uninit(BTreeMap::new()) },
+        })?;
+        // SAFETY: See above; each lock is initialised exactly once.
+        unsafe {
+            process.inner.init_once(c_str!("Process::inner"));
+            process.node_refs.init_once(c_str!("Process::node_refs"));
+            process.nodes.init_once(c_str!("Process::nodes"));
+        }
+        ctx.register_process(current_tgid(), process.clone())?;
+        Ok(process)
+    }
+
+    /// Acquires `inner` with lock-order tracking.
+    ///
+    /// All driver code should take the process locks through these
+    /// wrappers; the tracking compiles away in production builds.
+    pub(crate) fn lock_inner(&self) -> TrackedGuard<kernel::sync::Guard<'_, ProcessInner, kernel::sync::lock::spinlock::SpinLockBackend>> {
+        lock_order::acquire(lock_order::RANK_PROCESS_INNER);
+        TrackedGuard::new(self.inner.lock(), lock_order::RANK_PROCESS_INNER)
+    }
+
+    /// Acquires `node_refs` with lock-order tracking.
+    pub(crate) fn lock_node_refs(&self) -> TrackedGuard<kernel::sync::Guard<'_, ProcessNodeRefs, kernel::sync::lock::mutex::MutexBackend>> {
+        lock_order::acquire(lock_order::RANK_NODE_REFS);
+        TrackedGuard::new(self.node_refs.lock(), lock_order::RANK_NODE_REFS)
+    }
+
+    /// Tears the process down on the final close, delivering death
+    /// notifications to every registered watcher of our nodes.
+    pub(crate) fn cleanup(self: Arc<Self>) {
+        self.inner.lock().is_dead = true;
+        let watchers: Vec<Arc<Process>> = {
+            let ctx = self.ctx.inner.lock();
+            ctx.processes.iter().map(|(_, p)| p.clone()).collect()
+        };
+        for watcher in watchers {
+            if Arc::ptr_eq(&watcher, &self) {
+                continue;
+            }
+            // node_refs (rank 0) strictly before the watcher's inner
+            // (rank 1), per the documented order.
+            let cookies: Vec<u64> = {
+                let refs = watcher.lock_node_refs();
+                refs.by_handle
+                    .values()
+                    .filter(|r| {
+                        r.death_registered && Arc::ptr_eq(&r.node.owner, &self)
+                    })
+                    .filter_map(|r| r.death_cookie)
+                    .collect()
+            };
+            for cookie in cookies {
+                watcher.lock_inner().pending_deaths.push(cookie);
+                watcher.death_delivered(cookie);
+                watcher.notify_all_threads();
+            }
+        }
+        self.ctx.deregister_process(&self);
+    }
+
+    /// Wakes every thread of this process that may be waiting for work.
+    pub(crate) fn notify_all_threads(&self) {
+        let threads: Vec<Arc<Thread>> = self.lock_inner().threads.values().cloned().collect();
+        for thread in threads {
+            thread.work_condvar.notify_one();
+        }
+    }
+
+    /// Returns the thread object for the calling userspace thread,
+    /// creating it on first use.
+    pub(crate) fn get_thread(self: &Arc<Self>, id: i32) -> Result<Arc<Thread>> {
+        if let Some(thread) = self.inner.lock().threads.get(&id) {
+            return Ok(thread.clone());
+        }
+        let thread = Thread::new(self.clone(), id)?;
+        self.inner.lock().threads.insert(id, thread.clone());
+        Ok(thread)
+    }
+
+    /// Writes the driver protocol version to `data`.
+    pub(crate) fn version(&self, data: UserSlicePtr) -> Result {
+        data.writer().write(&BinderVersion::current())
+    }
+
+    /// Handles `BINDER_WRITE_READ` for a native (64-bit layout) caller.
+    pub(crate) fn read_write(self: &Arc<Self>, data: UserSlicePtr, is_compat: bool) -> Result {
+        if is_compat {
+            return self.read_write_compat(data);
+        }
+        let thread = self.get_thread(current_tid())?;
+        let (mut reader, mut writer) = data.reader_writer();
+        let mut req = reader.read::<BinderWriteRead>()?;
+        let res = self.do_write_read(&thread, &mut req);
+        // The consumed counters must reach userspace even on failure,
+        // like the C driver's binder_ioctl_write_read (and like the
+        // compat path below).
+        writer.write(&req)?;
+        res
+    }
+
+    /// Handles `BINDER_WRITE_READ` for a 32-bit caller: the buffer
+    /// pointers and sizes are 32 bits wide, so read the compat layout,
+    /// widen (zero-extending, matching `compat_ptr`), run the native
+    /// logic, and narrow the consumed counters back on the way out.
+    fn read_write_compat(self: &Arc<Self>, data: UserSlicePtr) -> Result {
+        let thread = self.get_thread(current_tid())?;
+        let (mut reader, mut writer) = data.reader_writer();
+        let mut req32 = reader.read::<BinderWriteRead32>()?;
+        let mut req = BinderWriteRead::from(req32);
+        let res = self.do_write_read(&thread, &mut req);
+        req32.update_from(&req);
+        // The consumed counters must reach userspace even on failure,
+        // like the C driver's `binder_ioctl_write_read`.
+        writer.write(&req32)?;
+        res
+    }
+
+    /// The layout-independent core of `BINDER_WRITE_READ`.
+    fn do_write_read(self: &Arc<Self>, thread: &Arc<Thread>, req: &mut BinderWriteRead) -> Result {
+        // The consumed counters are user-controlled; reject inconsistent
+        // ones before they feed the pointer arithmetic below.
+        if req.write_consumed > req.write_size || req.read_consumed > req.read_size {
+            return Err(EINVAL);
+        }
+        if req.write_size > 0 {
+            let write_ptr = req
+                .write_buffer
+                .checked_add(req.write_consumed)
+                .ok_or(EINVAL)?;
+            // SAFETY: Userspace supplied the pointer; all accesses go
+            // through `copy_{from,to}_user`, which check it.
+            let mut reader = unsafe {
+                UserSlicePtr::new(write_ptr as _, (req.write_size - req.write_consumed) as usize)
+            }
+            .reader();
+            let before = reader.len();
+            let res = thread.write(&mut reader);
+            req.write_consumed += (before - reader.len()) as u64;
+            res?;
+        }
+        if req.read_size > 0 {
+            let read_ptr = req
+                .read_buffer
+                .checked_add(req.read_consumed)
+                .ok_or(EINVAL)?;
+            // SAFETY: As above.
+            let mut writer = unsafe {
+                UserSlicePtr::new(read_ptr as _, (req.read_size - req.read_consumed) as usize)
+            }
+            .writer();
+            let before = writer.len();
+            let res = thread.read(&mut writer, req.read_size > 0);
+            req.read_consumed += (before - writer.len()) as u64;
+            res?;
+        }
+        Ok(())
+    }
+
+    /// Registers the calling process as the context manager.
+    ///
+    /// The uid check runs first, before any node is published, matching
+    /// the ordering of the C driver's `binder_ioctl_set_ctx_mgr` (uid
+    /// gate, then security hook, then installation).
+    pub(crate) fn set_as_manager(self: &Arc<Self>) -> Result {
+        let euid = kernel::task::Task::current().euid();
+        let node = Node::new(self.clone(), 0, 0)?;
+        let mut ctx = self.ctx.inner.lock();
+        match ctx.manager_uid {
+            Some(uid) if uid != euid => return Err(EPERM),
+            _ => {}
+        }
+        if ctx.manager.is_some() {
+            return Err(EBUSY);
+        }
+        ctx.manager_uid = Some(euid);
+        ctx.manager = Some(crate::node::NodeRef::new(node, 1, 1));
+        self.inner.lock().is_manager = true;
+        Ok(())
+    }
+
+    /// Picks the thread that will service the next piece of work.
+    ///
+    /// Takes from the front of the rotation; the thread re-enters at
+    /// the back via [`thread_ready`](Self::thread_ready) once it is
+    /// done, giving round-robin fairness (see the `ready_threads` field
+    /// doc for the LIFO trade-off this departs from).
+    pub(crate) fn select_ready_thread(self: &Arc<Self>) -> Option<Arc<Thread>> {
+        let mut inner = self.lock_inner();
+        if inner.ready_threads.is_empty() {
+            return None;
+        }
+        let thread = inner.ready_threads.remove(0);
+        drop(inner);
+        thread.lock_inner().deliveries += 1;
+        Some(thread)
+    }
+
+    /// Returns `thread` to the ready rotation, at the back.
+    ///
+    /// Also used for failed deliveries, which therefore keep their
+    /// back placement.
+    pub(crate) fn thread_ready(self: &Arc<Self>, thread: Arc<Thread>) {
+        let mut inner = self.lock_inner();
+        let _ = inner.ready_threads.try_reserve(1);
+        inner.ready_threads.push(thread);
+    }
+
+    /// Prints this process's state, including its full handle table, for
+    /// debugfs.
+    ///
+    /// The handle walk takes only `node_refs` -- first in the lock order,
+    /// never nested inside `inner` -- and reads the counts off each ref
+    /// rather than calling into the node (which would need the owner's
+    /// lock and invert the order).
+    pub(crate) fn debug_print(&self, m: &mut kernel::seq_file::SeqFile) {
+        {
+            let inner = self.lock_inner();
+            kernel::seq_print!(
+                m,
+                "threads: {} ready: {} frozen: {} delivered_deaths: {} protocol_violations: {}
+",
+                inner.threads.len(),
+                inner.ready_threads.len(),
+                inner.is_frozen,
+                inner.delivered_deaths.len(),
+                inner.protocol_violations,
+            );
+            for thread in inner.threads.values() {
+                let deliveries = thread.lock_inner().deliveries;
+                kernel::seq_print!(m, "  thread {}: deliveries {}
+", thread.id, deliveries);
+            }
+        }
+        let refs = self.lock_node_refs();
+        for (handle, nref) in refs.by_handle.iter() {
+            kernel::seq_print!(
+                m,
+                "  ref {}: node {} strong {} weak {}{}
+",
+                handle,
+                nref.node.debug_id,
+                nref.strong_count,
+                nref.weak_count,
+                if nref.death_registered { " death" } else { "" },
+            );
+        }
+    }
+
+    /// Records the delivery of a death notification awaiting its ack.
+    pub(crate) fn death_delivered(self: &Arc<Self>, cookie: u64) {
+        let mut inner = self.lock_inner();
+        let _ = inner.delivered_deaths.try_reserve(1);
+        inner.delivered_deaths.push(cookie);
+        if inner.delivered_deaths.len() > DELIVERED_DEATHS_SOFT_CAP
+            && !inner.delivered_deaths_warned
+        {
+            inner.delivered_deaths_warned = true;
+            let len = inner.delivered_deaths.len();
+            drop(inner);
+            pr_warn!(
+                "binder: pid {} has {} unacknowledged death notifications
+",
+                current_tid(),
+                len,
+            );
+        }
+    }
+
+    /// Handles `BC_DEAD_BINDER_DONE` for `cookie`.
+    pub(crate) fn dead_binder_done(self: &Arc<Self>, cookie: u64) {
+        let mut inner = self.lock_inner();
+        if let Some(pos) = inner.delivered_deaths.iter().position(|&c| c == cookie) {
+            inner.delivered_deaths.remove(pos);
+        }
+        if inner.delivered_deaths.len() <= DELIVERED_DEATHS_SOFT_CAP {
+            inner.delivered_deaths_warned = false;
+        }
+    }
+
+    /// Handles `BINDER_GET_NODE_INFO_FOR_REF`.
+    ///
+    /// Manager-only, and the permission check runs before the lookup so
+    /// non-managers learn nothing about the handle space. The ref is
+    /// accepted whether it holds strong references or only weak ones:
+    /// a weak-only ref resolves too, and the reported counts are the
+    /// node's real totals (so `strong_count` is zero only when nobody
+    /// holds a strong ref anywhere).
+    pub(crate) fn get_node_info_from_ref(
+        self: &Arc<Self>,
+        info: &mut BinderNodeInfoForRef,
+    ) -> Result {
+        if !self.lock_inner().is_manager {
+            return Err(EPERM);
+        }
+        if info.strong_count != 0
+            || info.weak_count != 0
+            || info.reserved1 != 0
+            || info.reserved2 != 0
+            || info.reserved3 != 0
+        {
+            return Err(EINVAL);
+        }
+        let refs = self.lock_node_refs();
+        let nref = refs.by_handle.get(&info.handle).ok_or(EINVAL)?;
+        nref.populate_counts(&mut info.strong_count, &mut info.weak_count);
+        Ok(())
+    }
+
+    /// Registers `watcher` for freeze notifications on this process.
+    ///
+    /// The current state is queued immediately, as in the C driver, so
+    /// the watcher never has to guess the initial value.
+    pub(crate) fn add_freeze_listener(self: &Arc<Self>, watcher: Arc<Process>, cookie: u64) {
+        let mut inner = self.lock_inner();
+        let frozen = inner.is_frozen;
+        inner.freeze_listeners.push(FreezeListener {
+            watcher: watcher.clone(),
+            cookie,
+            acked: false,
+            resend: false,
+        });
+        drop(inner);
+        watcher.lock_inner().pending_frozen.push((cookie, frozen));
+    }
+
+    /// Removes the listener identified by `cookie` that `watcher`
+    /// registered on this process, acking with the clear-done command.
+    pub(crate) fn clear_freeze_listener(self: &Arc<Self>, watcher: &Arc<Process>, cookie: u64) -> Result {
+        let mut inner = self.lock_inner();
+        let before = inner.freeze_listeners.len();
+        inner
+            .freeze_listeners
+            .retain(|l| !(l.cookie == cookie && Arc::ptr_eq(&l.watcher, watcher)));
+        if inner.freeze_listeners.len() == before {
+            return Err(EINVAL);
+        }
+        drop(inner);
+        watcher.lock_inner().pending_clear_done.push(cookie);
+        Ok(())
+    }
+
+    /// Acknowledges a delivered freeze notification; if the state changed
+    /// while the ack was outstanding, the latest state is requeued
+    /// (coalesced: intermediate flips are not replayed).
+    pub(crate) fn freeze_notification_done(self: &Arc<Self>, cookie: u64) {
+        // The ack arrives at the watcher; find the listener on whichever
+        // process it watches. Listeners are identified by (watcher,
+        // cookie), and the watcher is us.
+        // Scan our own record: the C driver tracks this on the ref; here
+        // the owner holds the listener, so walk the context's processes.
+        let processes: Vec<Arc<Process>> = {
+            let ctx = self.ctx.inner.lock();
+            ctx.processes.iter().map(|(_, p)| p.clone()).collect()
+        };
+        for proc in processes {
+            // Finish with the owner's lock before requeueing to our own
+            // pending list: record what to do while iterating, act after
+            // the guard drops.
+            let mut requeue = None;
+            let mut found = false;
+            {
+                let mut inner = proc.lock_inner();
+                let frozen = inner.is_frozen;
+                for l in inner.freeze_listeners.iter_mut() {
+                    if l.cookie == cookie && Arc::ptr_eq(&l.watcher, self) {
+                        found = true;
+                        l.acked = true;
+                        if core::mem::replace(&mut l.resend, false) {
+                            l.acked = false;
+                            requeue = Some(frozen);
+                        }
+                        break;
+                    }
+                }
+            }
+            if let Some(frozen) = requeue {
+                self.lock_inner().pending_frozen.push((cookie, frozen));
+            }
+            if found {
+                return;
+            }
+        }
+    }
+
+    /// Applies a `BINDER_FREEZE` request to this process, notifying every
+    /// registered listener of the new state.
+    pub(crate) fn set_frozen(self: &Arc<Self>, frozen: bool) {
+        let deliveries: Vec<(Arc<Process>, u64)> = {
+            let mut inner = self.lock_inner();
+            inner.is_frozen = frozen;
+            inner
+                .freeze_listeners
+                .iter_mut()
+                .filter_map(|l| {
+                    if l.acked {
+                        l.acked = false;
+                        Some((l.watcher.clone(), l.cookie))
+                    } else {
+                        // An un-acked notification is outstanding; mark
+                        // for resend so the ack requeues the new state.
+                        l.resend = true;
+                        None
+                    }
+                })
+                .collect()
+        };
+        for (watcher, cookie) in deliveries {
+            watcher.lock_inner().pending_frozen.push((cookie, frozen));
+        }
+    }
+
+    /// The ioctl handler, shared by the native and compat entry points.
+    ///
+    /// Whether the caller is a compat task is derived from
+    /// `in_compat_syscall` rather than from which entry point ran, so
+    /// indirect invocations (e.g. io_uring) are classified correctly.
+    pub(crate) fn ioctl(this: ArcBorrow<'_, Process>, _file: &File, cmd: u32, arg: usize) -> Result {
+        let this = Arc::from(this);
+        // SAFETY: Just reads the current task's compat flag.
+        let is_compat = unsafe { bindings::in_compat_syscall() };
+        // SAFETY: `arg` is a userspace pointer for all commands handled
+        // here; size checks happen at copy time.
+        let data = unsafe { UserSlicePtr::new(arg as _, ioctl_size(cmd)) };
+        match cmd {
+            BINDER_WRITE_READ => this.read_write(data, is_compat),
+            BINDER_VERSION => this.version(data),
+            BINDER_SET_CONTEXT_MGR => this.set_as_manager(),
+            BINDER_SET_MAX_THREADS => {
+                let max = data.reader().read::<u32>()?;
+                this.inner.with_lock(|inner| inner.max_threads = max);
+                Ok(())
+            }
+            BINDER_ENABLE_ONEWAY_SPAM_DETECTION => {
+                let enable = data.reader().read::<u32>()? != 0;
+                this.inner
+                    .with_lock(|inner| inner.oneway_spam_detection_enabled = enable);
+                Ok(())
+            }
+            BINDER_GET_NODE_INFO_FOR_REF => {
+                let (mut reader, mut writer) = data.reader_writer();
+                let mut info = reader.read::<BinderNodeInfoForRef>()?;
+                this.get_node_info_from_ref(&mut info)?;
+                writer.write(&info)
+            }
+            BINDER_FREEZE => {
+                let info = data.reader().read::<BinderFreezeInfo>()?;
+                // Freeze every instance the thread group has open, as
+                // the C driver does for its per-pid proc list.
+                let targets = this.ctx.processes_by_tgid(info.pid as i32);
+                if targets.is_empty() {
+                    return Err(EINVAL);
+                }
+                for target in targets {
+                    target.set_frozen(info.enable != 0);
+                }
+                Ok(())
+            }
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// A freeze-notification registration on the process it watches.
+pub(crate) struct FreezeListener {
+    /// The process that asked to be notified.
+    pub(crate) watcher: Arc<Process>,
+    /// Userspace's identifier for this registration.
+    pub(crate) cookie: u64,
+    /// Whether the last delivered notification has been acked. A new
+    /// notification is only queued while acked; otherwise `resend` is
+    /// set and the ack requeues the latest state.
+    pub(crate) acked: bool,
+    pub(crate) resend: bool,
+}
+
+/// Returns the size in bytes of the ioctl payload.
+fn ioctl_size(cmd: u32) -> usize {
+    ((cmd >> bindings::_IOC_SIZESHIFT) & bindings::_IOC_SIZEMASK) as usize
+}
+
+/// Returns the calling thread's id.
+fn current_tid() -> i32 {
+    // SAFETY: `current` is always valid.
+    unsafe { bindings::task_pid_nr(bindings::get_current()) as i32 }
+}
+
+/// Returns the calling thread group's id (the userspace "pid").
+fn current_tgid() -> i32 {
+    // SAFETY: `current` is always valid.
+    unsafe { bindings::task_tgid_nr(bindings::get_current()) as i32 }
+}
@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Binder -- the Android IPC mechanism.
+
+use kernel::{
+    c_str,
+    miscdevice::{MiscDevice, MiscDeviceOptions, MiscDeviceRegistration},
+    prelude::*,
+};
+
+mod allocation;
+mod context;
+mod defs;
+mod lock_order;
+mod node;
+mod process;
+mod thread;
+mod transaction;
+
+use crate::{context::Context, process::Process};
+use kernel::sync::Arc;
+
+/// The binder driver module state.
+pub(crate) struct BinderModule {
+    _reg: core::pin::Pin<alloc::boxed::Box<MiscDeviceRegistration<BinderDevice>>>,
+}
+
+/// The `/dev/rust_binder` misc device.
+pub(crate) struct BinderDevice;
+
+impl MiscDevice for BinderDevice {
+    type Ptr = Arc<Process>;
+    type RegData = Arc<Context>;
+
+    fn open_with_data(ctx: &Arc<Context>, file: &kernel::file::File) -> Result<Arc<Process>> {
+        Process::open(ctx.clone(), file)
+    }
+
+    fn release(process: Arc<Process>, _file: &kernel::file::File) {
+        process.cleanup();
+    }
+
+    const HAS_IOCTL: bool = true;
+
+    fn ioctl(
+        process: kernel::sync::ArcBorrow<'_, Process>,
+        file: &kernel::file::File,
+        cmd: u32,
+        arg: usize,
+    ) -> Result<isize> {
+        Process::ioctl(process, file, cmd, arg)?;
+        Ok(0)
+    }
+}
+
+impl BinderModule {
+    /// Initialises the binder device.
+    pub(crate) fn init() -> Result<Self> {
+        let ctx = Context::new()?;
+        let opts = MiscDeviceOptions {
+            name: c_str!("rust_binder"),
+        };
+        let reg = MiscDeviceRegistration::<BinderDevice>::register_with_data(opts, ctx)?;
+        Ok(Self { _reg: reg })
+    }
+}
@@ -0,0 +1,526 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Binder threads: the per-thread state of a process's binder worker
+//! threads.
+
+use crate::{
+    allocation::Allocation,
+    defs::*,
+    lock_order::{self, TrackedGuard},
+    process::Process,
+    transaction::Transaction,
+};
+use kernel::pages::Pages;
+use kernel::PAGE_SIZE;
+use kernel::{
+    c_str,
+    list::{ListArcSafe, ListItem, ListLinks},
+    prelude::*,
+    sync::{Arc, CondVar, SpinLock},
+    user_ptr::{UserSlicePtrReader, UserSlicePtrWriter},
+};
+use core::sync::atomic::AtomicBool;
+
+/// State guarded by the thread's spinlock.
+pub(crate) struct InnerThread {
+    /// Whether this thread is registered as a looper.
+    pub(crate) is_looper: bool,
+    /// A pending error code to deliver to userspace as `BR_ERROR`.
+    pub(crate) return_error: u32,
+    /// Transactions this thread has handled, for the fairness
+    /// accounting in `debug_print`.
+    pub(crate) deliveries: u64,
+    /// Transactions queued for delivery to this thread.
+    pub(crate) pending_transactions: alloc::vec::Vec<TransactionWork>,
+    /// Deliver `BR_ONEWAY_SPAM_SUSPECT` on the next read: this thread's
+    /// last oneway send flooded the target node's async space.
+    pub(crate) oneway_spam_suspect: bool,
+    /// Completions pending delivery, run-length encoded as
+    /// (pending_frozen, count): N consecutive completions of the same
+    /// flavour coalesce into one entry instead of N work items, which is
+    /// what keeps the queue short when completions pile up under load.
+    /// Only *adjacent* same-flavour completions merge -- a different
+    /// flavour (or, once other work shares this queue, any interleaved
+    /// work) starts a new entry, so userspace observes the original
+    /// order and the pending-frozen variant never folds into a plain
+    /// complete.
+    pub(crate) pending_completions: alloc::vec::Vec<(bool, u32)>,
+}
+
+/// A binder worker thread.
+pub(crate) struct Thread {
+    /// The process this thread belongs to.
+    pub(crate) process: Arc<Process>,
+    /// The thread id reported by userspace.
+    pub(crate) id: i32,
+    pub(crate) inner: SpinLock<InnerThread>,
+    /// Used to wait for work.
+    pub(crate) work_condvar: CondVar,
+    pub(crate) links: ListLinks,
+    pub(crate) list_tracker: AtomicBool,
+}
+
+impl ListArcSafe for Thread {
+    fn tracker(&self) -> &AtomicBool {
+        &self.list_tracker
+    }
+}
+
+// SAFETY: `links` is used only by the process's thread list, and the
+// offset names that same field.
+unsafe impl ListItem for Thread {
+    const LINKS_OFFSET: usize = core::mem::offset_of!(Thread, links);
+
+    fn links(&self) -> &ListLinks {
+        &self.links
+    }
+}
+
+impl Thread {
+    pub(crate) fn new(process: Arc<Process>, id: i32) -> Result<Arc<Self>> {
+        let thread = Arc::try_new(Self {
+            process,
+            id,
+            // SAFETY: Initialised below before the arc is shared.
+            inner: unsafe {
+                SpinLock::new_uninit(InnerThread {
+                    is_looper: false,
+                    return_error: 0,
+                    deliveries: 0,
+                    pending_transactions: alloc::vec::Vec::new(),
+                    oneway_spam_suspect: false,
+                    pending_completions: alloc::vec::Vec::new(),
+                })
+            },
+            // SAFETY: Initialised below before the arc is shared.
+            work_condvar: unsafe { CondVar::new_uninit() },
+            links: ListLinks::new(),
+            list_tracker: AtomicBool::new(false),
+        })?;
+        // SAFETY: Both objects are initialised exactly once and the data
+        // behind the `Arc` does not move.
+        unsafe {
+            thread.inner.init_once(c_str!("Thread::inner"));
+            thread.work_condvar.init_once(c_str!("Thread::work_condvar"));
+        }
+        Ok(thread)
+    }
+
+    /// Acquires the thread's `inner` with lock-order tracking.
+    pub(crate) fn lock_inner(&self) -> TrackedGuard<kernel::sync::Guard<'_, InnerThread, kernel::sync::lock::spinlock::SpinLockBackend>> {
+        lock_order::acquire(lock_order::RANK_THREAD_INNER);
+        TrackedGuard::new(self.inner.lock(), lock_order::RANK_THREAD_INNER)
+    }
+
+    /// Handles one buffer of `BC_*` commands from userspace.
+    pub(crate) fn write(self: &Arc<Self>, reader: &mut UserSlicePtrReader) -> Result {
+        while !reader.is_empty() {
+            let cmd = reader.read::<u32>()?;
+            match cmd {
+                BC_ENTER_LOOPER | BC_REGISTER_LOOPER => {
+                    self.inner.lock().is_looper = true;
+                }
+                BC_TRANSACTION => {
+                    let tr = reader.read::<BinderTransactionData>()?;
+                    self.transaction(&tr)?;
+                }
+                BC_REQUEST_DEATH_NOTIFICATION => {
+                    let hc = reader.read::<BinderHandleCookie>()?;
+                    let mut refs = self.process.lock_node_refs();
+                    let nref = refs.by_handle.get_mut(&{ hc.handle }).ok_or(EINVAL)?;
+                    if nref.death_registered {
+                        return Err(EINVAL);
+                    }
+                    nref.death_registered = true;
+                    nref.death_cookie = Some(hc.cookie);
+                }
+                BC_CLEAR_DEATH_NOTIFICATION => {
+                    let hc = reader.read::<BinderHandleCookie>()?;
+                    let mut refs = self.process.lock_node_refs();
+                    let nref = refs.by_handle.get_mut(&{ hc.handle }).ok_or(EINVAL)?;
+                    nref.death_registered = false;
+                    nref.death_cookie = None;
+                }
+                BC_DEAD_BINDER_DONE => {
+                    let cookie = reader.read::<u64>()?;
+                    self.process.dead_binder_done(cookie);
+                }
+                BC_INCREFS_DONE | BC_ACQUIRE_DONE => {
+                    let ptr = reader.read::<u64>()?;
+                    let _cookie = reader.read::<u64>()?;
+                    let node = self.process.nodes.lock().get(&ptr).cloned();
+                    let ok = match node {
+                        Some(node) => node.inc_ref_done_locked(),
+                        None => false,
+                    };
+                    if !ok {
+                        // Userspace acked a ref it never held. Tell it
+                        // (BR_ERROR) and count it; the log line is
+                        // user-triggerable by construction, so warn
+                        // rather than err and leave rate limiting to the
+                        // console.
+                        kernel::pr_warn!(
+                            "binder: spurious {} from thread {}
+",
+                            if cmd == BC_ACQUIRE_DONE {
+                                "BC_ACQUIRE_DONE"
+                            } else {
+                                "BC_INCREFS_DONE"
+                            },
+                            self.id,
+                        );
+                        self.process.lock_inner().protocol_violations += 1;
+                        self.lock_inner().return_error = EINVAL.to_errno() as u32;
+                    }
+                }
+                BC_REQUEST_FREEZE_NOTIFICATION => {
+                    let hc = reader.read::<BinderHandleCookie>()?;
+                    let owner = {
+                        let refs = self.process.lock_node_refs();
+                        let nref = refs.by_handle.get(&{ hc.handle }).ok_or(EINVAL)?;
+                        nref.node.owner.clone()
+                    };
+                    owner.add_freeze_listener(self.process.clone(), hc.cookie);
+                }
+                BC_CLEAR_FREEZE_NOTIFICATION => {
+                    let hc = reader.read::<BinderHandleCookie>()?;
+                    let owner = {
+                        let refs = self.process.lock_node_refs();
+                        let nref = refs.by_handle.get(&{ hc.handle }).ok_or(EINVAL)?;
+                        nref.node.owner.clone()
+                    };
+                    owner.clear_freeze_listener(&self.process, hc.cookie)?;
+                }
+                BC_FREEZE_NOTIFICATION_DONE => {
+                    let cookie = reader.read::<u64>()?;
+                    self.process.freeze_notification_done(cookie);
+                }
+                _ => return Err(EINVAL),
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues `work` for delivery by this thread and wakes it.
+    pub(crate) fn push_transaction(&self, work: TransactionWork) {
+        let mut inner = self.lock_inner();
+        let _ = inner.pending_transactions.try_reserve(1);
+        inner.pending_transactions.push(work);
+        drop(inner);
+        self.work_condvar.notify_one();
+    }
+
+    /// Handles a `BC_TRANSACTION` from this thread.
+    fn transaction(self: &Arc<Self>, tr: &BinderTransactionData) -> Result {
+        if tr.offsets_size % 8 != 0 {
+            return Err(EINVAL);
+        }
+        // Resolve the target node: handle 0 is the context manager.
+        let node = if tr.target == 0 {
+            let ctx = self.process.ctx.inner.lock();
+            ctx.manager.as_ref().ok_or(ENOENT)?.node.clone()
+        } else {
+            let refs = self.process.lock_node_refs();
+            refs.by_handle
+                .get(&(tr.target as u32))
+                .ok_or(EINVAL)?
+                .node
+                .clone()
+        };
+        let to = node.owner.clone();
+
+        // Buffer layout: [data][pad][offsets][pad][secctx?].
+        let data_size = tr.data_size as usize;
+        let offsets_size = tr.offsets_size as usize;
+        let offsets_off = Transaction::offsets_start(0, data_size)?;
+        let mut total = offsets_off.checked_add(offsets_size).ok_or(EINVAL)?;
+        let secctx_off = Transaction::offsets_start(total, 0)?;
+        if node.flags & FLAT_BINDER_FLAG_TXN_SECURITY_CTX != 0 {
+            total = secctx_off.checked_add(SECCTX_MAX).ok_or(EINVAL)?;
+        }
+
+        // Back the buffer with freshly-allocated pages, built into the
+        // refcounted slice in a single allocation.
+        let npages = total.div_ceil(PAGE_SIZE).max(1);
+        let mut pages = alloc::vec::Vec::new();
+        pages.try_reserve_exact(npages).map_err(|_| ENOMEM)?;
+        for _ in 0..npages {
+            pages.push(Pages::<0>::new()?);
+        }
+        let payload = Allocation {
+            offset: 0,
+            size: total,
+            pages: Arc::from_iter_fallible(pages.into_iter(), npages)?,
+            info: None,
+        };
+
+        // Copy the two user regions in.
+        if data_size > 0 {
+            // SAFETY: Userspace supplied the pointer; every access goes
+            // through the checked copy helpers.
+            let mut r = unsafe {
+                kernel::user_ptr::UserSlicePtr::new(tr.data_buffer as _, data_size)
+            }
+            .reader();
+            payload.copy_into(&mut r, 0, data_size)?;
+        }
+        if offsets_size > 0 {
+            // SAFETY: As above.
+            let mut r = unsafe {
+                kernel::user_ptr::UserSlicePtr::new(tr.data_offsets as _, offsets_size)
+            }
+            .reader();
+            payload.copy_into(&mut r, offsets_off, offsets_size)?;
+        }
+
+        let txn = Transaction::new(
+            self.clone(),
+            to.clone(),
+            Some(node),
+            None,
+            tr.code,
+            tr.flags,
+            data_size,
+            offsets_size,
+        )?;
+        txn.write_secctx(&payload, secctx_off)?;
+        txn.submit(total)?;
+
+        let work = TransactionWork { txn, payload };
+        match to.select_ready_thread() {
+            Some(thread) => thread.push_transaction(work),
+            None => {
+                let mut inner = to.lock_inner();
+                let _ = inner.pending_work.try_reserve(1);
+                inner.pending_work.push(work);
+                drop(inner);
+                to.notify_all_threads();
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues the completion for an accepted send; `pending_frozen` is
+    /// whether the oneway target accepted it while frozen.
+    ///
+    /// Coalesces with the queue tail when the flavour matches: the new
+    /// completion simply bumps the tail's count.
+    pub(crate) fn push_completion(&self, pending_frozen: bool) {
+        let mut inner = self.lock_inner();
+        if let Some(tail) = inner.pending_completions.last_mut() {
+            if tail.0 == pending_frozen {
+                tail.1 += 1;
+                return;
+            }
+        }
+        let _ = inner.pending_completions.try_reserve(1);
+        inner.pending_completions.push((pending_frozen, 1));
+    }
+
+    /// Marks this thread's next read to include `BR_ONEWAY_SPAM_SUSPECT`.
+    pub(crate) fn set_oneway_spam_suspect(&self) {
+        self.lock_inner().oneway_spam_suspect = true;
+    }
+
+    /// Takes the next transaction available to this thread, if any.
+    fn next_transaction(self: &Arc<Self>) -> Option<TransactionWork> {
+        {
+            let mut inner = self.lock_inner();
+            if !inner.pending_transactions.is_empty() {
+                return Some(inner.pending_transactions.remove(0));
+            }
+        }
+        let mut pinner = self.process.lock_inner();
+        if pinner.pending_work.is_empty() {
+            None
+        } else {
+            Some(pinner.pending_work.remove(0))
+        }
+    }
+
+    /// Writes one transaction delivery, or requeues it if the buffer is
+    /// too small.
+    fn deliver_transaction(
+        self: &Arc<Self>,
+        writer: &mut UserSlicePtrWriter,
+        work: TransactionWork,
+    ) -> Result<bool> {
+        let needed = 4 + core::mem::size_of::<BinderTransactionData>();
+        if writer.len() < needed {
+            let mut inner = self.lock_inner();
+            let _ = inner.pending_transactions.try_reserve(1);
+            inner.pending_transactions.insert(0, work);
+            return Ok(false);
+        }
+        let txn = &work.txn;
+        writer.write(&BR_TRANSACTION)?;
+        writer.write(&BinderTransactionData {
+            target: txn.target_node.as_ref().map_or(0, |n| n.ptr),
+            cookie: txn.target_node.as_ref().map_or(0, |n| n.cookie),
+            code: txn.code,
+            flags: txn.flags,
+            sender_pid: txn.from.id,
+            sender_euid: 0,
+            data_size: txn.data_size as u64,
+            offsets_size: txn.offsets_size as u64,
+            // The recipient-side buffer mapping is not implemented in
+            // this tree yet; the payload lives kernel-side in
+            // `work.payload` until then.
+            data_buffer: 0,
+            data_offsets: 0,
+        })?;
+        self.lock_inner().deliveries += 1;
+        // Dropping the payload runs the clear-on-free zeroing when the
+        // transaction demanded it.
+        drop(work);
+        Ok(true)
+    }
+
+    /// Fills the read buffer with `BR_*` work for userspace.
+    pub(crate) fn read(self: &Arc<Self>, writer: &mut UserSlicePtrWriter, wait: bool) -> Result {
+        // Take the pending error only when it can actually be written;
+        // a short read buffer must leave it queued for the next read,
+        // not silently discard the protocol-violation report.
+        let error = if writer.len() >= 8 {
+            core::mem::replace(&mut self.lock_inner().return_error, 0)
+        } else {
+            0
+        };
+        if error != 0 {
+            writer.write(&BR_ERROR)?;
+            writer.write(&error)?;
+        }
+        let spam_suspect =
+            core::mem::replace(&mut self.lock_inner().oneway_spam_suspect, false);
+        if spam_suspect {
+            writer.write(&BR_ONEWAY_SPAM_SUSPECT)?;
+        }
+        'completions: loop {
+            let completion = {
+                let mut inner = self.lock_inner();
+                if inner.pending_completions.is_empty() {
+                    None
+                } else {
+                    Some(inner.pending_completions.remove(0))
+                }
+            };
+            let Some((pending_frozen, count)) = completion else {
+                break;
+            };
+            let word = if pending_frozen {
+                BR_TRANSACTION_PENDING_FROZEN
+            } else {
+                BR_TRANSACTION_COMPLETE
+            };
+            for delivered in 0..count {
+                if writer.len() < core::mem::size_of::<u32>() {
+                    // Out of read buffer mid-run: requeue the rest at
+                    // the front so nothing is lost and order holds.
+                    let remaining = count - delivered;
+                    let mut inner = self.lock_inner();
+                    let _ = inner.pending_completions.try_reserve(1);
+                    inner.pending_completions.insert(0, (pending_frozen, remaining));
+                    break 'completions;
+                }
+                writer.write(&word)?;
+            }
+        }
+        loop {
+            // Check space before taking the entry: a full buffer must
+            // leave the notification queued (the listener's ack latch
+            // depends on it eventually arriving), and delivery is FIFO
+            // so a freeze/unfreeze pair arrives in order.
+            if writer.len() < 4 + core::mem::size_of::<BinderFrozenStateInfo>() {
+                break;
+            }
+            let delivery = {
+                let mut inner = self.process.lock_inner();
+                if inner.pending_frozen.is_empty() {
+                    None
+                } else {
+                    Some(inner.pending_frozen.remove(0))
+                }
+            };
+            let Some((cookie, frozen)) = delivery else { break };
+            writer.write(&BR_FROZEN_BINDER)?;
+            writer.write(&BinderFrozenStateInfo {
+                cookie,
+                is_frozen: frozen as u32,
+                reserved: 0,
+            })?;
+        }
+        loop {
+            if writer.len() < 4 + 8 {
+                break;
+            }
+            let cookie = {
+                let mut inner = self.process.lock_inner();
+                if inner.pending_clear_done.is_empty() {
+                    None
+                } else {
+                    Some(inner.pending_clear_done.remove(0))
+                }
+            };
+            let Some(cookie) = cookie else { break };
+            writer.write(&BR_CLEAR_FREEZE_NOTIFICATION_DONE)?;
+            writer.write(&cookie)?;
+        }
+        loop {
+            if writer.len() < 4 + 8 {
+                break;
+            }
+            let cookie = {
+                let mut inner = self.process.lock_inner();
+                if inner.pending_deaths.is_empty() {
+                    None
+                } else {
+                    Some(inner.pending_deaths.remove(0))
+                }
+            };
+            let Some(cookie) = cookie else { break };
+            writer.write(&BR_DEAD_BINDER)?;
+            writer.write(&cookie)?;
+        }
+
+        // Transactions: deliver what is queued; if nothing is and the
+        // caller asked to block, park in the ready rotation and wait on
+        // the work condvar until something arrives or a signal ends the
+        // wait.
+        let mut delivered = false;
+        loop {
+            if let Some(work) = self.next_transaction() {
+                if !self.deliver_transaction(writer, work)? {
+                    break;
+                }
+                delivered = true;
+                continue;
+            }
+            // Block only while nothing has been handed to userspace yet;
+            // once something was delivered, return it rather than
+            // napping on a non-empty reply.
+            if !wait || delivered {
+                break;
+            }
+            self.process.thread_ready(self.clone());
+            let mut inner = self.inner.lock();
+            let interrupted = self.work_condvar.wait_interruptible(&mut inner);
+            drop(inner);
+            // Whether woken or interrupted, leave the rotation; a
+            // dispatcher that already popped us is unaffected.
+            self.process
+                .lock_inner()
+                .ready_threads
+                .retain(|t| !Arc::ptr_eq(t, self));
+            if interrupted {
+                return Err(ERESTARTSYS);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A transaction queued for delivery, with its kernel-side payload.
+pub(crate) struct TransactionWork {
+    pub(crate) txn: Arc<Transaction>,
+    pub(crate) payload: Allocation,
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Binder transactions.
+
+use crate::{
+    allocation::Allocation,
+    defs::{FLAT_BINDER_FLAG_TXN_SECURITY_CTX, MAX_TRANSACTION_STACK_DEPTH},
+    node::Node,
+    process::Process,
+    thread::Thread,
+};
+use kernel::types::ScopeGuard;
+use kernel::{bindings, prelude::*, sync::Arc};
+
+/// `TF_ONE_WAY`: the transaction expects no reply.
+pub(crate) const TF_ONE_WAY: u32 = bindings::transaction_flags_TF_ONE_WAY;
+
+/// A transaction in flight between two processes.
+pub(crate) struct Transaction {
+    /// The thread that initiated the transaction.
+    pub(crate) from: Arc<Thread>,
+    /// The process the transaction targets.
+    pub(crate) to: Arc<Process>,
+    /// The node the transaction targets, unless it is a reply.
+    pub(crate) target_node: Option<Arc<Node>>,
+    /// The transaction this one stacks on top of, for nested synchronous
+    /// calls.
+    pub(crate) stack_next: Option<Arc<Transaction>>,
+    /// Depth of this transaction in the stack: 0 for the outermost call,
+    /// parent depth + 1 otherwise. Stored rather than recomputed so the
+    /// depth check is O(1) and needs no lock (the field is immutable).
+    pub(crate) depth: u32,
+    /// Transaction flags (`TF_*`).
+    pub(crate) flags: u32,
+    pub(crate) code: u32,
+    /// Payload geometry, echoed in the delivery header.
+    pub(crate) data_size: usize,
+    pub(crate) offsets_size: usize,
+    /// The sender's security id, snapped at submit time.
+    ///
+    /// The *string* form is materialised lazily (only for targets that
+    /// asked for it), but the identity must be the sender's at send
+    /// time, not whatever the delivering context looks like -- so the
+    /// cheap secid capture is eager even though the expensive
+    /// `security_secid_to_secctx` is not.
+    pub(crate) sender_secid: u32,
+}
+
+impl Transaction {
+    pub(crate) fn new(
+        from: Arc<Thread>,
+        to: Arc<Process>,
+        target_node: Option<Arc<Node>>,
+        stack_next: Option<Arc<Transaction>>,
+        code: u32,
+        flags: u32,
+        data_size: usize,
+        offsets_size: usize,
+    ) -> Result<Arc<Self>> {
+        let depth = match &stack_next {
+            Some(parent) => {
+                if parent.depth >= MAX_TRANSACTION_STACK_DEPTH - 1 {
+                    // The sender sees BR_FAILED_REPLY, like other submit
+                    // failures; the chain below the limit is unaffected.
+                    return Err(E2BIG);
+                }
+                parent.depth + 1
+            }
+            None => 0,
+        };
+        let mut sender_secid = 0;
+        // SAFETY: Reads the current task's credentials; no context
+        // requirements.
+        unsafe {
+            kernel::bindings::security_cred_getsecid(
+                (*kernel::bindings::get_current()).cred,
+                &mut sender_secid,
+            )
+        };
+        Arc::try_new(Self {
+            from,
+            to,
+            target_node,
+            stack_next,
+            flags,
+            code,
+            depth,
+            data_size,
+            offsets_size,
+            sender_secid,
+        })
+        .map_err(Error::from)
+    }
+
+    /// Computes where the offsets region starts for a payload at
+    /// `data_address` of `data_size` bytes.
+    ///
+    /// Overflow (a hostile size) yields `EINVAL` instead of a wrapped
+    /// address.
+    pub(crate) fn offsets_start(data_address: usize, data_size: usize) -> Result<usize> {
+        kernel::align::ptr_align_checked(data_address, data_size).ok_or(EINVAL)
+    }
+
+    /// Writes the sender's security context into the allocation at
+    /// `offset`, if the target node asked for one.
+    ///
+    /// The string is fetched here -- during buffer construction, not at
+    /// submit -- and only when `FLAT_BINDER_FLAG_TXN_SECURITY_CTX` is
+    /// set on the target, so the common no-secctx case performs no LSM
+    /// string work at all. The context is released on every exit path
+    /// via a scope guard, covering mid-copy failures that would
+    /// otherwise leak it.
+    ///
+    /// Returns the number of bytes written (0 when not requested).
+    pub(crate) fn write_secctx(&self, alloc: &Allocation, offset: usize) -> Result<usize> {
+        let Some(node) = &self.target_node else {
+            return Ok(0);
+        };
+        if node.flags & FLAT_BINDER_FLAG_TXN_SECURITY_CTX == 0 {
+            return Ok(0);
+        }
+        let mut ctx: *mut core::ffi::c_char = core::ptr::null_mut();
+        let mut len: u32 = 0;
+        // SAFETY: Out-parameters are local; the secid was captured at
+        // submit time.
+        let ret = unsafe {
+            kernel::bindings::security_secid_to_secctx(self.sender_secid, &mut ctx, &mut len)
+        };
+        if ret != 0 {
+            // A node that demanded a secctx must not receive a
+            // transaction without one.
+            return Err(Error::from_errno(ret));
+        }
+        let _release = ScopeGuard::new(|| {
+            // SAFETY: `ctx`/`len` came from a successful
+            // `security_secid_to_secctx` above.
+            unsafe { kernel::bindings::security_release_secctx(ctx, len) };
+        });
+        // SAFETY: The LSM returned `len` valid bytes at `ctx`.
+        let bytes = unsafe { core::slice::from_raw_parts(ctx as *const u8, len as usize) };
+        alloc.write_kernel(bytes, offset)?;
+        alloc.write_kernel(&[0u8], offset + bytes.len())?;
+        Ok(bytes.len() + 1)
+    }
+
+    /// Returns whether this is a oneway (asynchronous) transaction.
+    pub(crate) fn is_oneway(&self) -> bool {
+        self.flags & TF_ONE_WAY != 0
+    }
+
+    /// Submits the transaction towards its target, updating the context
+    /// statistics at this single choke point.
+    pub(crate) fn submit(self: &Arc<Self>, size: usize) -> Result {
+        let ctx = &self.from.process.ctx;
+        ctx.stats.inc_sent(self.is_oneway());
+        if self.to.inner.lock().is_dead {
+            ctx.stats.inc_failed();
+            return Err(ESRCH);
+        }
+        if !self.is_oneway() {
+            self.from.push_completion(false);
+        }
+        if self.is_oneway() {
+            // Hold the target's inner lock across both the freeze-state
+            // read and the completion queueing, so a concurrent
+            // unfreeze cannot slip between the decision and the flag:
+            // the completion flavour is pinned while the state is.
+            // (Process inner is rank 1, the sender thread's inner taken
+            // by push_completion is rank 2 -- in order.)
+            let target_inner = self.to.lock_inner();
+            let target_frozen = target_inner.is_frozen;
+            self.from.push_completion(target_frozen);
+            drop(target_inner);
+            if let Some(node) = &self.target_node {
+                let enabled = self
+                    .from
+                    .process
+                    .inner
+                    .lock()
+                    .oneway_spam_detection_enabled;
+                // Free async space is owned by the target's allocator;
+                // until the Rust allocator lands, derive pressure from
+                // the node accounting alone with a fixed budget.
+                let free_async_space = crate::process::ASYNC_SPACE_BUDGET
+                    .saturating_sub(node.pending_oneway_bytes.load(core::sync::atomic::Ordering::Relaxed));
+                if enabled && node.submit_oneway_accounting(size, free_async_space) {
+                    self.from.set_oneway_spam_suspect();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the delivery of a reply for this transaction.
+    pub(crate) fn reply_delivered(&self) {
+        self.from.process.ctx.stats.inc_replies();
+    }
+
+    /// Cancels an in-flight transaction that can no longer be delivered.
+    pub(crate) fn cancel(&self) {
+        self.from.process.ctx.stats.inc_failed();
+    }
+}
+
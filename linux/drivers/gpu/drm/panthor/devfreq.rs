@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: GPL-2.0 or MIT
+
+//! Devfreq integration for the panthor GPU driver.
+
+use kernel::{
+    c_str,
+    devfreq::{
+        DevFreq, DevFreqProfile, DevFreqUtilizationTracker, DevStatus, DevfreqProfileFields,
+        SimpleOnDemandData, SimpleOnDemandDataFields,
+    },
+    prelude::*,
+    sync::{Arc, SpinLock},
+};
+
+/// Utilisation tracking state, guarded by the devfreq spinlock.
+pub(crate) struct Inner {
+    tracker: DevFreqUtilizationTracker,
+    current_frequency: u64,
+}
+
+/// The panthor devfreq state.
+pub(crate) struct PanthorDevfreq {
+    pub(crate) inner: SpinLock<Inner>,
+}
+
+impl PanthorDevfreq {
+    pub(crate) fn new() -> Result<Arc<Self>> {
+        let this = Arc::try_new(Self {
+            // SAFETY: Initialised below before the arc is shared.
+            inner: unsafe {
+                SpinLock::new_uninit(Inner {
+                    tracker: DevFreqUtilizationTracker::new(),
+                    current_frequency: 0,
+                })
+            },
+        })?;
+        // SAFETY: Initialised exactly once; the data does not move behind
+        // the `Arc`.
+        unsafe { this.inner.init_once(c_str!("PanthorDevfreq::inner")) };
+        Ok(this)
+    }
+
+    /// Records that the GPU started executing work.
+    ///
+    /// Callable from irq-adjacent paths: the lock is taken irqsave.
+    pub(crate) fn record_busy(&self) {
+        self.inner.lock_irqsave().tracker.record_busy();
+    }
+
+    /// Records that the GPU went idle.
+    ///
+    /// Callable from irq-adjacent paths: the lock is taken irqsave.
+    pub(crate) fn record_idle(&self) {
+        self.inner.lock_irqsave().tracker.record_idle();
+    }
+}
+
+impl DevFreqProfile for PanthorDevfreq {
+    type Data = Arc<PanthorDevfreq>;
+
+    fn target(_data: kernel::sync::ArcBorrow<'_, PanthorDevfreq>, _freq: u64) -> Result {
+        // The clock handover to Rust is still pending; the C glue sets the
+        // core clock for now.
+        Ok(())
+    }
+
+    fn get_dev_status(data: kernel::sync::ArcBorrow<'_, PanthorDevfreq>) -> Result<DevStatus> {
+        let mut inner = data.inner.lock_irqsave();
+        let freq = inner.current_frequency;
+        Ok(inner.tracker.snapshot_and_reset(freq))
+    }
+}
+
+/// Brings up devfreq for the GPU and registers it as a cooling device.
+pub(crate) fn init(
+    dev: *mut kernel::bindings::device,
+    initial_freq: u64,
+) -> Result<DevFreq<PanthorDevfreq>> {
+    let state = PanthorDevfreq::new()?;
+    let gov = SimpleOnDemandData::new(SimpleOnDemandDataFields {
+        upthreshold: 45,
+        downdifferential: 5,
+    })?;
+    let mut devfreq = DevFreq::<PanthorDevfreq>::add_device(
+        dev,
+        DevfreqProfileFields {
+            polling_ms: 50,
+            initial_freq,
+            suspend_freq: None,
+        },
+        state,
+        gov,
+    )?;
+    // Propagate EPROBE_DEFER so the driver core retries the probe once
+    // the energy model is ready, instead of silently running uncooled.
+    devfreq.cooling_em_register()?;
+    Ok(devfreq)
+}
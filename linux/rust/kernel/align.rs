@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Alignment arithmetic helpers.
+
+/// Aligns `size` up to the size of a pointer and adds it to `addr`,
+/// returning [`None`] if either step overflows.
+///
+/// Callers computing offsets into user-controlled buffers should prefer
+/// this over open-coded `addr + ptr_align(size)`: with hostile sizes the
+/// unchecked form wraps and produces an offset into unrelated memory,
+/// while `None` here lets the caller fail with `EINVAL`.
+///
+/// ```
+/// use kernel::align::ptr_align_checked;
+///
+/// assert_eq!(ptr_align_checked(0x1000, 5), Some(0x1000 + 8));
+/// assert_eq!(ptr_align_checked(usize::MAX, 1), None);
+/// assert_eq!(ptr_align_checked(0, usize::MAX), None);
+/// ```
+pub const fn ptr_align_checked(addr: usize, size: usize) -> Option<usize> {
+    const ALIGN: usize = core::mem::size_of::<usize>();
+    let aligned = match size.checked_add(ALIGN - 1) {
+        Some(v) => v & !(ALIGN - 1),
+        None => return None,
+    };
+    addr.checked_add(aligned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALIGN: usize = core::mem::size_of::<usize>();
+
+    #[test]
+    fn boundaries_return_none() {
+        assert_eq!(ptr_align_checked(usize::MAX, 1), None);
+        assert_eq!(ptr_align_checked(0, usize::MAX), None);
+        assert_eq!(ptr_align_checked(usize::MAX - ALIGN + 1, ALIGN), None);
+    }
+
+    #[test]
+    fn matches_unchecked_alignment() {
+        assert_eq!(ptr_align_checked(0, 0), Some(0));
+        assert_eq!(ptr_align_checked(0x1000, 1), Some(0x1000 + ALIGN));
+        assert_eq!(ptr_align_checked(0x1000, ALIGN), Some(0x1000 + ALIGN));
+        assert_eq!(
+            ptr_align_checked(0x1000, ALIGN + 1),
+            Some(0x1000 + 2 * ALIGN)
+        );
+    }
+}
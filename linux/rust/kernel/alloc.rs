@@ -0,0 +1,90 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Implementation of the kernel's memory allocation infrastructure.
+
+use crate::bindings;
+
+/// Indicates an allocation error.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AllocError;
+
+/// Flags to be used when allocating memory.
+///
+/// They can be combined with the operators `|`, `&`, and `!`.
+///
+/// Values can be used from the [`flags`] module.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Flags(u32);
+
+impl Flags {
+    /// Get the raw representation of this flag.
+    pub(crate) fn as_raw(self) -> u32 {
+        self.0
+    }
+}
+
+impl core::ops::BitOr for Flags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Allocation flags.
+///
+/// These are meant to be used in functions that can allocate memory.
+pub mod flags {
+    use super::Flags;
+
+    /// `GFP_KERNEL` is typical for kernel-internal allocations. The caller
+    /// requires `ZONE_NORMAL` or a lower zone for direct access but can
+    /// direct reclaim.
+    pub const GFP_KERNEL: Flags = Flags(crate::bindings::GFP_KERNEL);
+
+    /// `GFP_ATOMIC` users can not sleep and need the allocation to succeed.
+    /// A lower watermark is applied to allow access to "atomic reserves".
+    pub const GFP_ATOMIC: Flags = Flags(crate::bindings::GFP_ATOMIC);
+
+    /// `GFP_NOFS` will use direct reclaim but will not use any filesystem
+    /// interfaces.
+    pub const GFP_NOFS: Flags = Flags(crate::bindings::GFP_NOFS);
+
+    /// `GFP_NOWAIT` is for kernel allocations that should not stall for
+    /// direct reclaim, start physical IO or use any filesystem callback.
+    pub const GFP_NOWAIT: Flags = Flags(crate::bindings::GFP_NOWAIT);
+
+    /// `__GFP_ZERO` returns a zeroed page on success.
+    pub const __GFP_ZERO: Flags = Flags(crate::bindings::__GFP_ZERO);
+}
+
+/// Re-export of the fallible collection types used throughout the kernel
+/// crate.
+pub use alloc::{boxed::Box, vec::Vec};
+
+// SAFETY: The kernel allocator never unwinds and always respects the
+// requested layout.
+unsafe impl core::alloc::GlobalAlloc for KernelAllocator {
+    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        // SAFETY: `krealloc` with a null pointer behaves like `kmalloc`;
+        // the size comes from a valid `Layout`.
+        unsafe {
+            bindings::krealloc(
+                core::ptr::null(),
+                layout.size(),
+                bindings::GFP_KERNEL,
+            )
+            .cast()
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: core::alloc::Layout) {
+        // SAFETY: `ptr` came from `krealloc` per this type's contract.
+        unsafe { bindings::kfree(ptr.cast()) }
+    }
+}
+
+/// The global kernel allocator backing `Box` and `Vec`.
+pub struct KernelAllocator;
+
+#[global_allocator]
+static ALLOCATOR: KernelAllocator = KernelAllocator;
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Common clock framework.
+//!
+//! C header: [`include/linux/clk.h`](srctree/include/linux/clk.h)
+
+use crate::{bindings, error::to_result, error::Result};
+
+/// A clock obtained through the device-managed interface.
+///
+/// # Invariants
+///
+/// The inner pointer was returned by a successful `devm_clk_get` and stays
+/// valid while the owning device is bound.
+///
+/// There is no `Drop` implementation: release is owned by devres, so a
+/// [`Clk`] must not outlive the device it was acquired from (the same
+/// contract as the other devm-backed wrappers).
+pub struct Clk(pub(crate) *mut bindings::clk);
+
+// SAFETY: The clk API is internally locked and callable from any thread.
+unsafe impl Send for Clk {}
+
+impl Clk {
+    /// Prepares and enables the clock.
+    pub fn prepare_enable(&self) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::clk_prepare_enable(self.0) })
+    }
+
+    /// Disables and unprepares the clock.
+    ///
+    /// Must balance a previous [`Clk::prepare_enable`].
+    pub fn disable_unprepare(&self) {
+        // SAFETY: The pointer is valid per the type invariant.
+        unsafe { bindings::clk_disable_unprepare(self.0) }
+    }
+
+    /// Returns the current rate of the clock in Hz.
+    pub fn get_rate(&self) -> u64 {
+        // SAFETY: The pointer is valid per the type invariant.
+        unsafe { bindings::clk_get_rate(self.0) as u64 }
+    }
+
+    /// Requests a new rate for the clock, in Hz.
+    pub fn set_rate(&self, rate: u64) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::clk_set_rate(self.0, rate as _) })
+    }
+}
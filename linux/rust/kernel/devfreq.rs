@@ -0,0 +1,746 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Devfreq: generic dynamic voltage and frequency scaling.
+//!
+//! C header: [`include/linux/devfreq.h`](srctree/include/linux/devfreq.h)
+
+use crate::{
+    bindings,
+    time::Ktime,
+    error::{from_err_ptr, to_result, Error, Result},
+    types::ForeignOwnable,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// Registry mapping a device to its devfreq driver data.
+///
+/// The devfreq callbacks only receive the parent `struct device`, whose
+/// drvdata belongs to the *bus* driver (the platform wrapper stores its
+/// own `Driver::Data` there) -- stashing ours in it caused type
+/// confusion on unbind. The association therefore lives here, keyed by
+/// device pointer, claimed in `add_device` and released on drop.
+mod dev_registry {
+    use super::*;
+
+    const SLOTS: usize = 64;
+
+    struct Slot {
+        dev: AtomicUsize,
+        data: AtomicPtr<core::ffi::c_void>,
+    }
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Slot = Slot {
+        dev: AtomicUsize::new(0),
+        data: AtomicPtr::new(core::ptr::null_mut()),
+    };
+
+    static TABLE: [Slot; SLOTS] = [EMPTY; SLOTS];
+
+    pub(super) fn register(
+        dev: *mut bindings::device,
+        data: *mut core::ffi::c_void,
+    ) -> crate::error::Result {
+        for slot in TABLE.iter() {
+            if slot
+                .dev
+                .compare_exchange(0, dev as usize, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                slot.data.store(data, Ordering::Release);
+                return Ok(());
+            }
+        }
+        // More concurrent devfreq devices than slots; fail the add
+        // rather than silently misrouting callbacks.
+        Err(crate::error::code::EBUSY)
+    }
+
+    pub(super) fn lookup(dev: *mut bindings::device) -> Option<*mut core::ffi::c_void> {
+        for slot in TABLE.iter() {
+            if slot.dev.load(Ordering::Acquire) == dev as usize {
+                return Some(slot.data.load(Ordering::Acquire));
+            }
+        }
+        None
+    }
+
+    pub(super) fn unregister(dev: *mut bindings::device) {
+        for slot in TABLE.iter() {
+            if slot.dev.load(Ordering::Acquire) == dev as usize {
+                slot.data.store(core::ptr::null_mut(), Ordering::Relaxed);
+                slot.dev.store(0, Ordering::Release);
+                return;
+            }
+        }
+    }
+}
+
+/// Device utilisation statistics reported to the governor.
+#[derive(Clone, Copy, Default)]
+pub struct DevStatus {
+    /// Total observation time, in the same unit as `busy_time`.
+    pub total_time: u64,
+    /// Time the device was busy.
+    pub busy_time: u64,
+    /// The frequency the device currently runs at, in Hz.
+    pub current_frequency: u64,
+}
+
+/// The static fields of a devfreq profile.
+#[derive(Clone, Copy, Default)]
+pub struct DevfreqProfileFields {
+    /// Polling interval in milliseconds; 0 disables polling.
+    pub polling_ms: u32,
+    /// The initial frequency, in Hz.
+    pub initial_freq: u64,
+    /// A fixed frequency to run at while suspended, in Hz.
+    ///
+    /// When set, `devfreq_suspend_device` retargets the device to this
+    /// frequency through the profile's `target` callback (with max-freq
+    /// flag semantics) before stopping the governor, and resume restores
+    /// the pre-suspend frequency -- so `target` must accept the value
+    /// even if the OPP table would not normally pick it. The
+    /// [`DevFreq::pm_suspend`] helper is the path that triggers this.
+    /// [`None`] maps to the C sentinel 0, meaning no fixed suspend
+    /// frequency.
+    pub suspend_freq: Option<u64>,
+}
+
+/// A driver's devfreq profile implementation.
+pub trait DevFreqProfile {
+    /// Driver data handed to the callbacks.
+    type Data: ForeignOwnable + Sync + Send;
+
+    /// Requests the device to run at `freq` (in Hz).
+    fn target(data: <Self::Data as ForeignOwnable>::Borrowed<'_>, freq: u64) -> Result;
+
+    /// Reports utilisation since the previous call.
+    fn get_dev_status(data: <Self::Data as ForeignOwnable>::Borrowed<'_>) -> Result<DevStatus>;
+
+    /// Whether [`get_cur_freq`](Self::get_cur_freq) is implemented.
+    ///
+    /// When false the C profile slot stays null and the devfreq core
+    /// falls back to its own bookkeeping (`previous_freq`), the stock
+    /// behaviour.
+    const HAS_GET_CUR_FREQ: bool = false;
+
+    /// Reports the frequency the hardware is actually running at, in Hz.
+    ///
+    /// Gives the core the true value (e.g. read back from the clock)
+    /// instead of the last requested one. Only wired into the profile
+    /// when [`HAS_GET_CUR_FREQ`](Self::HAS_GET_CUR_FREQ) is set.
+    fn get_cur_freq(_data: <Self::Data as ForeignOwnable>::Borrowed<'_>) -> Result<u64> {
+        Err(crate::error::code::ENOTSUPP)
+    }
+}
+
+/// Accumulates busy/idle time for a `get_dev_status` implementation.
+///
+/// Mechanism only: it is `&mut` and carries no lock, so callers wrap it
+/// in whatever guards their context needs (an irqsave spinlock when the
+/// transitions come from irq-adjacent paths). The transition recorders
+/// are idempotent -- a second `record_busy` while already busy does not
+/// double-count -- because double events are exactly what irq-adjacent
+/// callers deliver, and getting that wrong is the bug every driver
+/// reimplementation makes.
+pub struct DevFreqUtilizationTracker {
+    /// When the device last became busy, if it currently is.
+    busy_since: Option<Ktime>,
+    busy_time: Ktime,
+    last_update: Ktime,
+    total_time: Ktime,
+}
+
+impl DevFreqUtilizationTracker {
+    /// Creates a tracker with all accumulators at zero, based at now.
+    pub fn new() -> Self {
+        Self {
+            busy_since: None,
+            busy_time: Ktime::from_ns(0),
+            last_update: Ktime::ktime_get(),
+            total_time: Ktime::from_ns(0),
+        }
+    }
+
+    fn advance(&mut self) -> Ktime {
+        let now = Ktime::ktime_get();
+        self.total_time = self.total_time + (now - self.last_update);
+        self.last_update = now;
+        now
+    }
+
+    /// Records a transition to busy; idempotent while already busy.
+    pub fn record_busy(&mut self) {
+        let now = self.advance();
+        if self.busy_since.is_none() {
+            self.busy_since = Some(now);
+        }
+    }
+
+    /// Records a transition to idle; idempotent while already idle.
+    pub fn record_idle(&mut self) {
+        let now = self.advance();
+        if let Some(since) = self.busy_since.take() {
+            self.busy_time = self.busy_time + (now - since);
+        }
+    }
+
+    /// Produces a [`DevStatus`] for the window since the last snapshot
+    /// and rebases the accumulators, carrying a still-busy period into
+    /// the new window.
+    pub fn snapshot_and_reset(&mut self, current_frequency: u64) -> DevStatus {
+        let now = self.advance();
+        if let Some(since) = self.busy_since {
+            self.busy_time = self.busy_time + (now - since);
+            self.busy_since = Some(now);
+        }
+        let status = DevStatus {
+            total_time: self.total_time.to_ns() as u64,
+            busy_time: self.busy_time.to_ns() as u64,
+            current_frequency,
+        };
+        self.busy_time = Ktime::from_ns(0);
+        self.total_time = Ktime::from_ns(0);
+        status
+    }
+}
+
+impl Default for DevFreqUtilizationTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Governor configuration passed to `devfreq_add_device`.
+///
+/// # Safety
+///
+/// The raw data returned by [`into_raw`](GovernorData::into_raw) must
+/// match the layout the governor named by
+/// [`governor_name`](GovernorData::governor_name) casts it to.
+pub unsafe trait GovernorData {
+    /// The `DEVFREQ_GOV_*` name of the governor.
+    fn governor_name() -> *const core::ffi::c_char;
+
+    /// The raw data pointer handed to the governor, kept alive by the
+    /// [`DevFreq`] that consumed `self`.
+    fn into_raw(self) -> *mut core::ffi::c_void;
+
+    /// Reclaims a pointer produced by [`into_raw`](GovernorData::into_raw).
+    ///
+    /// Called by [`DevFreq`] after the devfreq is removed (so the
+    /// governor can no longer dereference the data). The default no-op
+    /// suits null-payload governors; anything that allocated must
+    /// override.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from this type's `into_raw` and not have
+    /// been reclaimed already.
+    unsafe fn free_raw(_ptr: *mut core::ffi::c_void) {}
+}
+
+/// Tunables of the simple-ondemand governor.
+#[derive(Clone, Copy)]
+pub struct SimpleOnDemandDataFields {
+    /// Load percentage above which the governor picks a higher frequency.
+    pub upthreshold: u32,
+    /// Hysteresis subtracted from `upthreshold` before stepping down.
+    pub downdifferential: u32,
+}
+
+/// Validated simple-ondemand governor data.
+pub struct SimpleOnDemandData {
+    raw: Box<bindings::devfreq_simple_ondemand_data>,
+}
+
+impl SimpleOnDemandData {
+    /// Creates governor data from the given tunables, enforcing the
+    /// governor's invariants.
+    ///
+    /// The checks mirror what the governor's sysfs store callbacks
+    /// enforce at runtime: `upthreshold` is a load percentage and must
+    /// not exceed 100, and `downdifferential` must be strictly below it
+    /// (equal would make the down-step condition unsatisfiable). Both
+    /// violations are `EINVAL`. A zero `upthreshold` is technically
+    /// legal to the C side but pins the governor at the maximum
+    /// frequency; it is allowed here, with this warning as its
+    /// documentation.
+    pub fn new(fields: SimpleOnDemandDataFields) -> Result<Self> {
+        if fields.upthreshold > 100 || fields.downdifferential >= fields.upthreshold {
+            return Err(crate::error::code::EINVAL);
+        }
+        Ok(Self {
+            raw: Box::try_new(bindings::devfreq_simple_ondemand_data {
+                upthreshold: fields.upthreshold,
+                downdifferential: fields.downdifferential,
+            })?,
+        })
+    }
+}
+
+// SAFETY: The raw pointer is a `devfreq_simple_ondemand_data`, which is
+// what the simple-ondemand governor expects.
+unsafe impl GovernorData for SimpleOnDemandData {
+    fn governor_name() -> *const core::ffi::c_char {
+        bindings::DEVFREQ_GOV_SIMPLE_ONDEMAND.as_ptr().cast()
+    }
+
+    fn into_raw(self) -> *mut core::ffi::c_void {
+        Box::into_raw(self.raw).cast()
+    }
+
+    unsafe fn free_raw(ptr: *mut core::ffi::c_void) {
+        // SAFETY: Per the trait contract, `ptr` is the Box leaked in
+        // `into_raw`.
+        drop(unsafe {
+            Box::from_raw(ptr.cast::<bindings::devfreq_simple_ondemand_data>())
+        });
+    }
+}
+
+/// Governor data for the performance governor, which pins the device at
+/// its maximum frequency. Takes no configuration.
+pub struct PerformanceData;
+
+// SAFETY: The performance governor dereferences no data; null is the
+// expected payload.
+unsafe impl GovernorData for PerformanceData {
+    fn governor_name() -> *const core::ffi::c_char {
+        bindings::DEVFREQ_GOV_PERFORMANCE.as_ptr().cast()
+    }
+
+    fn into_raw(self) -> *mut core::ffi::c_void {
+        core::ptr::null_mut()
+    }
+}
+
+/// Governor data for the powersave governor, which pins the device at its
+/// minimum frequency. Takes no configuration.
+pub struct PowersaveData;
+
+// SAFETY: As for `PerformanceData`.
+unsafe impl GovernorData for PowersaveData {
+    fn governor_name() -> *const core::ffi::c_char {
+        bindings::DEVFREQ_GOV_POWERSAVE.as_ptr().cast()
+    }
+
+    fn into_raw(self) -> *mut core::ffi::c_void {
+        core::ptr::null_mut()
+    }
+}
+
+/// Governor data for the userspace governor.
+///
+/// The governor itself takes no profile data -- the requested frequency
+/// arrives through sysfs (`set_freq`), not through this payload.
+pub struct UserspaceData;
+
+// SAFETY: As for `PerformanceData`.
+unsafe impl GovernorData for UserspaceData {
+    fn governor_name() -> *const core::ffi::c_char {
+        bindings::DEVFREQ_GOV_USERSPACE.as_ptr().cast()
+    }
+
+    fn into_raw(self) -> *mut core::ffi::c_void {
+        core::ptr::null_mut()
+    }
+}
+
+// A `PassiveData` governor type additionally needs the parent-devfreq
+// plumbing (`devfreq_passive_data.parent`); it is deliberately left out
+// until that exists.
+
+/// A devfreq device.
+///
+/// # Invariants
+///
+/// `devfreq` was returned by a successful `devfreq_add_device` against
+/// `dev` and has not been removed; `profile` and `gov_data` outlive it.
+pub struct DevFreq<P: DevFreqProfile> {
+    devfreq: NonNull<bindings::devfreq>,
+    dev: *mut bindings::device,
+    profile: Box<bindings::devfreq_dev_profile>,
+    gov_data: *mut core::ffi::c_void,
+    /// Reclaims `gov_data`; captured from the governor type at
+    /// `add_device` time since `DevFreq` is not generic over it.
+    gov_free: unsafe fn(*mut core::ffi::c_void),
+    /// The cooling device registered via
+    /// [`cooling_em_register`](Self::cooling_em_register), unregistered on
+    /// drop.
+    cooling: Option<NonNull<bindings::thermal_cooling_device>>,
+    _p: PhantomData<P>,
+}
+
+impl<P: DevFreqProfile> DevFreq<P> {
+    /// Registers a devfreq device for `dev`.
+    pub fn add_device<G: GovernorData>(
+        dev: *mut bindings::device,
+        fields: DevfreqProfileFields,
+        data: P::Data,
+        gov: G,
+    ) -> Result<Self> {
+        let mut profile = Box::try_new(bindings::devfreq_dev_profile {
+            initial_freq: fields.initial_freq as _,
+            polling_ms: fields.polling_ms,
+            suspend_freq: fields.suspend_freq.unwrap_or(0) as _,
+            target: Some(target_callback::<P>),
+            get_dev_status: Some(get_dev_status_callback::<P>),
+            get_cur_freq: if P::HAS_GET_CUR_FREQ {
+                Some(get_cur_freq_callback::<P>)
+            } else {
+                None
+            },
+            // SAFETY: All zeros is valid for the remaining fields.
+            ..unsafe { core::mem::zeroed() }
+        })?;
+        let gov_data = gov.into_raw();
+        let data_ptr = data.into_foreign();
+        // The parent device's drvdata belongs to the bus driver; the
+        // callback association goes through the registry instead.
+        if let Err(e) = dev_registry::register(dev, data_ptr) {
+            // SAFETY: Ownership of both pointers returns to us.
+            unsafe {
+                G::free_raw(gov_data);
+                drop(P::Data::from_foreign(data_ptr));
+            }
+            return Err(e);
+        }
+        // SAFETY: `dev` is a valid device per the caller, and everything
+        // passed outlives the devfreq per this type's invariant.
+        let devfreq = from_err_ptr(unsafe {
+            bindings::devfreq_add_device(dev, &mut *profile, G::governor_name(), gov_data)
+        });
+        let devfreq = match devfreq {
+            Ok(d) => d,
+            Err(e) => {
+                dev_registry::unregister(dev);
+                // SAFETY: On failure ownership of both pointers returns
+                // to us; the governor never saw the data.
+                unsafe {
+                    G::free_raw(gov_data);
+                    drop(P::Data::from_foreign(data_ptr));
+                }
+                return Err(e);
+            }
+        };
+        // INVARIANT: `devfreq_add_device` succeeded.
+        Ok(Self {
+            // SAFETY: `from_err_ptr` guarantees non-error; the C call
+            // never returns null on success.
+            devfreq: unsafe { NonNull::new_unchecked(devfreq) },
+            dev,
+            profile,
+            gov_data,
+            gov_free: G::free_raw,
+            cooling: None,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns the raw devfreq pointer.
+    pub fn as_raw(&self) -> *mut bindings::devfreq {
+        self.devfreq.as_ptr()
+    }
+
+    /// Clamps the frequencies the governor may choose to `[min, max]`
+    /// (both in Hz), returning a handle that removes the constraint when
+    /// dropped.
+    ///
+    /// Built on `dev_pm_qos`: one MIN_FREQUENCY and one MAX_FREQUENCY
+    /// request. Note the unit mismatch this converts for you: the QoS
+    /// frequency requests speak kHz while devfreq profiles speak Hz.
+    /// Constraints clamp the governor's choice (the scaling limits are
+    /// recomputed through the QoS notifiers); they do not retarget the
+    /// device immediately.
+    pub fn add_freq_constraint(&self, min: u64, max: u64) -> Result<FreqQosHandle> {
+        let mut reqs = Box::try_new(FreqQosRequests {
+            // SAFETY: Zeroed is the documented pre-add state for
+            // `dev_pm_qos_request`.
+            min: unsafe { core::mem::zeroed() },
+            max: unsafe { core::mem::zeroed() },
+        })?;
+        // SAFETY: The device is valid and the request structs are
+        // heap-pinned inside the box for the handle's lifetime.
+        to_result(unsafe {
+            bindings::dev_pm_qos_add_request(
+                self.dev,
+                &mut reqs.min,
+                bindings::dev_pm_qos_req_type_DEV_PM_QOS_MIN_FREQUENCY,
+                (min / 1000) as _,
+            )
+        })?;
+        // SAFETY: As above.
+        let ret = unsafe {
+            bindings::dev_pm_qos_add_request(
+                self.dev,
+                &mut reqs.max,
+                bindings::dev_pm_qos_req_type_DEV_PM_QOS_MAX_FREQUENCY,
+                (max / 1000) as _,
+            )
+        };
+        if ret < 0 {
+            // SAFETY: The min request was added above; unwind it.
+            unsafe { bindings::dev_pm_qos_remove_request(&mut reqs.min) };
+            return Err(Error::from_errno(ret));
+        }
+        Ok(FreqQosHandle { reqs })
+    }
+
+    /// Enumerates the frequencies in the device's OPP table, ascending.
+    ///
+    /// Requires an OPP table to be registered for the device; without one
+    /// the first lookup fails with `ENODEV`, which is propagated. The
+    /// capacity hint from `dev_pm_opp_get_opp_count` is just a hint --
+    /// the table can change between the count and the walk, so the walk
+    /// is what decides the contents.
+    pub fn available_frequencies(&self) -> Result<Vec<u64>> {
+        let mut freqs = Vec::new();
+        // SAFETY: The device is valid per the type invariant.
+        let count = unsafe { bindings::dev_pm_opp_get_opp_count(self.dev) };
+        if count > 0 {
+            freqs.try_reserve(count as usize).map_err(|_| crate::error::code::ENOMEM)?;
+        }
+        let mut freq: core::ffi::c_ulong = 0;
+        loop {
+            // SAFETY: The device is valid and `freq` is a local
+            // in/out-parameter that the helper rounds up to the next OPP.
+            let opp = unsafe { bindings::dev_pm_opp_find_freq_ceil(self.dev, &mut freq) };
+            // SAFETY: Error pointers are not dereferenced.
+            if unsafe { bindings::IS_ERR(opp.cast()) } {
+                // SAFETY: See above.
+                let err = unsafe { bindings::PTR_ERR(opp.cast()) } as core::ffi::c_int;
+                if err == -(bindings::ERANGE as core::ffi::c_int) {
+                    break; // Walked past the last OPP.
+                }
+                return Err(Error::from_errno(err));
+            }
+            freqs.try_reserve(1).map_err(|_| crate::error::code::ENOMEM)?;
+            freqs.push(freq as u64);
+            // SAFETY: `opp` is a valid OPP returned with an elevated
+            // refcount.
+            unsafe { bindings::dev_pm_opp_put(opp) };
+            freq += 1;
+        }
+        Ok(freqs)
+    }
+
+    /// Suspends the devfreq device and then the underlying device via
+    /// runtime PM.
+    ///
+    /// The invariant both helpers maintain: the governor must never be
+    /// live while the device is runtime-suspended. Suspend therefore
+    /// stops the governor first (`devfreq_suspend_device`, so no
+    /// `target` callback can race the dying device) and only then drops
+    /// the runtime-PM reference; [`pm_resume`](Self::pm_resume) is the
+    /// exact mirror. On a runtime-PM failure the governor is resumed
+    /// again before returning, so the pair unwinds cleanly.
+    pub fn pm_suspend(&self) -> Result {
+        // SAFETY: The devfreq and device are live per the type invariant.
+        to_result(unsafe { bindings::devfreq_suspend_device(self.as_raw()) })?;
+        // SAFETY: See above.
+        let ret = unsafe { bindings::pm_runtime_put_sync_suspend(self.dev) };
+        if ret < 0 {
+            // SAFETY: The governor was stopped above and the device is
+            // still powered; restart it so the failure leaves no
+            // half-suspended state.
+            unsafe { bindings::devfreq_resume_device(self.as_raw()) };
+            return Err(Error::from_errno(ret));
+        }
+        Ok(())
+    }
+
+    /// Resumes the underlying device via runtime PM and then the devfreq
+    /// device.
+    ///
+    /// See [`pm_suspend`](Self::pm_suspend) for the ordering rationale.
+    pub fn pm_resume(&self) -> Result {
+        // SAFETY: The device is live per the type invariant.
+        to_result(unsafe { bindings::pm_runtime_resume_and_get(self.dev) })?;
+        // SAFETY: The devfreq is live per the type invariant.
+        let ret = unsafe { bindings::devfreq_resume_device(self.as_raw()) };
+        if ret < 0 {
+            // SAFETY: Balance the reference taken above.
+            unsafe { bindings::pm_runtime_put(self.dev) };
+            return Err(Error::from_errno(ret));
+        }
+        Ok(())
+    }
+
+    /// Registers this devfreq as a cooling device using the energy model.
+    ///
+    /// The returned cooling device is retained and unregistered when the
+    /// [`DevFreq`] is dropped. Errors are returned unmapped: in
+    /// particular, `EPROBE_DEFER` (the energy model not being ready yet)
+    /// reaches the caller as-is so probe paths can propagate it and be
+    /// retried by the driver core.
+    pub fn cooling_em_register(&mut self) -> Result {
+        // SAFETY: The devfreq is live per the type invariant.
+        let cdev = from_err_ptr(unsafe {
+            bindings::devfreq_cooling_em_register(self.as_raw(), core::ptr::null_mut())
+        })?;
+        // SAFETY: `from_err_ptr` guarantees non-error; success is never
+        // null.
+        self.cooling = Some(unsafe { NonNull::new_unchecked(cdev) });
+        Ok(())
+    }
+}
+
+impl<P: DevFreqProfile> Drop for DevFreq<P> {
+    fn drop(&mut self) {
+        if let Some(cooling) = self.cooling.take() {
+            // SAFETY: The cooling device was registered in
+            // `cooling_em_register` and not unregistered since.
+            unsafe { bindings::devfreq_cooling_unregister(cooling.as_ptr()) };
+        }
+        // SAFETY: The devfreq is live per the type invariant.
+        unsafe { bindings::devfreq_remove_device(self.devfreq.as_ptr()) };
+        let data = dev_registry::lookup(self.dev);
+        dev_registry::unregister(self.dev);
+        // SAFETY: The registry entry was created in `add_device` and the
+        // C side no longer calls back after removal; same for the
+        // governor data.
+        unsafe {
+            if let Some(data) = data {
+                drop(P::Data::from_foreign(data));
+            }
+            (self.gov_free)(self.gov_data);
+        }
+    }
+}
+
+/// # Safety
+///
+/// Called by the devfreq core with the device whose drvdata was set in
+/// `add_device`.
+unsafe extern "C" fn target_callback<P: DevFreqProfile>(
+    dev: *mut bindings::device,
+    freq: *mut core::ffi::c_ulong,
+    _flags: u32,
+) -> core::ffi::c_int {
+    let Some(ptr) = dev_registry::lookup(dev) else {
+        return -(bindings::ENODEV as core::ffi::c_int);
+    };
+    // SAFETY: The registry entry is live while the devfreq exists, which
+    // is the only window the core invokes callbacks in.
+    let data = unsafe { P::Data::borrow(ptr) };
+    // SAFETY: The core passes a valid out-parameter.
+    match P::target(data, unsafe { *freq } as u64) {
+        Ok(()) => 0,
+        Err(e) => e.to_errno(),
+    }
+}
+
+/// # Safety
+///
+/// As for `target_callback`.
+unsafe extern "C" fn get_cur_freq_callback<P: DevFreqProfile>(
+    dev: *mut bindings::device,
+    freq: *mut core::ffi::c_ulong,
+) -> core::ffi::c_int {
+    let Some(ptr) = dev_registry::lookup(dev) else {
+        return -(bindings::ENODEV as core::ffi::c_int);
+    };
+    // SAFETY: As in `target_callback`.
+    let data = unsafe { P::Data::borrow(ptr) };
+    match P::get_cur_freq(data) {
+        Ok(v) => {
+            // SAFETY: The core passes a valid out-parameter.
+            unsafe { *freq = v as _ };
+            0
+        }
+        Err(e) => e.to_errno(),
+    }
+}
+
+/// # Safety
+///
+/// As for `target_callback`.
+unsafe extern "C" fn get_dev_status_callback<P: DevFreqProfile>(
+    dev: *mut bindings::device,
+    stat: *mut bindings::devfreq_dev_status,
+) -> core::ffi::c_int {
+    let Some(ptr) = dev_registry::lookup(dev) else {
+        return -(bindings::ENODEV as core::ffi::c_int);
+    };
+    // SAFETY: As in `target_callback`.
+    let data = unsafe { P::Data::borrow(ptr) };
+    match P::get_dev_status(data) {
+        Ok(s) => {
+            // SAFETY: The core passes a valid out-parameter.
+            unsafe {
+                (*stat).total_time = s.total_time as _;
+                (*stat).busy_time = s.busy_time as _;
+                (*stat).current_frequency = s.current_frequency as _;
+            }
+            0
+        }
+        Err(e) => e.to_errno(),
+    }
+}
+
+
+struct FreqQosRequests {
+    min: bindings::dev_pm_qos_request,
+    max: bindings::dev_pm_qos_request,
+}
+
+/// An active frequency constraint; dropping it lifts the constraint.
+///
+/// # Invariants
+///
+/// Both requests in `reqs` were successfully added and not yet removed.
+pub struct FreqQosHandle {
+    reqs: Box<FreqQosRequests>,
+}
+
+// SAFETY: The QoS request list handles its own locking.
+unsafe impl Send for FreqQosHandle {}
+
+impl Drop for FreqQosHandle {
+    fn drop(&mut self) {
+        // SAFETY: Both requests are live per the type invariant.
+        unsafe {
+            bindings::dev_pm_qos_remove_request(&mut self.reqs.max);
+            bindings::dev_pm_qos_remove_request(&mut self.reqs.min);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_ondemand_validation() {
+        // The panthor pair is valid.
+        assert!(SimpleOnDemandData::new(SimpleOnDemandDataFields {
+            upthreshold: 45,
+            downdifferential: 5,
+        })
+        .is_ok());
+        // A percentage cannot exceed 100.
+        assert!(SimpleOnDemandData::new(SimpleOnDemandDataFields {
+            upthreshold: 101,
+            downdifferential: 5,
+        })
+        .is_err());
+        // The differential must be strictly below the threshold.
+        assert!(SimpleOnDemandData::new(SimpleOnDemandDataFields {
+            upthreshold: 45,
+            downdifferential: 45,
+        })
+        .is_err());
+        assert!(SimpleOnDemandData::new(SimpleOnDemandDataFields {
+            upthreshold: 45,
+            downdifferential: 46,
+        })
+        .is_err());
+    }
+}
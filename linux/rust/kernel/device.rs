@@ -0,0 +1,179 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Generic devices that are part of the kernel's driver model.
+//!
+//! C header: [`include/linux/device.h`](srctree/include/linux/device.h)
+
+use crate::{
+    bindings,
+    error::{to_result, Error, Result},
+    types::{AlwaysRefCounted, Opaque},
+};
+use core::ptr::NonNull;
+
+/// A reference-counted device.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to
+/// `get_device` ensures that the allocation remains valid at least until
+/// the matching call to `put_device`.
+#[repr(transparent)]
+pub struct Device(Opaque<bindings::device>);
+
+// SAFETY: `Device` is just a wrapper over the C struct, which the driver
+// core allows to be used from any thread.
+unsafe impl Send for Device {}
+// SAFETY: See above.
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Creates a reference to a [`Device`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the
+    /// lifetime of the returned reference.
+    pub unsafe fn from_raw<'a>(ptr: *mut bindings::device) -> &'a Self {
+        // SAFETY: `Device` is a transparent wrapper over `bindings::device`.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::device {
+        self.0.get()
+    }
+}
+
+impl Device {
+    /// Enables runtime PM for this device, returning a guard that
+    /// disables it again on drop.
+    ///
+    /// Enable/disable must balance; the RAII form makes the probe-error
+    /// path (the classic place the disable leaks) automatic. Call
+    /// [`PmRuntimeEnableGuard::forget`] for devices that stay enabled
+    /// for their lifetime and disable elsewhere.
+    pub fn pm_runtime_enable(&self) -> PmRuntimeEnableGuard<'_> {
+        // SAFETY: The device is valid per the type invariant.
+        unsafe { bindings::pm_runtime_enable(self.as_raw()) };
+        PmRuntimeEnableGuard { dev: self }
+    }
+
+    /// Takes a runtime-PM reference, resuming the device synchronously,
+    /// and returns a guard that puts the reference on drop.
+    pub fn pm_runtime_get(&self) -> Result<PmRuntimeGuard<'_>> {
+        self.pm_runtime_get_sync()?;
+        Ok(PmRuntimeGuard { dev: self })
+    }
+
+    /// Takes a runtime-PM reference, resuming the device synchronously.
+    ///
+    /// On failure the usage count is dropped again with
+    /// `pm_runtime_put_noidle` before returning, as the C API mandates
+    /// -- that subtlety (a failed get still increments) is the main
+    /// reason to use this wrapper over the raw call.
+    pub fn pm_runtime_get_sync(&self) -> Result {
+        // SAFETY: The device is valid per the type invariant.
+        let ret = unsafe { bindings::pm_runtime_get_sync(self.as_raw()) };
+        if ret < 0 {
+            // SAFETY: Balance the increment the failed get performed.
+            unsafe { bindings::pm_runtime_put_noidle(self.as_raw()) };
+            return Err(Error::from_errno(ret));
+        }
+        Ok(())
+    }
+
+    /// Drops a runtime-PM reference.
+    pub fn pm_runtime_put(&self) -> Result {
+        // SAFETY: The device is valid per the type invariant.
+        to_result(unsafe { bindings::pm_runtime_put(self.as_raw()) })
+    }
+
+    /// Prints a message through `_dev_printk` with this device as the
+    /// context, so the output is prefixed with the driver and device
+    /// name.
+    ///
+    /// Used by the `dev_*!` macros; not meant to be called directly.
+    #[doc(hidden)]
+    pub fn printk(&self, klevel: &'static [u8], args: core::fmt::Arguments<'_>) {
+        use core::fmt::Write;
+        // Render into a stack buffer; device log lines are short and
+        // truncation is acceptable for diagnostics.
+        struct Buf([u8; 512], usize);
+        impl Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                let remaining = self.0.len() - 1 - self.1;
+                let n = s.len().min(remaining);
+                self.0[self.1..self.1 + n].copy_from_slice(&s.as_bytes()[..n]);
+                self.1 += n;
+                Ok(())
+            }
+        }
+        let mut buf = Buf([0; 512], 0);
+        let _ = buf.write_fmt(args);
+        // SAFETY: The device is valid per the type invariant; level and
+        // format are NUL-terminated literals and the buffer was
+        // NUL-padded at construction.
+        unsafe {
+            bindings::_dev_printk(
+                klevel.as_ptr().cast(),
+                self.as_raw(),
+                b"%s\0".as_ptr().cast(),
+                buf.0.as_ptr(),
+            )
+        };
+    }
+}
+
+// SAFETY: The type invariants guarantee `Device` is always ref-counted.
+unsafe impl AlwaysRefCounted for Device {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference guarantees that the
+        // refcount is non-zero.
+        unsafe { bindings::get_device(self.as_raw()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is
+        // non-zero.
+        unsafe { bindings::put_device(obj.cast().as_ptr()) }
+    }
+}
+
+/// Balances a [`Device::pm_runtime_enable`] with a disable on drop.
+pub struct PmRuntimeEnableGuard<'a> {
+    dev: &'a Device,
+}
+
+impl PmRuntimeEnableGuard<'_> {
+    /// Leaves runtime PM enabled permanently; the caller takes over the
+    /// responsibility to disable.
+    pub fn forget(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for PmRuntimeEnableGuard<'_> {
+    fn drop(&mut self) {
+        // SAFETY: The device is valid and was enabled by the matching
+        // `pm_runtime_enable`.
+        unsafe { bindings::pm_runtime_disable(self.dev.as_raw()) };
+    }
+}
+
+/// An owned runtime-PM reference; dropping it puts the reference.
+///
+/// Runtime PM is disabled across system sleep transitions, so guards
+/// should not be assumed to keep hardware powered through suspend --
+/// coordinate with the system-sleep callbacks for that.
+pub struct PmRuntimeGuard<'a> {
+    dev: &'a Device,
+}
+
+impl Drop for PmRuntimeGuard<'_> {
+    fn drop(&mut self) {
+        // SAFETY: The device is valid and the guard owns one usage-count
+        // reference.
+        unsafe { bindings::pm_runtime_put(self.dev.as_raw()) };
+    }
+}
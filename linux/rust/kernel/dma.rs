@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Direct memory access (DMA).
+//!
+//! C header: [`include/linux/dma-mapping.h`](srctree/include/linux/dma-mapping.h)
+
+use crate::{
+    alloc::Flags,
+    bindings,
+    device::Device,
+    error::{code::*, to_result, Result},
+    types::ARef,
+};
+
+impl Device {
+    /// Sets both the streaming and coherent DMA masks to `mask`.
+    pub fn dma_set_mask_and_coherent(&self, mask: u64) -> Result {
+        // SAFETY: The device is valid per the type invariant.
+        to_result(unsafe { bindings::dma_set_mask_and_coherent(self.as_raw(), mask) })
+    }
+
+    /// Allocates a coherent DMA buffer of `count` elements of `T` for
+    /// this device.
+    pub fn dma_alloc_coherent<T>(&self, count: usize, gfp: Flags) -> Result<CoherentAllocation<T>> {
+        CoherentAllocation::new(self, count, gfp)
+    }
+}
+
+/// A coherent DMA buffer.
+///
+/// The memory is coherent between CPU and device: no cache maintenance is
+/// needed around accesses. Coherence is not ordering, though -- making a
+/// descriptor visible to the device still requires the driver's usual
+/// write barrier / doorbell protocol after filling the buffer.
+///
+/// The allocation holds an [`ARef`] to the device, so it cannot outlive
+/// it; this is deliberately stricter than devres ownership because
+/// coherent buffers routinely outlive probe-scoped resources.
+///
+/// # Invariants
+///
+/// `cpu_addr`/`dma_handle` describe a live coherent allocation of
+/// `count * size_of::<T>()` bytes made against `dev`.
+pub struct CoherentAllocation<T> {
+    dev: ARef<Device>,
+    cpu_addr: *mut T,
+    dma_handle: bindings::dma_addr_t,
+    count: usize,
+}
+
+// SAFETY: The buffer is plain memory owned by the allocation.
+unsafe impl<T: Send> Send for CoherentAllocation<T> {}
+
+impl<T> CoherentAllocation<T> {
+    fn new(dev: &Device, count: usize, gfp: Flags) -> Result<Self> {
+        let size = count.checked_mul(core::mem::size_of::<T>()).ok_or(EINVAL)?;
+        let mut dma_handle: bindings::dma_addr_t = 0;
+        // SAFETY: The device is valid and the out-parameter is local.
+        let cpu_addr = unsafe {
+            bindings::dma_alloc_coherent(dev.as_raw(), size, &mut dma_handle, gfp.as_raw())
+        };
+        if cpu_addr.is_null() {
+            return Err(ENOMEM);
+        }
+        // INVARIANT: The allocation succeeded with the recorded geometry.
+        Ok(Self {
+            dev: ARef::from(dev),
+            cpu_addr: cpu_addr.cast(),
+            dma_handle,
+            count,
+        })
+    }
+
+    /// Returns the bus address the device uses to reach the buffer.
+    pub fn dma_handle(&self) -> bindings::dma_addr_t {
+        self.dma_handle
+    }
+
+    /// Returns the number of `T` elements in the buffer.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns a raw pointer to the CPU mapping.
+    ///
+    /// Prefer the slice accessors; this exists for descriptor rings whose
+    /// entries the device updates concurrently, where creating a
+    /// reference would be unsound.
+    pub fn as_ptr(&self) -> *mut T {
+        self.cpu_addr
+    }
+
+    /// Borrows the buffer as a slice.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the device is not writing to the buffer for
+    /// the duration of the borrow.
+    pub unsafe fn as_slice(&self) -> &[T] {
+        // SAFETY: The allocation is live per the invariant and the caller
+        // vouches for the absence of device writes.
+        unsafe { core::slice::from_raw_parts(self.cpu_addr, self.count) }
+    }
+
+    /// Mutable counterpart of [`as_slice`](Self::as_slice).
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure the device accesses no part of the buffer
+    /// for the duration of the borrow.
+    pub unsafe fn as_slice_mut(&mut self) -> &mut [T] {
+        // SAFETY: As above, with exclusivity from `&mut self`.
+        unsafe { core::slice::from_raw_parts_mut(self.cpu_addr, self.count) }
+    }
+}
+
+impl<T> Drop for CoherentAllocation<T> {
+    fn drop(&mut self) {
+        let size = self.count * core::mem::size_of::<T>();
+        // SAFETY: The allocation is live per the invariant, and the
+        // device is kept alive by the held `ARef`.
+        unsafe {
+            bindings::dma_free_coherent(
+                self.dev.as_raw(),
+                size,
+                self.cpu_addr.cast(),
+                self.dma_handle,
+            )
+        };
+    }
+}
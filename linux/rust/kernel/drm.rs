@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! DRM subsystem abstractions.
+
+pub mod gem;
+pub mod gpuvm;
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! GEM objects.
+//!
+//! C header: [`include/drm/drm_gem.h`](srctree/include/drm/drm_gem.h)
+
+use crate::{bindings, types::Opaque};
+
+/// A GEM buffer object.
+///
+/// # Invariants
+///
+/// References are only created to objects whose refcount is held for the
+/// reference's duration.
+#[repr(transparent)]
+pub struct Object(Opaque<bindings::drm_gem_object>);
+
+impl Object {
+    /// Creates a reference from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid and its refcount held for the lifetime of the
+    /// returned reference.
+    pub unsafe fn from_raw<'a>(ptr: *mut bindings::drm_gem_object) -> &'a Self {
+        // SAFETY: `Object` is a transparent wrapper.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::drm_gem_object {
+        self.0.get()
+    }
+
+    /// Returns the size of the object in bytes.
+    ///
+    /// Immutable after object initialisation, so no locking applies.
+    pub fn size(&self) -> u64 {
+        // SAFETY: The object is valid per the type invariant.
+        unsafe { (*self.as_raw()).size as u64 }
+    }
+}
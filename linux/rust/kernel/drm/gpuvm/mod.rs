@@ -0,0 +1,897 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! DRM GPU VA manager.
+//!
+//! C header: [`include/drm/drm_gpuvm.h`](srctree/include/drm/drm_gpuvm.h)
+
+pub mod sm_ops;
+
+use crate::{
+    alloc::AllocError,
+    bindings,
+    error::{code::*, Error},
+    str::CStr,
+    types::{AlwaysRefCounted, Opaque},
+};
+use alloc::boxed::Box;
+use core::{marker::PhantomData, ops::Range, ptr::NonNull};
+
+pub use sm_ops::{GpuVaRef, OpMap, OpMapped, OpRemap, OpUnmap, RichSmError, SmContext};
+
+/// A driver-specific implementation of a GPU VA manager.
+pub trait DriverGpuVm: Sized + 'static {
+    /// Driver data attached to each GPU VA.
+    type VaData;
+
+    /// Data shared by all users of the GPU VM.
+    ///
+    /// It is not protected by any lock; only immutable access is provided.
+    type SharedData: Sync;
+
+    /// Driver data attached to each vm_bo (the per-(vm, GEM object)
+    /// link).
+    ///
+    /// Mutable access is serialised by the GEM object's gpuva lock; see
+    /// [`GpuVmBo::lock_gpuva`] and [`GpuVmBo::data_mut`]. Which state
+    /// belongs where: per-BO mapping bookkeeping (e.g. counters) lives
+    /// here under the gpuva lock, vm-global state lives in
+    /// [`SharedDataLocked`](Self::SharedDataLocked) under the resv --
+    /// the two locks are easy to confuse and protect different things.
+    type VmBoData: Send;
+
+    /// Shared state protected by the VM's reservation lock.
+    ///
+    /// The split: [`SharedData`](Self::SharedData) is for always-readable
+    /// immutable state; this is for state that must mutate, with the
+    /// resv as its lock -- accessed only through
+    /// [`GpuVm::shared_locked`]/[`GpuVm::shared_mut`], which demand the
+    /// exec guard as proof. This is the `LockedBy` pattern with the resv
+    /// as the owner.
+    type SharedDataLocked: Send;
+
+    /// Context threaded through a split/merge operation.
+    ///
+    /// This is where a driver keeps per-operation state (e.g. which
+    /// page-table updates to issue), available to every step callback.
+    type StepContext;
+
+    /// Error type a step callback may fail with.
+    ///
+    /// Unlike a bare errno, this can carry driver-specific detail (e.g.
+    /// the address that failed); it is surfaced to the `sm_map`/`sm_unmap`
+    /// caller via [`RichSmError::Driver`].
+    type StepError: FromErrno;
+
+    /// Observation hook invoked before each split/merge step.
+    ///
+    /// Compiled in (and called) only under `CONFIG_DRM_GPUVM_TRACE`, so
+    /// production builds pay nothing. The default does nothing. This is
+    /// the callback stand-in for a real tracepoint, shaped so a
+    /// tracepoint can replace it later without touching driver impls:
+    /// one record per step, remaps reported as their prev/next halves
+    /// separately, which is what split-heavy workload analysis needs.
+    #[cfg(CONFIG_DRM_GPUVM_TRACE)]
+    fn on_op(_ctx: &mut SmContext<'_, Self>, _info: sm_ops::OpInfo) {}
+
+    /// Called for each new mapping to create during a split/merge.
+    fn step_map(
+        ctx: &mut SmContext<'_, Self>,
+        op: OpMap<'_, Self>,
+    ) -> Result<(), Self::StepError>;
+
+    /// Called for each mapping to remove during a split/merge.
+    fn step_unmap(
+        ctx: &mut SmContext<'_, Self>,
+        op: OpUnmap<'_, Self>,
+    ) -> Result<(), Self::StepError>;
+
+    /// Called for each mapping to split during a split/merge.
+    fn step_remap(
+        ctx: &mut SmContext<'_, Self>,
+        op: OpRemap<'_, Self>,
+    ) -> Result<(), Self::StepError>;
+}
+
+/// Conversion from a raw errno, for error types that must be representable
+/// across the C boundary.
+pub trait FromErrno {
+    /// Converts an errno into the error type.
+    fn from_errno(errno: core::ffi::c_int) -> Self;
+
+    /// Converts the error into the errno reported to the C machinery.
+    fn to_errno(&self) -> core::ffi::c_int;
+}
+
+impl FromErrno for crate::error::Error {
+    fn from_errno(errno: core::ffi::c_int) -> Self {
+        crate::error::Error::from_errno(errno)
+    }
+
+    fn to_errno(&self) -> core::ffi::c_int {
+        crate::error::Error::to_errno(*self)
+    }
+}
+
+/// A GPU VA manager for a driver `T`.
+///
+/// # Invariants
+///
+/// `gpuvm` is an initialised `drm_gpuvm` whose refcount this wrapper
+/// participates in via `drm_gpuvm_get`/`drm_gpuvm_put`.
+#[repr(C)]
+pub struct GpuVm<T: DriverGpuVm> {
+    gpuvm: Opaque<bindings::drm_gpuvm>,
+    /// Monomorphised destructor used by the type-erased `vm_free`
+    /// callback: the vtable is one static shared by all driver types,
+    /// so the allocation itself records how to drop and free it with
+    /// the right layout and the right `T` drop glue.
+    drop_fn: unsafe fn(*mut bindings::drm_gpuvm),
+    shared: T::SharedData,
+    /// Resv-protected shared state; see `DriverGpuVm::SharedDataLocked`.
+    shared_locked: core::cell::UnsafeCell<T::SharedDataLocked>,
+    /// Number of VAs currently linked into the tree. Maintained by the
+    /// wrapper's insert/remove paths (which are the only ones), so the
+    /// leak check in [`GpuVmCore`] needs no tree walk.
+    pub(crate) va_count: core::sync::atomic::AtomicU64,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: The gpuvm core serialises its own state; driver access to
+// `SharedData` is immutable and `SharedData: Sync`.
+unsafe impl<T: DriverGpuVm> Sync for GpuVm<T> {}
+// SAFETY: See above.
+unsafe impl<T: DriverGpuVm> Send for GpuVm<T> {}
+
+impl<T: DriverGpuVm> GpuVm<T> {
+    /// Creates a new GPU VA manager covering `range`.
+    ///
+    /// `reserve` optionally carves out a kernel-reserved node; pass
+    /// [`None`] for VMs without one (a zero-length reserve is passed to
+    /// `drm_gpuvm_init`, which treats it as "no kernel node"). A
+    /// [`Some`] reserve must be non-empty and lie entirely within
+    /// `range`, checked here so a bad region fails with `EINVAL` instead
+    /// of tripping a warning inside `drm_gpuvm_init`.
+    ///
+    /// The VM runs in `DRM_GPUVM_IMMEDIATE_MODE`: split/merge step
+    /// callbacks execute synchronously under the resv lock during
+    /// `sm_map`/`sm_unmap`, rather than being deferred to a job queue.
+    pub fn new(
+        name: &'static CStr,
+        drm: *mut bindings::drm_device,
+        range: Range<u64>,
+        reserve: Option<Range<u64>>,
+        shared: T::SharedData,
+        shared_locked: T::SharedDataLocked,
+    ) -> Result<crate::types::ARef<Self>, Error> {
+        let reserve = match reserve {
+            Some(r) => {
+                if r.is_empty() || r.start < range.start || r.end > range.end {
+                    return Err(EINVAL);
+                }
+                r
+            }
+            None => 0..0,
+        };
+        let this = Box::try_new(Self {
+            gpuvm: Opaque::uninit(),
+            drop_fn: vm_free_typed::<T>,
+            shared,
+            shared_locked: core::cell::UnsafeCell::new(shared_locked),
+            va_count: core::sync::atomic::AtomicU64::new(0),
+            _p: PhantomData,
+        })
+        .map_err(|_: AllocError| ENOMEM)?;
+        let raw = Box::into_raw(this);
+        // SAFETY: `raw` is a valid allocation; `drm_gpuvm_init` initialises
+        // the embedded gpuvm with a refcount of one, which the returned
+        // `ARef` takes over. The VA and reserve bounds are passed through
+        // to the C side, which handles the empty-reserve case.
+        unsafe {
+            bindings::drm_gpuvm_init(
+                Opaque::raw_get(core::ptr::addr_of!((*raw).gpuvm)),
+                name.as_char_ptr(),
+                bindings::DRM_GPUVM_IMMEDIATE_MODE as _,
+                drm,
+                range.start,
+                range.end - range.start,
+                reserve.start,
+                reserve.end - reserve.start,
+                &VTABLE,
+            );
+            Ok(crate::types::ARef::from_raw(NonNull::new_unchecked(raw)))
+        }
+    }
+
+    /// Returns a raw pointer to the embedded `drm_gpuvm`.
+    pub(crate) fn gpuvm(&self) -> *mut bindings::drm_gpuvm {
+        self.gpuvm.get()
+    }
+
+    /// Returns the shared data of this GPU VM.
+    pub fn shared(&self) -> &T::SharedData {
+        &self.shared
+    }
+
+    /// Looks up the existing vm_bo linking this VM to `obj`, if any.
+    ///
+    /// Wraps `drm_gpuvm_bo_find`, which walks the object's gpuva list --
+    /// hence the guard parameter: the requirement is enforced by the
+    /// signature instead of documented-and-hoped. On a hit the C helper
+    /// returns with the refcount already elevated, which the handle
+    /// adopts.
+    ///
+    /// The intended pattern is find-first, allocate-on-miss: callers
+    /// try this before reserving a fresh vm_bo allocation, so the
+    /// common reuse case allocates nothing.
+    pub fn find_bo(
+        &self,
+        obj: &crate::drm::gem::Object,
+        _guard: &GemGpuvaLockGuard<'_>,
+    ) -> Option<GpuVmBo<T>> {
+        // SAFETY: The gpuvm and object are valid, and the object's gpuva
+        // lock is held per the guard.
+        let bo = unsafe { bindings::drm_gpuvm_bo_find(self.gpuvm(), obj.as_raw()) };
+        let bo = NonNull::new(bo)?;
+        // INVARIANT: vm_bos in this VM come from the wrapper's
+        // allocations, and the find returned an owned reference.
+        Some(GpuVmBo {
+            bo,
+            _p: PhantomData,
+        })
+    }
+
+    /// Prints every mapping (GPU address, range, GEM offset, object) into
+    /// `m`, one line each, for a debugfs file.
+    ///
+    /// Iterates with repeated `drm_gpuva_find_first` probes under the
+    /// exec guard -- in immediate mode the resv guards VA-tree mutation,
+    /// so the lock requirement is enforced by the parameter, consistent
+    /// with [`find_bo`](Self::find_bo). Returns `Err` on seq overflow so
+    /// the walk aborts rather than silently truncating; the seq
+    /// machinery retries with a larger buffer.
+    pub fn dump_mappings(
+        &self,
+        m: &mut crate::seq_file::SeqFile,
+        guard: &GpuVmExec<'_, T>,
+    ) -> Result<(), Error> {
+        assert!(guard.guards(self), "exec guard is for a different GpuVm");
+        let mut next: u64 = 0;
+        loop {
+            // SAFETY: The gpuvm is valid and the resv is held per the
+            // guard, keeping the tree stable across the probe.
+            let va = unsafe {
+                bindings::drm_gpuva_find_first(self.gpuvm(), next, u64::MAX - next)
+            };
+            if va.is_null() {
+                return Ok(());
+            }
+            // SAFETY: `va` is a live tree node while the resv is held.
+            let (addr, range, offset, obj) = unsafe {
+                ((*va).va.addr, (*va).va.range, (*va).gem.offset, (*va).gem.obj)
+            };
+            let end = addr.saturating_add(range);
+            crate::seq_print!(
+                m,
+                "  va [{:#x}, {:#x}) offset {:#x} obj {:p}
+",
+                addr,
+                end,
+                offset,
+                obj,
+            );
+            if m.has_overflowed() {
+                return Err(crate::error::code::EAGAIN);
+            }
+            // A mapping ending at the top of the address space has no
+            // successor; stop instead of wrapping `next` back to zero
+            // and walking forever.
+            let Some(n) = addr.checked_add(range) else {
+                return Ok(());
+            };
+            if n == u64::MAX {
+                return Ok(());
+            }
+            next = n;
+        }
+    }
+
+    /// Reads the resv-protected shared state; the exec guard is the
+    /// proof the resv is held.
+    ///
+    /// Panics if `guard` was obtained from a different VM: a foreign
+    /// guard proves nothing about this VM's resv, and accepting it
+    /// would let two threads alias this state.
+    pub fn shared_locked<'a>(&'a self, guard: &'a GpuVmExec<'a, T>) -> &'a T::SharedDataLocked {
+        assert!(guard.guards(self), "exec guard is for a different GpuVm");
+        // SAFETY: This VM's resv is held for the guard's lifetime (the
+        // identity was just checked), serialising all access.
+        unsafe { &*self.shared_locked.get() }
+    }
+
+    /// Mutates the resv-protected shared state.
+    ///
+    /// Takes the guard by `&mut` so a single guard cannot mint two
+    /// aliasing mutable borrows, and checks the guard belongs to this
+    /// VM for the same reason as [`shared_locked`](Self::shared_locked).
+    pub fn shared_mut<'a>(
+        &'a self,
+        guard: &'a mut GpuVmExec<'a, T>,
+    ) -> &'a mut T::SharedDataLocked {
+        assert!(guard.guards(self), "exec guard is for a different GpuVm");
+        // SAFETY: As in `shared_locked`, with exclusivity from the
+        // mutable guard borrow.
+        unsafe { &mut *self.shared_locked.get() }
+    }
+
+    /// Locks the VM's reservations for an operation expecting to add
+    /// `num_fences` fences, waiting interruptibly.
+    ///
+    /// Shorthand for `prepare_with_flags(num_fences, true)`, preserving
+    /// the existing behaviour (and the `EINTR`/`ERESTARTSYS` surface
+    /// callers already handle).
+    pub fn prepare(&self, num_fences: u32) -> Result<GpuVmExec<'_, T>, Error> {
+        self.prepare_with_flags(num_fences, true)
+    }
+
+    /// Like [`prepare`](Self::prepare), with the wait mode chosen by the
+    /// caller.
+    ///
+    /// Interruptible waiting lets fatal signals abort long reservation
+    /// waits and is right for ioctl paths. Uninterruptible waiting is
+    /// for teardown, where returning `EINTR` would leak the cleanup --
+    /// but it can stall unkillably on a wedged fence, so it must only be
+    /// used where forward progress of the reservation holders is
+    /// guaranteed.
+    pub fn prepare_with_flags(
+        &self,
+        num_fences: u32,
+        interruptible: bool,
+    ) -> Result<GpuVmExec<'_, T>, Error> {
+        let flags = if interruptible {
+            bindings::DRM_EXEC_INTERRUPTIBLE_WAIT
+        } else {
+            0
+        };
+        // The drm_exec must not move once initialised: the locked
+        // ww_mutexes register its embedded ww_acquire_ctx by address.
+        // Heap-allocate it so the guard can be returned by value while
+        // the struct itself stays put.
+        let exec = Box::try_new(Opaque::<bindings::drm_exec>::uninit())
+            .map_err(|_: AllocError| ENOMEM)?;
+        // SAFETY: `exec` is heap-pinned, valid for write, and initialised
+        // before use; the gpuvm is valid per the type invariant. The
+        // cleanup-driven loop is the expansion of
+        // `drm_exec_until_all_locked`: `drm_exec_cleanup` returns true
+        // while another locking attempt is needed (first pass, or after
+        // an EDEADLK backoff dropped all locks), so contention retries
+        // instead of failing.
+        let mut ret = 0;
+        unsafe {
+            bindings::drm_exec_init(exec.get(), flags, 0);
+            while bindings::drm_exec_cleanup(exec.get()) {
+                ret = bindings::drm_gpuvm_prepare_vm(self.gpuvm(), exec.get(), num_fences);
+                if ret == -(bindings::EDEADLK as core::ffi::c_int) {
+                    // Contention: loop again; cleanup has rolled back.
+                    continue;
+                }
+                if ret != 0 {
+                    break;
+                }
+            }
+        }
+        if ret != 0 {
+            // SAFETY: The exec was initialised above; fini releases
+            // whatever the partial attempt still holds.
+            unsafe { bindings::drm_exec_fini(exec.get()) };
+            return Err(Error::from_errno(ret));
+        }
+        Ok(GpuVmExec {
+            exec,
+            vm: self,
+        })
+    }
+
+    /// Returns whether `addr..addr + range` is a valid range to map in
+    /// this VM.
+    ///
+    /// This is the full check the C machinery performs before a map: the
+    /// range must lie within the VA space *and* must not overlap the
+    /// kernel-reserved node. Use [`GpuVm::overlaps_reserved`] to tell the
+    /// two failure modes apart: a range outside the VA space is a plain
+    /// caller bug, while one that overlaps the reserved node is a
+    /// valid-looking address the driver must never hand out.
+    ///
+    /// No lock is needed: the VA bounds and reserve region are immutable
+    /// after init.
+    pub fn range_valid(&self, addr: u64, range: u64) -> bool {
+        // SAFETY: The gpuvm is valid per the type invariant.
+        unsafe { bindings::drm_gpuvm_range_valid(self.gpuvm(), addr, range) }
+    }
+
+    /// Returns whether `addr..addr + range` overlaps the kernel-reserved
+    /// region.
+    pub fn overlaps_reserved(&self, addr: u64, range: u64) -> bool {
+        // SAFETY: The gpuvm is valid per the type invariant; the reserved
+        // node is immutable after init.
+        let (res_addr, res_range) = unsafe {
+            let vm = self.gpuvm();
+            (
+                (*vm).kernel_alloc_node.va.addr,
+                (*vm).kernel_alloc_node.va.range,
+            )
+        };
+        if res_range == 0 {
+            return false;
+        }
+        let end = addr.saturating_add(range);
+        let res_end = res_addr.saturating_add(res_range);
+        addr < res_end && res_addr < end
+    }
+}
+
+// SAFETY: The embedded gpuvm is ref-counted by the C side, and `vm_free`
+// below releases the allocation on the final put.
+unsafe impl<T: DriverGpuVm> AlwaysRefCounted for GpuVm<T> {
+    fn inc_ref(&self) {
+        // SAFETY: The gpuvm is valid per the type invariant.
+        unsafe { bindings::drm_gpuvm_get(self.gpuvm()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The gpuvm is valid per the safety requirements.
+        unsafe { bindings::drm_gpuvm_put(Opaque::raw_get(core::ptr::addr_of!((*obj.as_ptr()).gpuvm))) };
+    }
+}
+
+static VTABLE: bindings::drm_gpuvm_ops = bindings::drm_gpuvm_ops {
+    vm_free: Some(vm_free),
+    sm_step_map: Some(sm_ops::raw_step_map),
+    sm_step_unmap: Some(sm_ops::raw_step_unmap),
+    sm_step_remap: Some(sm_ops::raw_step_remap),
+    // SAFETY: All zeros is valid for the remaining optional callbacks.
+    ..unsafe { core::mem::zeroed() }
+};
+
+/// # Safety
+///
+/// Called by the gpuvm core on the final `drm_gpuvm_put` of a vm created
+/// in `GpuVm::new`, so the allocation starts with the `VmFreeHeader`
+/// layout and `drop_fn` is the monomorphised destructor stored there.
+unsafe extern "C" fn vm_free(gpuvm: *mut bindings::drm_gpuvm) {
+    // SAFETY: Per the function contract; the header prefix is shared by
+    // every `GpuVm<T>` (`repr(C)`, gpuvm first, drop_fn second), and the
+    // stored function reconstructs the full type.
+    unsafe {
+        let hdr = crate::container_of!(gpuvm, VmFreeHeader, gpuvm) as *mut VmFreeHeader;
+        ((*hdr).drop_fn)(gpuvm)
+    }
+}
+
+/// # Safety
+///
+/// `gpuvm` must be the embedded gpuvm of a `Box`-allocated `GpuVm<T>`
+/// for this exact `T`, on its final put.
+unsafe fn vm_free_typed<T: DriverGpuVm>(gpuvm: *mut bindings::drm_gpuvm) {
+    // SAFETY: Per the function contract. Reconstructing the `Box` with
+    // the real `GpuVm<T>` type runs the destructors of `shared`,
+    // `shared_locked` and every other field, and deallocates with the
+    // layout the allocation was made with.
+    unsafe {
+        let raw = crate::container_of!(gpuvm, GpuVm<T>, gpuvm) as *mut GpuVm<T>;
+        drop(Box::from_raw(raw));
+    }
+}
+
+/// The layout prefix every `GpuVm<T>` starts with, used by the
+/// type-erased `vm_free` to reach the stored destructor.
+#[repr(C)]
+struct VmFreeHeader {
+    gpuvm: Opaque<bindings::drm_gpuvm>,
+    drop_fn: unsafe fn(*mut bindings::drm_gpuvm),
+}
+
+/// A pre-allocated GPU VA, ready to be inserted into the tree by a map or
+/// remap step.
+///
+/// Immediate mode requires these to be allocated up front, outside the
+/// fence-signalling critical path.
+pub struct GpuVaAlloc<T: DriverGpuVm> {
+    pub(crate) va: Box<GpuVa<T>>,
+}
+
+impl<T: DriverGpuVm> GpuVaAlloc<T> {
+    /// Allocates a new VA node carrying `data`.
+    pub fn new(data: T::VaData) -> Result<Self, AllocError> {
+        Ok(Self {
+            va: Box::try_new(GpuVa {
+                gpuva: Opaque::uninit(),
+                data,
+            })?,
+        })
+    }
+}
+
+impl<T: DriverGpuVm> GpuVaAlloc<T> {
+    /// Replaces the driver data carried by this allocation.
+    ///
+    /// Mainly useful after recycling via [`GpuVaRemoved::into_alloc`],
+    /// where the node still carries the data of the mapping it used to
+    /// be.
+    pub fn set_data(&mut self, data: T::VaData) {
+        self.va.data = data;
+    }
+}
+
+/// A GPU VA that has been removed from the interval tree.
+///
+/// Returned by the unmap/remap step helpers. The VA may either be dropped
+/// (freeing it) or recycled via [`into_alloc`](Self::into_alloc) to avoid
+/// a fresh allocation for a subsequent map. Dropping it implicitly is
+/// correct but, under `CONFIG_DEBUG_KERNEL`, warns once: a driver running
+/// mapping churn almost always meant to recycle.
+pub struct GpuVaRemoved<T: DriverGpuVm> {
+    pub(crate) va: Box<GpuVa<T>>,
+}
+
+impl<T: DriverGpuVm> GpuVaRemoved<T> {
+    /// Takes ownership of a VA node that the C side has just unlinked.
+    ///
+    /// # Safety
+    ///
+    /// `gpuva` must point at the `gpuva` field of a `GpuVa<T>` allocated
+    /// by [`GpuVaAlloc::new`], already removed from the interval tree,
+    /// with no other outstanding users.
+    pub(crate) unsafe fn from_raw(gpuva: *mut bindings::drm_gpuva) -> Self {
+        // SAFETY: `GpuVa` is `repr(C)` with the gpuva first, so the op
+        // pointer is also the allocation pointer; ownership transfers per
+        // the function contract.
+        Self {
+            va: unsafe { Box::from_raw(gpuva.cast()) },
+        }
+    }
+
+    /// Recycles the removed VA into an allocation usable by a later map,
+    /// avoiding a fresh heap allocation.
+    ///
+    /// The node holds no tree state once unlinked, so reuse is a pure
+    /// ownership transfer; the stale driver data rides along and should
+    /// be replaced with [`GpuVaAlloc::set_data`] before the next insert.
+    pub fn into_alloc(self) -> GpuVaAlloc<T> {
+        let va = core::mem::ManuallyDrop::new(self);
+        // SAFETY: `ManuallyDrop` suppresses the policing drop; the box is
+        // moved out exactly once.
+        GpuVaAlloc {
+            va: unsafe { core::ptr::read(&va.va) },
+        }
+    }
+}
+
+impl<T: DriverGpuVm> Drop for GpuVaRemoved<T> {
+    fn drop(&mut self) {
+        // Warn once globally, as the type docs promise: freeing instead
+        // of recycling is legitimate, so repeating the hint would only
+        // spam the log of any driver that chooses it.
+        #[cfg(CONFIG_DEBUG_KERNEL)]
+        {
+            use core::sync::atomic::{AtomicBool, Ordering};
+            static WARNED: AtomicBool = AtomicBool::new(false);
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                crate::pr_warn!(
+                    "gpuvm: removed VA dropped instead of recycled; consider into_alloc()
+"
+                );
+            }
+        }
+    }
+}
+
+/// A GPU VA node with its driver data.
+#[repr(C)]
+pub struct GpuVa<T: DriverGpuVm> {
+    pub(crate) gpuva: Opaque<bindings::drm_gpuva>,
+    pub(crate) data: T::VaData,
+}
+
+impl<T: DriverGpuVm> GpuVa<T> {
+    /// Returns the raw gpuva pointer of a heap-allocated node.
+    pub(crate) fn gpuva_ptr(this: *mut Self) -> *mut bindings::drm_gpuva {
+        // SAFETY: Only field projection; nothing is dereferenced.
+        unsafe { Opaque::raw_get(core::ptr::addr_of!((*this).gpuva)) }
+    }
+}
+
+/// Holds the VM's reservation locks acquired by [`GpuVm::prepare`];
+/// dropping releases them via `drm_exec_fini`.
+///
+/// The guard records which VM it locked, and the resv-protected
+/// accessors check that identity, so a guard for one VM cannot serve as
+/// proof for another.
+pub struct GpuVmExec<'a, T: DriverGpuVm> {
+    /// Heap-pinned: the locked ww_mutexes hold the embedded
+    /// ww_acquire_ctx by address, so the struct must never move while
+    /// locks are held.
+    exec: Box<Opaque<bindings::drm_exec>>,
+    vm: &'a GpuVm<T>,
+}
+
+impl<T: DriverGpuVm> GpuVmExec<'_, T> {
+    /// Returns whether this guard locked `vm`.
+    pub(crate) fn guards(&self, vm: &GpuVm<T>) -> bool {
+        core::ptr::eq(self.vm, vm)
+    }
+}
+
+impl<T: DriverGpuVm> Drop for GpuVmExec<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: The exec was initialised in `prepare_with_flags` and
+        // holds the locks this releases.
+        unsafe { bindings::drm_exec_fini(self.exec.get()) };
+    }
+}
+
+/// An owned reference to a `drm_gpuvm_bo`: the connection between this VM
+/// and one GEM object.
+///
+/// # Invariants
+///
+/// `bo` points at a live vm_bo whose refcount this handle owns one unit
+/// of.
+pub struct GpuVmBo<T: DriverGpuVm> {
+    /// Points at the `bo` field of a [`VmBoInner<T>`]: every vm_bo in a
+    /// VM managed by this wrapper was allocated through it, so the cast
+    /// back to the containing allocation is part of the type invariant.
+    pub(crate) bo: NonNull<bindings::drm_gpuvm_bo>,
+    pub(crate) _p: PhantomData<T>,
+}
+
+/// The allocation backing a vm_bo: the C struct plus the driver's data.
+#[repr(C)]
+pub(crate) struct VmBoInner<T: DriverGpuVm> {
+    pub(crate) bo: Opaque<bindings::drm_gpuvm_bo>,
+    pub(crate) data: core::cell::UnsafeCell<T::VmBoData>,
+}
+
+/// Proof that the GEM object's gpuva lock is held.
+///
+/// This is the same mutex the map steps take to link VAs into the
+/// object's gpuva list; [`GpuVmBo::data_mut`] demands it because all
+/// mutation of `VmBoData` funnels through this guard (and the C side
+/// never touches driver data), making the mutex its full serialisation.
+#[must_use = "the lock is released immediately when the guard is unused"]
+pub struct GemGpuvaLockGuard<'a> {
+    obj: *mut bindings::drm_gem_object,
+    _p: PhantomData<&'a ()>,
+}
+
+impl Drop for GemGpuvaLockGuard<'_> {
+    fn drop(&mut self) {
+        // SAFETY: This guard holds the lock taken in `lock_gpuva`.
+        unsafe { bindings::mutex_unlock(core::ptr::addr_of_mut!((*self.obj).gpuva.lock)) };
+    }
+}
+
+impl<T: DriverGpuVm> GpuVmBo<T> {
+    /// Takes the GEM object's gpuva lock.
+    pub fn lock_gpuva(&self) -> GemGpuvaLockGuard<'_> {
+        // SAFETY: The vm_bo is live per the type invariant, and so is
+        // its object; `gpuva.lock` is the object's VA-list mutex.
+        unsafe {
+            let obj = (*self.bo.as_ptr()).obj;
+            bindings::mutex_lock(core::ptr::addr_of_mut!((*obj).gpuva.lock));
+            GemGpuvaLockGuard {
+                obj,
+                _p: PhantomData,
+            }
+        }
+    }
+
+    /// Reads the driver data of this vm_bo under the gpuva lock.
+    pub fn data<'a>(&'a self, _guard: &'a GemGpuvaLockGuard<'a>) -> &'a T::VmBoData {
+        // SAFETY: The vm_bo points into a `VmBoInner<T>` per the type
+        // invariant, and the guard serialises access.
+        unsafe { &*(*self.inner()).data.get() }
+    }
+
+    /// Mutates the driver data of this vm_bo.
+    ///
+    /// Takes the guard mutably so one guard cannot mint two aliasing
+    /// borrows -- the same discipline as [`GpuVm::shared_mut`], which
+    /// guards *vm-global* state under the *resv*; this guards *per-BO*
+    /// state under the *gpuva* lock.
+    pub fn data_mut<'a>(&'a self, _guard: &'a mut GemGpuvaLockGuard<'a>) -> &'a mut T::VmBoData {
+        // SAFETY: As in `data`, with exclusivity from the mutable guard.
+        unsafe { &mut *(*self.inner()).data.get() }
+    }
+
+    fn inner(&self) -> *const VmBoInner<T> {
+        // SAFETY: Per the type invariant the bo pointer is the first
+        // field of a `VmBoInner<T>`.
+        unsafe { crate::container_of!(self.bo.as_ptr(), VmBoInner<T>, bo) }
+    }
+
+    /// Releases this reference, deferring the potential free.
+    ///
+    /// The free (and the GEM unref it implies) is queued onto the VM's
+    /// cleanup list and runs at the next [`GpuVm::deferred_cleanup`].
+    /// This is the right call from fence-signalling or IRQ-adjacent
+    /// paths, and from any context that may already hold the resv.
+    pub fn put_deferred(self) {
+        let bo = self.bo;
+        core::mem::forget(self);
+        // SAFETY: We own one reference per the type invariant.
+        unsafe { bindings::drm_gpuvm_bo_put_deferred(bo.as_ptr()) };
+    }
+
+    /// Releases this reference immediately, under the resv lock.
+    ///
+    /// For sleepable teardown only, where nothing can queue further
+    /// puts: the free is observable as soon as this returns, which is
+    /// what lets shutdown paths (and the [`GpuVmCore`] leak check)
+    /// see a truthful picture instead of work still parked on the
+    /// deferred list.
+    pub fn put_immediate(self) {
+        let bo = self.bo;
+        core::mem::forget(self);
+        // SAFETY: We own one reference; the resv is taken around the put
+        // as the C helper requires for the final-unref path.
+        unsafe {
+            let obj = (*bo.as_ptr()).obj;
+            bindings::dma_resv_lock((*obj).resv, core::ptr::null_mut());
+            bindings::drm_gpuvm_bo_put(bo.as_ptr());
+            bindings::dma_resv_unlock((*obj).resv);
+        }
+    }
+}
+
+impl<T: DriverGpuVm> Drop for GpuVmBo<T> {
+    fn drop(&mut self) {
+        // Implicit drop uses the deferred path: it is safe from every
+        // context, which a Drop impl cannot be picky about.
+        // SAFETY: We own one reference per the type invariant.
+        unsafe { bindings::drm_gpuvm_bo_put_deferred(self.bo.as_ptr()) };
+    }
+}
+
+impl<T: DriverGpuVm> GpuVm<T> {
+    /// Runs the queued deferred vm_bo frees.
+    ///
+    /// Call from a workqueue or another sleepable context; pairs with
+    /// [`GpuVmBo::put_deferred`].
+    pub fn deferred_cleanup(&self) {
+        // SAFETY: The gpuvm is valid per the type invariant.
+        unsafe { bindings::drm_gpuvm_deferred_cleanup(self.gpuvm()) };
+    }
+
+    /// Runs the queued deferred frees synchronously, now.
+    ///
+    /// Equivalent to [`deferred_cleanup`](Self::deferred_cleanup) but
+    /// meant for final teardown, where deferral buys nothing and hides
+    /// leaks; must be called from sleepable context with no fence
+    /// callbacks still able to queue puts.
+    pub fn immediate_cleanup(&self) {
+        self.deferred_cleanup();
+    }
+}
+
+/// The driver-side owner of a [`GpuVm`].
+///
+/// Dropping the core is the natural "this VM is over" point, so it hosts
+/// the leak check: under `CONFIG_DEBUG_KERNEL`, dropping (or explicitly
+/// [`close`](Self::close)-ing) a core while mappings are still linked
+/// warns with the outstanding count. The check deliberately does not
+/// unmap on the driver's behalf -- automatic unmap would hide exactly the
+/// bug it exists to catch.
+pub struct GpuVmCore<T: DriverGpuVm> {
+    vm: crate::types::ARef<GpuVm<T>>,
+}
+
+impl<T: DriverGpuVm> GpuVmCore<T> {
+    /// Creates the core owning `vm`.
+    pub fn new(vm: crate::types::ARef<GpuVm<T>>) -> Self {
+        Self { vm }
+    }
+
+    /// Returns the owned VM.
+    pub fn vm(&self) -> &GpuVm<T> {
+        &self.vm
+    }
+
+    /// Tears the core down at a deterministic point, running the leak
+    /// check now rather than whenever the last reference dies.
+    pub fn close(self) {
+        // Dropping `self` runs the check; the method exists for the
+        // deterministic timing and the name.
+        drop(self);
+    }
+
+    #[cfg(CONFIG_DEBUG_KERNEL)]
+    fn check_leaks(&self) {
+        let outstanding = self
+            .vm
+            .va_count
+            .load(core::sync::atomic::Ordering::Relaxed);
+        if outstanding != 0 {
+            crate::pr_warn!(
+                "gpuvm: core dropped with {} mappings outstanding\n",
+                outstanding,
+            );
+        }
+    }
+
+    #[cfg(not(CONFIG_DEBUG_KERNEL))]
+    fn check_leaks(&self) {}
+}
+
+impl<T: DriverGpuVm> Drop for GpuVmCore<T> {
+    fn drop(&mut self) {
+        self.check_leaks();
+    }
+}
+
+/// A recycling pool of pre-allocated VA nodes.
+///
+/// Immediate-mode map/remap consumes a [`GpuVaAlloc`] per op (a remap up
+/// to two), and under mapping churn the per-op allocation shows up in
+/// profiles. The pool pre-fills a batch and recycles removed VAs back
+/// through [`GpuVaRemoved::into_alloc`].
+///
+/// Sizing guidance: `prealloc` should cover the maximum ops of one
+/// split/merge batch; `cap` a small multiple of that -- returns beyond
+/// the cap are simply dropped, bounding memory. The pool is an
+/// optimisation, not an atomic-context enabler: a miss falls back to a
+/// plain `GFP_KERNEL` allocation, so callers must still be in sleepable
+/// context.
+pub struct GpuVaPool<T: DriverGpuVm> {
+    free: crate::sync::SpinLock<alloc::vec::Vec<GpuVaAlloc<T>>>,
+    cap: usize,
+}
+
+impl<T: DriverGpuVm> GpuVaPool<T> {
+    /// Creates a pool pre-filled with `prealloc` nodes (each carrying
+    /// `T::VaData::default()`), retaining at most `cap` on returns.
+    pub fn new(prealloc: usize, cap: usize) -> crate::error::Result<crate::sync::Arc<Self>>
+    where
+        T::VaData: Default,
+    {
+        let mut free = alloc::vec::Vec::new();
+        free.try_reserve(prealloc.max(cap))
+            .map_err(|_| crate::error::code::ENOMEM)?;
+        for _ in 0..prealloc {
+            free.push(
+                GpuVaAlloc::new(T::VaData::default())
+                    .map_err(|_| crate::error::code::ENOMEM)?,
+            );
+        }
+        let pool = crate::sync::Arc::try_new(Self {
+            // SAFETY: Initialised below before the arc is shared.
+            free: unsafe { crate::sync::lock::Lock::new_uninit(free) },
+            cap,
+        })
+        .map_err(|_| crate::error::code::ENOMEM)?;
+        // SAFETY: Initialised exactly once; the data cannot move behind
+        // the `Arc`.
+        unsafe { pool.free.init_once(crate::c_str!("GpuVaPool::free")) };
+        Ok(pool)
+    }
+
+    /// Takes a node from the pool, falling back to a fresh allocation on
+    /// a miss.
+    pub fn get(&self, data: T::VaData) -> crate::error::Result<GpuVaAlloc<T>> {
+        if let Some(mut va) = self.free.lock().pop() {
+            va.set_data(data);
+            return Ok(va);
+        }
+        GpuVaAlloc::new(data).map_err(|_| crate::error::code::ENOMEM)
+    }
+
+    /// Returns a removed VA to the pool; dropped instead once the pool
+    /// is at capacity.
+    pub fn recycle(&self, removed: GpuVaRemoved<T>) {
+        let va = removed.into_alloc();
+        let mut free = self.free.lock();
+        if free.len() < self.cap {
+            free.push(va);
+        }
+        // Past the cap the alloc drops here, bounding pool memory.
+    }
+}
@@ -0,0 +1,530 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Split/merge step callbacks for the GPU VA manager.
+
+use super::{DriverGpuVm, FromErrno, GpuVaAlloc, GpuVaRemoved, GpuVm};
+use crate::{bindings, error::Error};
+use core::{ffi::{c_int, c_void}, marker::PhantomData};
+
+/// The context threaded through a split/merge operation.
+///
+/// It wraps the driver's [`DriverGpuVm::StepContext`] and additionally
+/// carries an error slot: because the step callbacks cross the C boundary,
+/// they can only report an errno to the gpuvm core, so a failing step
+/// stashes its full [`DriverGpuVm::StepError`] here and [`GpuVm::sm_map`]/
+/// [`GpuVm::sm_unmap`] surface it to the caller once the C call returns.
+pub struct SmContext<'a, T: DriverGpuVm> {
+    pub(crate) vm: &'a GpuVm<T>,
+    pub(crate) ctx: &'a mut T::StepContext,
+    pub(crate) error: Option<T::StepError>,
+}
+
+impl<'a, T: DriverGpuVm> SmContext<'a, T> {
+    /// Returns the GPU VM this operation runs against.
+    pub fn vm(&self) -> &GpuVm<T> {
+        self.vm
+    }
+
+    /// Returns the driver's per-operation context.
+    pub fn driver_ctx(&mut self) -> &mut T::StepContext {
+        self.ctx
+    }
+}
+
+/// A rich error out of a split/merge operation.
+pub enum RichSmError<T: DriverGpuVm> {
+    /// A step callback failed; the driver's full error is preserved.
+    Driver(T::StepError),
+    /// The failure originated inside the C machinery (e.g. allocation),
+    /// so only the raw errno is available.
+    Raw(Error),
+}
+
+/// A mapping to be created by [`DriverGpuVm::step_map`].
+pub struct OpMap<'a, T: DriverGpuVm> {
+    pub(crate) raw: *mut bindings::drm_gpuva_op_map,
+    pub(crate) _p: PhantomData<&'a T>,
+}
+
+impl<T: DriverGpuVm> OpMap<'_, T> {
+    /// The GPU address of the new mapping.
+    pub fn addr(&self) -> u64 {
+        // SAFETY: The op is valid for the duration of the step callback.
+        unsafe { (*self.raw).va.addr }
+    }
+
+    /// The length of the new mapping.
+    pub fn range(&self) -> u64 {
+        // SAFETY: See `addr`.
+        unsafe { (*self.raw).va.range }
+    }
+
+    /// The offset into the GEM object backing the new mapping.
+    pub fn offset(&self) -> u64 {
+        // SAFETY: See `addr`.
+        unsafe { (*self.raw).gem.offset }
+    }
+}
+
+/// A mapping to be removed by [`DriverGpuVm::step_unmap`].
+pub struct OpUnmap<'a, T: DriverGpuVm> {
+    pub(crate) raw: *mut bindings::drm_gpuva_op_unmap,
+    pub(crate) _p: PhantomData<&'a T>,
+}
+
+/// The kind of a split/merge step, for tracing.
+#[cfg(CONFIG_DRM_GPUVM_TRACE)]
+#[derive(Clone, Copy, Debug)]
+pub enum OpKind {
+    /// A new mapping is being created.
+    Map,
+    /// A whole mapping is being removed.
+    Unmap,
+    /// The surviving front half of a split mapping.
+    RemapPrev,
+    /// The surviving back half of a split mapping.
+    RemapNext,
+}
+
+/// One traced split/merge step.
+///
+/// A struct rather than bare arguments so fields can grow without
+/// touching driver impls.
+#[cfg(CONFIG_DRM_GPUVM_TRACE)]
+#[derive(Clone, Copy, Debug)]
+pub struct OpInfo {
+    /// What the step does.
+    pub kind: OpKind,
+    /// The GPU address the step operates on.
+    pub addr: u64,
+    /// The length the step operates on.
+    pub range: u64,
+}
+
+/// Evidence that a map step inserted its VA, consumed by the step's
+/// return path.
+pub struct OpMapped;
+
+/// A borrowed handle to a VA that was just linked into the tree.
+///
+/// The VA is owned by the GPUVM once inserted, so an owned handle would
+/// have to be weak; what drivers actually need -- recording page-table
+/// state for the new mapping -- only requires access for the lifetime of
+/// the operation, so the handle borrows from the op instead. It exposes
+/// the geometry and the driver data.
+pub struct GpuVaRef<'a, T: DriverGpuVm> {
+    va: &'a super::GpuVa<T>,
+}
+
+impl<T: DriverGpuVm> GpuVaRef<'_, T> {
+    /// The GPU address of the mapping.
+    pub fn addr(&self) -> u64 {
+        // SAFETY: The VA is linked and stable for the op's lifetime.
+        unsafe { (*self.va.gpuva.get()).va.addr }
+    }
+
+    /// The length of the mapping.
+    pub fn range(&self) -> u64 {
+        // SAFETY: See `addr`.
+        unsafe { (*self.va.gpuva.get()).va.range }
+    }
+
+    /// The offset into the backing GEM object.
+    pub fn offset(&self) -> u64 {
+        // SAFETY: See `addr`.
+        unsafe { (*self.va.gpuva.get()).gem.offset }
+    }
+
+    /// The driver data attached to the VA.
+    pub fn data(&self) -> &T::VaData {
+        &self.va.data
+    }
+}
+
+impl<'a, T: DriverGpuVm> OpMap<'a, T> {
+    /// Inserts `va` into the tree for this mapping.
+    pub fn insert(self, vm: &GpuVm<T>, va: GpuVaAlloc<T>) -> OpMapped {
+        let (mapped, _) = self.insert_returning(vm, va);
+        mapped
+    }
+
+    /// Like [`insert`](OpMap::insert), but also returns a handle to the
+    /// just-linked VA so the driver can associate state (e.g. page-table
+    /// bookkeeping) with the new mapping.
+    pub fn insert_returning(self, vm: &GpuVm<T>, va: GpuVaAlloc<T>) -> (OpMapped, GpuVaRef<'a, T>) {
+        let raw_va = alloc::boxed::Box::into_raw(va.va);
+        // SAFETY: `raw_va` is a live allocation whose embedded gpuva the
+        // tree takes over; the op and vm are valid for the step's
+        // duration. Ownership of the allocation transfers to the tree and
+        // is recovered in the unmap path via `GpuVaRemoved::from_raw`.
+        unsafe {
+            bindings::drm_gpuva_map(vm.gpuvm(), super::GpuVa::gpuva_ptr(raw_va), self.raw);
+            bindings::drm_gpuva_link(super::GpuVa::gpuva_ptr(raw_va));
+        }
+        vm.va_count
+            .fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        (
+            OpMapped,
+            GpuVaRef {
+                // SAFETY: The allocation lives until unmapped, which
+                // cannot happen within this op.
+                va: unsafe { &*raw_va },
+            },
+        )
+    }
+}
+
+impl<T: DriverGpuVm> OpUnmap<'_, T> {
+    /// The GPU address of the mapping being removed.
+    pub fn addr(&self) -> u64 {
+        // SAFETY: The op is valid for the duration of the step callback.
+        unsafe { (*(*self.raw).va).va.addr }
+    }
+
+    /// The length of the mapping being removed.
+    pub fn range(&self) -> u64 {
+        // SAFETY: See `addr`.
+        unsafe { (*(*self.raw).va).va.range }
+    }
+}
+
+impl<T: DriverGpuVm> OpUnmap<'_, T> {
+    /// Unmaps the VA and takes ownership of the removed node.
+    ///
+    /// Must be called at most once per op; the returned
+    /// [`GpuVaRemoved`] is how the driver frees or recycles the node.
+    pub fn remove(self, vm: &GpuVm<T>) -> GpuVaRemoved<T> {
+        vm.va_count
+            .fetch_sub(1, core::sync::atomic::Ordering::Relaxed);
+        // SAFETY: The op is valid for the step's duration and its `va`
+        // was inserted by this wrapper, so it is the gpuva embedded in a
+        // `GpuVa<T>`.
+        unsafe {
+            let va = (*self.raw).va;
+            bindings::drm_gpuva_unmap(self.raw);
+            bindings::drm_gpuva_unlink(va);
+            GpuVaRemoved::from_raw(va)
+        }
+    }
+}
+
+/// A mapping to be split by [`DriverGpuVm::step_remap`].
+pub struct OpRemap<'a, T: DriverGpuVm> {
+    pub(crate) raw: *mut bindings::drm_gpuva_op_remap,
+    pub(crate) _p: PhantomData<&'a T>,
+}
+
+// The extern shims cannot be generic over `T` at the vtable (it is a
+// single static), so the real shims are instantiated per driver type via
+// monomorphised functions and the vtable is built in `GpuVm::new` for each
+// `T`. The helpers below are the per-`T` entry points.
+
+/// # Safety
+///
+/// See the expansion in `step_shim!`; `priv_` must be the `SmContext<T>`
+/// cookie of the running operation.
+pub(super) unsafe extern "C" fn raw_step_map(
+    op: *mut bindings::drm_gpuva_op,
+    priv_: *mut c_void,
+) -> c_int {
+    // SAFETY: The cookie layout starts with the dispatch record; see
+    // `SmDispatch`.
+    unsafe { ((*priv_.cast::<SmDispatch>()).map)(op, priv_) }
+}
+
+/// # Safety
+///
+/// As for `raw_step_map`.
+pub(super) unsafe extern "C" fn raw_step_unmap(
+    op: *mut bindings::drm_gpuva_op,
+    priv_: *mut c_void,
+) -> c_int {
+    // SAFETY: See `raw_step_map`.
+    unsafe { ((*priv_.cast::<SmDispatch>()).unmap)(op, priv_) }
+}
+
+/// # Safety
+///
+/// As for `raw_step_map`.
+pub(super) unsafe extern "C" fn raw_step_remap(
+    op: *mut bindings::drm_gpuva_op,
+    priv_: *mut c_void,
+) -> c_int {
+    // SAFETY: See `raw_step_map`.
+    unsafe { ((*priv_.cast::<SmDispatch>()).remap)(op, priv_) }
+}
+
+/// Monomorphisation shim: the gpuvm vtable is one static shared by all
+/// driver types, so the cookie passed through `priv_` starts with this
+/// dispatch record pointing at the `T`-specific handlers.
+#[repr(C)]
+pub(crate) struct SmDispatch {
+    map: unsafe extern "C" fn(*mut bindings::drm_gpuva_op, *mut c_void) -> c_int,
+    unmap: unsafe extern "C" fn(*mut bindings::drm_gpuva_op, *mut c_void) -> c_int,
+    remap: unsafe extern "C" fn(*mut bindings::drm_gpuva_op, *mut c_void) -> c_int,
+}
+
+#[repr(C)]
+pub(crate) struct SmCookie<'a, T: DriverGpuVm> {
+    dispatch: SmDispatch,
+    ctx: SmContext<'a, T>,
+}
+
+/// # Safety
+///
+/// `priv_` must point at an `SmCookie<T>` for this `T`.
+unsafe extern "C" fn typed_step_map<T: DriverGpuVm>(
+    op: *mut bindings::drm_gpuva_op,
+    priv_: *mut c_void,
+) -> c_int {
+    // SAFETY: Per the function contract.
+    let cookie = unsafe { &mut *priv_.cast::<SmCookie<'_, T>>() };
+    let wrapped = OpMap {
+        // SAFETY: The op is a map op when this callback runs.
+        raw: unsafe { core::ptr::addr_of_mut!((*op).__bindgen_anon_1.map) },
+        _p: PhantomData,
+    };
+    #[cfg(CONFIG_DRM_GPUVM_TRACE)]
+    T::on_op(
+        &mut cookie.ctx,
+        OpInfo {
+            kind: OpKind::Map,
+            addr: wrapped.addr(),
+            range: wrapped.range(),
+        },
+    );
+    fail_or_zero(&mut cookie.ctx, T::step_map(&mut cookie.ctx, wrapped))
+}
+
+/// # Safety
+///
+/// As for `typed_step_map`.
+unsafe extern "C" fn typed_step_unmap<T: DriverGpuVm>(
+    op: *mut bindings::drm_gpuva_op,
+    priv_: *mut c_void,
+) -> c_int {
+    // SAFETY: Per the function contract.
+    let cookie = unsafe { &mut *priv_.cast::<SmCookie<'_, T>>() };
+    let wrapped = OpUnmap {
+        // SAFETY: The op is an unmap op when this callback runs.
+        raw: unsafe { core::ptr::addr_of_mut!((*op).__bindgen_anon_1.unmap) },
+        _p: PhantomData,
+    };
+    #[cfg(CONFIG_DRM_GPUVM_TRACE)]
+    T::on_op(
+        &mut cookie.ctx,
+        OpInfo {
+            kind: OpKind::Unmap,
+            addr: wrapped.addr(),
+            range: wrapped.range(),
+        },
+    );
+    fail_or_zero(&mut cookie.ctx, T::step_unmap(&mut cookie.ctx, wrapped))
+}
+
+/// # Safety
+///
+/// As for `typed_step_map`.
+unsafe extern "C" fn typed_step_remap<T: DriverGpuVm>(
+    op: *mut bindings::drm_gpuva_op,
+    priv_: *mut c_void,
+) -> c_int {
+    // SAFETY: Per the function contract.
+    let cookie = unsafe { &mut *priv_.cast::<SmCookie<'_, T>>() };
+    let wrapped = OpRemap {
+        // SAFETY: The op is a remap op when this callback runs.
+        raw: unsafe { core::ptr::addr_of_mut!((*op).__bindgen_anon_1.remap) },
+        _p: PhantomData,
+    };
+    // Remaps trace as their surviving halves separately; that is the
+    // granularity split-analysis needs.
+    #[cfg(CONFIG_DRM_GPUVM_TRACE)]
+    // SAFETY: The op is a remap op; absent halves are null.
+    unsafe {
+        let remap = wrapped.raw;
+        let prev = (*remap).prev;
+        if !prev.is_null() {
+            T::on_op(
+                &mut cookie.ctx,
+                OpInfo {
+                    kind: OpKind::RemapPrev,
+                    addr: (*prev).va.addr,
+                    range: (*prev).va.range,
+                },
+            );
+        }
+        let next = (*remap).next;
+        if !next.is_null() {
+            T::on_op(
+                &mut cookie.ctx,
+                OpInfo {
+                    kind: OpKind::RemapNext,
+                    addr: (*next).va.addr,
+                    range: (*next).va.range,
+                },
+            );
+        }
+    }
+    fail_or_zero(&mut cookie.ctx, T::step_remap(&mut cookie.ctx, wrapped))
+}
+
+/// Converts a step result to the errno handed back to the C machinery,
+/// stashing the rich error in the context on failure. The errno path is
+/// unchanged for C: the core still sees a plain negative integer.
+fn fail_or_zero<T: DriverGpuVm>(
+    ctx: &mut SmContext<'_, T>,
+    res: Result<(), T::StepError>,
+) -> c_int {
+    match res {
+        Ok(()) => 0,
+        Err(e) => {
+            let errno = e.to_errno();
+            ctx.error = Some(e);
+            errno
+        }
+    }
+}
+
+/// A request describing a mapping to create via [`GpuVm::sm_map`].
+pub struct OpMapRequest {
+    /// The GPU address at which to map.
+    pub addr: u64,
+    /// The length of the mapping.
+    pub range: u64,
+    /// The offset into the backing GEM object.
+    pub offset: u64,
+}
+
+impl OpMapRequest {
+    /// Builds a request after validating the GEM window and the target
+    /// range.
+    ///
+    /// Checks, in order: `len` is non-zero, `gem_offset + len` does not
+    /// overflow (`EOVERFLOW`) and lies within `obj`'s size (`EINVAL`),
+    /// and the GPU range is valid for `vm`. All of this runs before any
+    /// split/merge machinery: a map that fails after `step_unmap` has
+    /// already executed leaves the old mappings gone, which is exactly
+    /// the corruption this constructor exists to prevent.
+    pub fn new<T: DriverGpuVm>(
+        vm: &GpuVm<T>,
+        obj: &crate::drm::gem::Object,
+        gpu_addr: u64,
+        len: u64,
+        gem_offset: u64,
+    ) -> crate::error::Result<Self> {
+        if len == 0 {
+            return Err(crate::error::code::EINVAL);
+        }
+        let end = gem_offset
+            .checked_add(len)
+            .ok_or(crate::error::code::EOVERFLOW)?;
+        if end > obj.size() {
+            return Err(crate::error::code::EINVAL);
+        }
+        Self::new_validated(vm, gpu_addr, len, gem_offset)
+    }
+
+    /// Builds a request after verifying the target range against `vm`.
+    ///
+    /// Fails with `EINVAL` before any of the split/merge machinery runs,
+    /// which matters because a failure after `step_unmap` has already
+    /// executed leaves the old mappings gone.
+    pub fn new_validated<T: DriverGpuVm>(
+        vm: &GpuVm<T>,
+        addr: u64,
+        range: u64,
+        offset: u64,
+    ) -> crate::error::Result<Self> {
+        if !vm.range_valid(addr, range) {
+            return Err(crate::error::code::EINVAL);
+        }
+        Ok(Self {
+            addr,
+            range,
+            offset,
+        })
+    }
+}
+
+impl<T: DriverGpuVm> GpuVm<T> {
+    /// Runs a split/merge map operation, driving the step callbacks.
+    ///
+    /// On a step failure, the driver's stashed [`DriverGpuVm::StepError`]
+    /// is returned; failures originating inside the C machinery come back
+    /// as [`RichSmError::Raw`].
+    pub fn sm_map(
+        &self,
+        ctx: &mut T::StepContext,
+        req: OpMapRequest,
+    ) -> Result<(), RichSmError<T>> {
+        let mut cookie = SmCookie {
+            dispatch: SmDispatch {
+                map: typed_step_map::<T>,
+                unmap: typed_step_unmap::<T>,
+                remap: typed_step_remap::<T>,
+            },
+            ctx: SmContext {
+                vm: self,
+                ctx,
+                error: None,
+            },
+        };
+        // SAFETY: The gpuvm is valid per the type invariant and the cookie
+        // outlives the call, which runs the step callbacks synchronously.
+        let ret = unsafe {
+            bindings::drm_gpuvm_sm_map(
+                self.gpuvm(),
+                core::ptr::addr_of_mut!(cookie).cast(),
+                req.addr,
+                req.range,
+                core::ptr::null_mut(),
+                req.offset,
+            )
+        };
+        finish_sm(cookie.ctx.error.take(), ret)
+    }
+
+    /// Runs a split/merge unmap operation over `[addr, addr + range)`.
+    pub fn sm_unmap(
+        &self,
+        ctx: &mut T::StepContext,
+        addr: u64,
+        range: u64,
+    ) -> Result<(), RichSmError<T>> {
+        let mut cookie = SmCookie {
+            dispatch: SmDispatch {
+                map: typed_step_map::<T>,
+                unmap: typed_step_unmap::<T>,
+                remap: typed_step_remap::<T>,
+            },
+            ctx: SmContext {
+                vm: self,
+                ctx,
+                error: None,
+            },
+        };
+        // SAFETY: As in `sm_map`.
+        let ret = unsafe {
+            bindings::drm_gpuvm_sm_unmap(
+                self.gpuvm(),
+                core::ptr::addr_of_mut!(cookie).cast(),
+                addr,
+                range,
+            )
+        };
+        finish_sm(cookie.ctx.error.take(), ret)
+    }
+}
+
+fn finish_sm<T: DriverGpuVm>(
+    stashed: Option<T::StepError>,
+    ret: c_int,
+) -> Result<(), RichSmError<T>> {
+    if ret == 0 {
+        return Ok(());
+    }
+    match stashed {
+        Some(e) => Err(RichSmError::Driver(e)),
+        None => Err(RichSmError::Raw(Error::from_errno(ret))),
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel errors.
+//!
+//! C header: [`include/uapi/asm-generic/errno-base.h`](srctree/include/uapi/asm-generic/errno-base.h)
+
+use crate::bindings;
+use core::ffi::c_int;
+
+/// Contains the C-compatible error codes.
+pub mod code {
+    macro_rules! declare_err {
+        ($err:tt $(,)? $($doc:expr),+) => {
+            $(
+            #[doc = $doc]
+            )*
+            pub const $err: super::Error =
+                match super::Error::try_from_errno(-(crate::bindings::$err as i32)) {
+                    Some(err) => err,
+                    None => panic!("Invalid errno in `declare_err!`"),
+                };
+        };
+    }
+
+    declare_err!(EPERM, "Operation not permitted.");
+    declare_err!(ENOENT, "No such file or directory.");
+    declare_err!(EIO, "I/O error.");
+    declare_err!(E2BIG, "Argument list too long.");
+    declare_err!(EBADF, "Bad file number.");
+    declare_err!(EAGAIN, "Try again.");
+    declare_err!(ENOMEM, "Out of memory.");
+    declare_err!(ESRCH, "No such process.");
+    declare_err!(EFAULT, "Bad address.");
+    declare_err!(EBUSY, "Device or resource busy.");
+    declare_err!(ENODEV, "No such device.");
+    declare_err!(EINVAL, "Invalid argument.");
+    declare_err!(ERANGE, "Math result not representable.");
+    declare_err!(ENOSYS, "Invalid system call number.");
+    declare_err!(ENOTTY, "Inappropriate ioctl for device.");
+    declare_err!(ENAMETOOLONG, "File name too long.");
+    declare_err!(ENOTSUPP, "Operation is not supported.");
+    declare_err!(EOVERFLOW, "Value too large for defined data type.");
+    declare_err!(ETIMEDOUT, "Connection timed out.");
+    declare_err!(ERESTARTSYS, "Restart the system call.");
+    declare_err!(EPROBE_DEFER, "Driver requests probe retry.");
+    declare_err!(EINTR, "Interrupted system call.");
+}
+
+/// Generic integer kernel error.
+///
+/// The kernel defines a set of integer generic error codes based on C and
+/// POSIX ones. These codes may have a more specific meaning in some contexts.
+///
+/// # Invariants
+///
+/// The value is a valid `errno` (i.e. `>= -MAX_ERRNO && < 0`).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Error(c_int);
+
+impl Error {
+    /// Creates an [`Error`] from a kernel error code.
+    ///
+    /// It is a bug to pass an out-of-range `errno`. `EINVAL` would
+    /// be returned in such a case.
+    pub fn from_errno(errno: c_int) -> Error {
+        if let Some(error) = Self::try_from_errno(errno) {
+            error
+        } else {
+            code::EINVAL
+        }
+    }
+
+    /// Creates an [`Error`] from a kernel error code, or returns [`None`] if
+    /// it is out of range.
+    pub(crate) const fn try_from_errno(errno: c_int) -> Option<Error> {
+        if errno < -(bindings::MAX_ERRNO as c_int) || errno >= 0 {
+            return None;
+        }
+        // INVARIANT: The check above ensures the type invariant will hold.
+        Some(Error(errno))
+    }
+
+    /// Returns the kernel error code.
+    pub fn to_errno(self) -> c_int {
+        self.0
+    }
+
+    /// Returns the error encoded as a pointer.
+    pub fn to_ptr<T>(self) -> *mut T {
+        // SAFETY: `self.0` is a valid error due to its invariant.
+        unsafe { bindings::ERR_PTR(self.0 as _) as *mut _ }
+    }
+}
+
+impl core::fmt::Debug for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Error").field(&-self.0).finish()
+    }
+}
+
+impl From<core::alloc::AllocError> for Error {
+    fn from(_: core::alloc::AllocError) -> Error {
+        code::ENOMEM
+    }
+}
+
+impl From<core::convert::Infallible> for Error {
+    fn from(e: core::convert::Infallible) -> Error {
+        match e {}
+    }
+}
+
+impl From<core::num::TryFromIntError> for Error {
+    fn from(_: core::num::TryFromIntError) -> Error {
+        code::EINVAL
+    }
+}
+
+/// A [`Result`] with an [`Error`] error type.
+///
+/// To be used as the return type for functions that may fail.
+pub type Result<T = (), E = Error> = core::result::Result<T, E>;
+
+/// Converts an integer as returned by a C kernel function to an error if it's
+/// negative, and `Ok(())` otherwise.
+pub fn to_result(err: c_int) -> Result {
+    if err < 0 {
+        Err(Error::from_errno(err))
+    } else {
+        Ok(())
+    }
+}
+
+/// Transforms a [`crate::bindings`] error pointer into a [`Result`].
+///
+/// Some kernel C API functions return an error pointer on failure instead of
+/// an integer error code. This converts such a pointer into a [`Result`],
+/// extracting the `errno` with `PTR_ERR` when `IS_ERR` reports an error.
+pub fn from_err_ptr<T>(ptr: *mut T) -> Result<*mut T> {
+    let const_ptr: *const core::ffi::c_void = ptr.cast();
+    // SAFETY: The FFI function does not deref the pointer.
+    if unsafe { bindings::IS_ERR(const_ptr) } {
+        // SAFETY: The FFI function does not deref the pointer.
+        let err = unsafe { bindings::PTR_ERR(const_ptr) };
+        return Err(Error::from_errno(err as c_int));
+    }
+    Ok(ptr)
+}
@@ -0,0 +1,395 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Files and file descriptors.
+//!
+//! C headers: [`include/linux/fs.h`](srctree/include/linux/fs.h) and
+//! [`include/linux/file.h`](srctree/include/linux/file.h)
+
+use crate::{
+    bindings,
+    types::{AlwaysRefCounted, Opaque},
+};
+use core::ptr::NonNull;
+
+/// Flags associated with a [`File`].
+pub mod flags {
+    /// File is opened in append mode.
+    pub const O_APPEND: u32 = crate::bindings::O_APPEND;
+    /// Signal-driven I/O is enabled.
+    pub const O_ASYNC: u32 = crate::bindings::FASYNC;
+    /// Close-on-exec flag.
+    pub const O_CLOEXEC: u32 = crate::bindings::O_CLOEXEC;
+    /// File must be a directory.
+    pub const O_DIRECTORY: u32 = crate::bindings::O_DIRECTORY;
+    /// File is using nonblocking I/O.
+    pub const O_NONBLOCK: u32 = crate::bindings::O_NONBLOCK;
+    /// Also known as `O_NDELAY`.
+    pub const O_NDELAY: u32 = crate::bindings::O_NDELAY;
+}
+
+/// Mode bits associated with a [`File`].
+pub mod mode {
+    /// File is open for reading.
+    pub const FMODE_READ: u32 = crate::bindings::FMODE_READ;
+    /// File is open for writing.
+    pub const FMODE_WRITE: u32 = crate::bindings::FMODE_WRITE;
+    /// File supports positional (`pread`) reads.
+    pub const FMODE_PREAD: u32 = crate::bindings::FMODE_PREAD;
+    /// File supports positional (`pwrite`) writes.
+    pub const FMODE_PWRITE: u32 = crate::bindings::FMODE_PWRITE;
+}
+
+/// Wraps the kernel's `struct file`.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to
+/// `get_file` ensures that the allocation remains valid at least until the
+/// matching call to `fput`.
+#[repr(transparent)]
+pub struct File(Opaque<bindings::file>);
+
+// SAFETY: The kernel's file operations are designed to be callable from any
+// thread.
+unsafe impl Send for File {}
+// SAFETY: See above.
+unsafe impl Sync for File {}
+
+impl File {
+    /// Creates a reference to a [`File`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the
+    /// lifetime of the returned [`File`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::file) -> &'a File {
+        // SAFETY: `File` is a transparent wrapper over `bindings::file`.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_ptr(&self) -> *mut bindings::file {
+        self.0.get()
+    }
+
+    /// Returns an owned reference to this file, bumping its refcount.
+    ///
+    /// This is the kernel's `get_file` under its familiar name; it is
+    /// exactly `ARef::from(self)` via [`AlwaysRefCounted`], and exists so
+    /// the operation is discoverable next to the other `File` methods.
+    /// The returned [`ARef`](crate::types::ARef) keeps the file alive
+    /// after the borrow this was called on ends.
+    pub fn get(&self) -> crate::types::ARef<File> {
+        crate::types::ARef::from(self)
+    }
+
+    /// Returns the flags associated with the file.
+    ///
+    /// The flags are a combination of the constants in [`flags`].
+    pub fn flags(&self) -> u32 {
+        // SAFETY: The file is valid per the type invariant. `f_flags` can
+        // change concurrently (e.g. via `fcntl`), hence the volatile read.
+        unsafe { core::ptr::addr_of!((*self.as_ptr()).f_flags).read_volatile() }
+    }
+
+    /// Returns the mode bits associated with the file.
+    ///
+    /// The result is a combination of the constants in [`mode`]. Unlike
+    /// `f_flags`, most `f_mode` bits are fixed at open time, but a few are
+    /// updated later by the VFS, so the read is volatile for the same
+    /// reason as in [`File::flags`].
+    pub fn fmode(&self) -> u32 {
+        // SAFETY: The file is valid per the type invariant.
+        unsafe { core::ptr::addr_of!((*self.as_ptr()).f_mode).read_volatile() }
+    }
+
+    /// Returns whether the file is open for reading.
+    pub fn can_read(&self) -> bool {
+        self.fmode() & mode::FMODE_READ != 0
+    }
+
+    /// Returns whether the file is open for writing.
+    pub fn can_write(&self) -> bool {
+        self.fmode() & mode::FMODE_WRITE != 0
+    }
+
+    /// Returns whether the file supports positional reads.
+    ///
+    /// Drivers implementing positional I/O should reject reads at an
+    /// explicit offset when this is unset, like the VFS does for
+    /// `pread(2)`.
+    pub fn can_pread(&self) -> bool {
+        self.fmode() & mode::FMODE_PREAD != 0
+    }
+
+    /// Returns whether the file supports positional writes.
+    pub fn can_pwrite(&self) -> bool {
+        self.fmode() & mode::FMODE_PWRITE != 0
+    }
+
+    /// Renders the file's path into an owned string, for logging.
+    ///
+    /// This is a logging accessor, not a path API: the result is
+    /// relative to the caller's mount namespace at call time and can be
+    /// stale by the time it is printed, so it must never feed access
+    /// decisions. Unlinked files render with the usual " (deleted)"
+    /// suffix. Fails with `ENOMEM` if the scratch buffer cannot be
+    /// allocated and `ENAMETOOLONG` if the path does not fit a page.
+    pub fn name(&self) -> crate::error::Result<crate::str::CString> {
+        let mut buf = alloc::vec::Vec::new();
+        buf.try_reserve_exact(crate::PAGE_SIZE)
+            .map_err(|_| crate::error::code::ENOMEM)?;
+        buf.resize(crate::PAGE_SIZE, 0u8);
+        // SAFETY: The file is valid per the type invariant; `d_path`
+        // renders right-aligned into the buffer and returns an interior
+        // pointer (or an error pointer), which is the easy-to-misuse part
+        // this wrapper hides.
+        let start = crate::error::from_err_ptr(unsafe {
+            bindings::d_path(
+                core::ptr::addr_of!((*self.as_ptr()).f_path),
+                buf.as_mut_ptr().cast(),
+                buf.len() as _,
+            )
+        })?;
+        let offset = start as usize - buf.as_ptr() as usize;
+        let len = buf[offset..]
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(buf.len() - offset);
+        crate::str::CString::try_from_bytes(&buf[offset..offset + len])
+    }
+
+    /// Returns whether the file is in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.flags() & flags::O_NONBLOCK != 0
+    }
+
+    /// Sets or clears the file's non-blocking mode.
+    ///
+    /// The update is a read-modify-write of `f_flags`, which races with
+    /// concurrent `fcntl(F_SETFL)` unless serialised; `do_fcntl` uses
+    /// `f_lock` for exactly this, so we take the same spinlock rather
+    /// than doing a bare write. Note this only serialises the flag word
+    /// itself -- I/O already in flight may still observe the old mode,
+    /// just as with `fcntl` from userspace.
+    pub fn set_nonblocking(&self, on: bool) {
+        let ptr = self.as_ptr();
+        // SAFETY: The file is valid per the type invariant; `f_lock` is
+        // the designated lock for `f_flags` updates.
+        unsafe {
+            bindings::spin_lock(core::ptr::addr_of_mut!((*ptr).f_lock));
+            let old = (*ptr).f_flags;
+            (*ptr).f_flags = if on {
+                old | flags::O_NONBLOCK
+            } else {
+                old & !flags::O_NONBLOCK
+            };
+            bindings::spin_unlock(core::ptr::addr_of_mut!((*ptr).f_lock));
+        }
+    }
+}
+
+// SAFETY: The type invariants guarantee that `File` is always ref-counted.
+unsafe impl AlwaysRefCounted for File {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the
+        // refcount is nonzero.
+        unsafe { bindings::get_file(self.as_ptr()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is
+        // nonzero.
+        unsafe { bindings::fput(obj.cast().as_ptr()) }
+    }
+}
+
+/// A file that is known not to be shared across threads during the current
+/// syscall (the `fdget` no-refcount fast path).
+///
+/// The VFS only allows `f_pos` to be updated through files obtained this
+/// way, which is why position-modifying helpers take [`LocalFile`] rather
+/// than [`File`].
+#[repr(transparent)]
+pub struct LocalFile(Opaque<bindings::file>);
+
+impl LocalFile {
+    /// Creates a reference to a [`LocalFile`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and not accessed
+    /// concurrently through other threads for the lifetime of the
+    /// returned reference (the `fdget` guarantee).
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::file) -> &'a LocalFile {
+        // SAFETY: `LocalFile` is a transparent wrapper.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_ptr(&self) -> *mut bindings::file {
+        self.0.get()
+    }
+
+    /// Returns the current file position.
+    pub fn pos(&self) -> i64 {
+        // SAFETY: `f_pos` is not accessed concurrently per the type
+        // contract.
+        unsafe { (*self.as_ptr()).f_pos }
+    }
+
+    /// Sets the file position.
+    pub fn set_pos(&self, pos: i64) {
+        // SAFETY: See `pos`.
+        unsafe { (*self.as_ptr()).f_pos = pos };
+    }
+}
+
+/// Support for `O_ASYNC` (SIGIO) notification on a device.
+///
+/// One instance lives (pinned) in the driver's per-device state; the
+/// driver's `fasync` file operation forwards to [`register`] and its
+/// `release` must deregister each file with `register(-1, file, false)`,
+/// the C convention. Data-ready paths call [`notify`].
+///
+/// No lock is needed here: `fasync_helper` and `kill_fasync` serialise
+/// internally on the fasync lock and `f_lock` -- worth stating since the
+/// bare list head looks unprotected.
+///
+/// [`register`]: FasyncHelper::register
+/// [`notify`]: FasyncHelper::notify
+pub struct FasyncHelper {
+    head: Opaque<*mut bindings::fasync_struct>,
+    _pin: core::marker::PhantomPinned,
+}
+
+// SAFETY: The C side serialises all access to the list head.
+unsafe impl Send for FasyncHelper {}
+// SAFETY: See above.
+unsafe impl Sync for FasyncHelper {}
+
+impl FasyncHelper {
+    /// Creates an empty fasync list.
+    pub const fn new() -> Self {
+        Self {
+            head: Opaque::new(core::ptr::null_mut()),
+            _pin: core::marker::PhantomPinned,
+        }
+    }
+
+    /// Adds or removes `file` from the async notification list, as the
+    /// driver's `fasync` fop.
+    pub fn register(&self, fd: i32, file: &File, on: bool) -> crate::error::Result {
+        // SAFETY: The helper's address is stable (the type is !Unpin and
+        // held pinned in driver state) and the file is valid for the
+        // call.
+        let ret = unsafe {
+            bindings::fasync_helper(fd, file.as_ptr(), on as i32, self.head.get())
+        };
+        crate::error::to_result(ret)
+    }
+
+    /// Sends `SIGIO` (with the given poll band, e.g. `POLL_IN`) to every
+    /// registered owner. A no-op with no registrations.
+    pub fn notify(&self, band: i32) {
+        // SAFETY: The list head is valid; `kill_fasync` handles an empty
+        // list and does its own locking.
+        unsafe { bindings::kill_fasync(self.head.get(), bindings::SIGIO as i32, band) };
+    }
+}
+
+impl Drop for FasyncHelper {
+    fn drop(&mut self) {
+        // Every file must have been deregistered by its release fop; a
+        // non-empty list here means a file outlived the device state.
+        debug_assert!(
+            // SAFETY: The head pointer is valid for reading.
+            unsafe { (*self.head.get()).is_null() },
+            "FasyncHelper dropped with registrations outstanding"
+        );
+    }
+}
+
+/// A reservation of a file descriptor number, to be committed with a file
+/// or discarded.
+///
+/// Splitting allocation from installation lets a driver reserve fds while
+/// failure is still an option and only make them visible to userspace
+/// (`fd_install`) once the whole operation has succeeded.
+///
+/// # Invariants
+///
+/// `fd` was returned by `get_unused_fd_flags` and has been neither
+/// committed nor discarded.
+pub struct FileDescriptorReservation {
+    fd: u32,
+    flags: u32,
+}
+
+impl FileDescriptorReservation {
+    /// Reserves a new fd number with the given flags.
+    pub fn new(flags: u32) -> crate::error::Result<Self> {
+        // SAFETY: No context requirements.
+        let fd = unsafe { bindings::get_unused_fd_flags(flags) };
+        if fd < 0 {
+            return Err(crate::error::Error::from_errno(fd));
+        }
+        Ok(Self {
+            fd: fd as u32,
+            flags,
+        })
+    }
+
+    /// Returns the reserved fd number.
+    pub fn reserved_fd(&self) -> u32 {
+        self.fd
+    }
+
+    /// Returns the flags the reservation was created with.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Installs `file` into the reserved fd.
+    pub fn fd_install(self, file: crate::types::ARef<File>) {
+        let fd = self.fd;
+        core::mem::forget(self);
+        // SAFETY: The fd is reserved per the type invariant and the
+        // file reference is transferred to the fd table.
+        unsafe { bindings::fd_install(fd, crate::types::ARef::into_raw(file).as_ptr().cast()) };
+    }
+
+    /// Installs `file`, overriding only the close-on-exec disposition.
+    ///
+    /// `alloc_fd` applies `O_CLOEXEC` to the fd-table bitmap at
+    /// *reservation* time, so overriding means rewriting that bit. It
+    /// is rewritten *before* the install: once `fd_install` publishes
+    /// the file, a concurrently forking/exec'ing child could otherwise
+    /// observe the fd in the pre-override state and inherit something
+    /// the caller asked to be cloexec. Only `O_CLOEXEC` may differ from
+    /// the reservation flags; anything else is `EINVAL`, since the
+    /// other `get_unused_fd` flags took effect at reservation.
+    pub fn fd_install_with_flags(
+        self,
+        file: crate::types::ARef<File>,
+        flags: u32,
+    ) -> crate::error::Result<()> {
+        if flags & !flags::O_CLOEXEC != self.flags & !flags::O_CLOEXEC {
+            return Err(crate::error::code::EINVAL);
+        }
+        let fd = self.fd;
+        let cloexec = flags & flags::O_CLOEXEC != 0;
+        // SAFETY: The fd is reserved by this object and belongs to
+        // current; the bitmap bit may be rewritten while reserved.
+        unsafe { bindings::set_close_on_exec(fd, cloexec) };
+        self.fd_install(file);
+        Ok(())
+    }
+}
+
+impl Drop for FileDescriptorReservation {
+    fn drop(&mut self) {
+        // SAFETY: The fd is reserved and uncommitted per the invariant.
+        unsafe { bindings::put_unused_fd(self.fd) };
+    }
+}
@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Filesystem helpers.
+
+use crate::{
+    bindings,
+    error::{code::*, Result},
+    file::LocalFile,
+};
+
+/// Computes and stores a new file position for a device of fixed `size`,
+/// implementing the standard `SEEK_SET`/`SEEK_CUR`/`SEEK_END` arithmetic
+/// so `llseek` implementations need not.
+///
+/// Takes a [`LocalFile`] because updating `f_pos` is only permitted on
+/// files obtained through the `fdget` no-sharing fast path.
+///
+/// `SEEK_DATA`/`SEEK_HOLE` are rejected with `EINVAL`: devices have no
+/// holes. Negative results and positions beyond `MAX_LFS_FILESIZE` are
+/// rejected likewise, before any state is updated.
+pub fn generic_llseek(file: &LocalFile, offset: i64, whence: i32, size: i64) -> Result<i64> {
+    let base = match whence as u32 {
+        bindings::SEEK_SET => 0,
+        bindings::SEEK_CUR => file.pos(),
+        bindings::SEEK_END => size,
+        _ => return Err(EINVAL),
+    };
+    let new_pos = base.checked_add(offset).ok_or(EINVAL)?;
+    if new_pos < 0 || new_pos > bindings::MAX_LFS_FILESIZE as i64 {
+        return Err(EINVAL);
+    }
+    file.set_pos(new_pos);
+    Ok(new_pos)
+}
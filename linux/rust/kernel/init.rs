@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! In-place fallible initialisation.
+//!
+//! The minimal core of the pin-init pattern: an initialiser is a closure
+//! over an output slot, so a value containing self-referential or
+//! address-sensitive parts (locks, list links) can be constructed
+//! directly at its final address instead of being moved there.
+
+use core::marker::PhantomData;
+
+/// An in-place, pinned initialiser for `T` that can fail with `E`.
+///
+/// # Safety
+///
+/// Implementers must fully initialise the slot on `Ok`, and leave it
+/// uninitialised (or fully dropped) on `Err` -- the caller frees the
+/// slot without running `T`'s drop in the error case.
+pub unsafe trait PinInit<T, E = core::convert::Infallible>: Sized {
+    /// Initialises `slot`.
+    ///
+    /// # Safety
+    ///
+    /// `slot` must be valid for writes and pinned: it will not move
+    /// before being dropped in place.
+    unsafe fn __pinned_init(self, slot: *mut T) -> Result<(), E>;
+}
+
+/// Creates a [`PinInit`] from an initialiser closure.
+///
+/// # Safety
+///
+/// The closure must satisfy the [`PinInit`] contract: fully initialise
+/// the slot on `Ok`, leave nothing needing drop behind on `Err`.
+pub unsafe fn pin_init_from_closure<T, E>(
+    f: impl FnOnce(*mut T) -> Result<(), E>,
+) -> impl PinInit<T, E> {
+    struct ClosureInit<T, E, F>(F, PhantomData<fn(*mut T) -> E>);
+    // SAFETY: Forwarded to the closure per this function's contract.
+    unsafe impl<T, E, F: FnOnce(*mut T) -> Result<(), E>> PinInit<T, E> for ClosureInit<T, E, F> {
+        unsafe fn __pinned_init(self, slot: *mut T) -> Result<(), E> {
+            (self.0)(slot)
+        }
+    }
+    ClosureInit(f, PhantomData)
+}
@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Interrupt handling.
+//!
+//! C header: [`include/linux/interrupt.h`](srctree/include/linux/interrupt.h)
+
+pub mod flags;
+pub mod request;
+
+pub use flags::Flags;
+pub use request::{Handler, PerCpuHandler, PerCpuRegistration, Registration};
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Flags for IRQ registration.
+
+use crate::bindings;
+
+/// Flags controlling how an interrupt line is requested.
+///
+/// Values are combined with `|`:
+///
+/// ```
+/// use kernel::irq::Flags;
+///
+/// const FLAGS: Flags = Flags::shared().or(Flags::trigger_rising());
+/// assert_eq!(FLAGS.as_raw(), Flags::shared().as_raw() | Flags::trigger_rising().as_raw());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Flags(u64);
+
+impl Flags {
+    /// No flags; trigger configuration comes from the firmware.
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    /// Allow sharing the irq among several devices.
+    pub const fn shared() -> Self {
+        Self(bindings::IRQF_SHARED as u64)
+    }
+
+    /// Trigger on a rising edge.
+    pub const fn trigger_rising() -> Self {
+        Self(bindings::IRQF_TRIGGER_RISING as u64)
+    }
+
+    /// Trigger on a falling edge.
+    pub const fn trigger_falling() -> Self {
+        Self(bindings::IRQF_TRIGGER_FALLING as u64)
+    }
+
+    /// Trigger while high.
+    pub const fn trigger_high() -> Self {
+        Self(bindings::IRQF_TRIGGER_HIGH as u64)
+    }
+
+    /// Trigger while low.
+    pub const fn trigger_low() -> Self {
+        Self(bindings::IRQF_TRIGGER_LOW as u64)
+    }
+
+    /// Do not enable the irq automatically at request time.
+    pub const fn no_autoen() -> Self {
+        Self(bindings::IRQF_NO_AUTOEN as u64)
+    }
+
+    /// Keep the irq enabled over suspend.
+    pub const fn no_suspend() -> Self {
+        Self(bindings::IRQF_NO_SUSPEND as u64)
+    }
+
+    /// Interrupt is per-CPU (see
+    /// [`PerCpuRegistration`](super::PerCpuRegistration)).
+    pub const fn percpu() -> Self {
+        Self(bindings::IRQF_PERCPU as u64)
+    }
+
+    /// `const` form of the `|` operator, usable in constants.
+    pub const fn or(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+
+    /// Returns the raw `IRQF_*` bits.
+    pub const fn as_raw(self) -> u64 {
+        self.0
+    }
+}
+
+impl core::ops::BitOr for Flags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.or(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings;
+
+    #[test]
+    fn combinators_produce_raw_bits() {
+        const COMBINED: Flags = Flags::shared().or(Flags::trigger_rising());
+        assert_eq!(
+            COMBINED.as_raw(),
+            (bindings::IRQF_SHARED | bindings::IRQF_TRIGGER_RISING) as u64,
+        );
+        assert_eq!(
+            (Flags::trigger_high() | Flags::no_suspend()).as_raw(),
+            (bindings::IRQF_TRIGGER_HIGH | bindings::IRQF_NO_SUSPEND) as u64,
+        );
+        assert_eq!(Flags::none().as_raw(), 0);
+        assert_eq!(
+            (Flags::percpu() | Flags::none()).as_raw(),
+            bindings::IRQF_PERCPU as u64,
+        );
+    }
+}
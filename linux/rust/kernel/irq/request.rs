@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! IRQ registration.
+
+use super::flags::Flags;
+use crate::{
+    bindings,
+    error::{to_result, Result},
+    str::CStr,
+};
+use alloc::boxed::Box;
+use core::{marker::PhantomData, pin::Pin};
+
+/// The result of an interrupt handler.
+#[repr(u32)]
+pub enum IrqReturn {
+    /// The interrupt was not from this device.
+    None = bindings::irqreturn_IRQ_NONE,
+    /// The interrupt was handled by this device.
+    Handled = bindings::irqreturn_IRQ_HANDLED,
+}
+
+/// An interrupt handler.
+pub trait Handler: Sync {
+    /// Called from interrupt context when the irq fires.
+    fn handle(&self) -> IrqReturn;
+}
+
+impl<H: Handler> Handler for crate::sync::Arc<H> {
+    fn handle(&self) -> IrqReturn {
+        H::handle(self)
+    }
+}
+
+/// A registration of an interrupt handler for a given interrupt line.
+///
+/// # Invariants
+///
+/// The irq in `irq` is requested with `handler` as its cookie until the
+/// registration is dropped.
+pub struct Registration<T: Handler> {
+    irq: u32,
+    handler: Pin<Box<T>>,
+    /// Whether the irq has already been freed (by
+    /// [`shutdown`](Self::shutdown)). A plain bool suffices: both
+    /// shutdown (consuming) and drop (`&mut`) hold unique access.
+    freed: bool,
+}
+
+impl<T: Handler> Registration<T> {
+    /// Registers `handler` for the interrupt line `irq`.
+    pub fn register(irq: u32, flags: Flags, name: &'static CStr, handler: T) -> Result<Self> {
+        let handler = Pin::from(Box::try_new(handler)?);
+        // SAFETY: `handler` is pinned for the lifetime of the
+        // registration, and `free_irq` in drop guarantees the C side stops
+        // using the cookie before it is freed.
+        to_result(unsafe {
+            bindings::request_irq(
+                irq,
+                Some(handle_irq_callback::<T>),
+                flags.as_raw() as _,
+                name.as_char_ptr(),
+                (&*handler as *const T as *mut T).cast(),
+            )
+        })?;
+        // INVARIANT: The request above succeeded.
+        Ok(Self {
+            irq,
+            handler,
+            freed: false,
+        })
+    }
+
+    /// Returns a reference to the handler.
+    pub fn handler(&self) -> &T {
+        &self.handler
+    }
+
+    /// Marks (or unmarks) this interrupt as a system wakeup source.
+    ///
+    /// Calls must balance: the genirq core keeps a wake depth and warns
+    /// on underflow. The expected pattern is `set_wake(true)` from the
+    /// driver's suspend callback and `set_wake(false)` from resume --
+    /// not a one-off at registration time. No state is cached here; the
+    /// core's depth counter is the source of truth.
+    pub fn set_wake(&self, on: bool) -> Result {
+        // SAFETY: The irq was requested in `register` and is still owned
+        // by this registration.
+        to_result(unsafe {
+            if on {
+                bindings::enable_irq_wake(self.irq)
+            } else {
+                bindings::disable_irq_wake(self.irq)
+            }
+        })
+    }
+}
+
+impl<H: Handler + Send + Sync + 'static> Registration<crate::sync::Arc<H>> {
+    /// Clones out an owned reference to the handler data.
+    ///
+    /// Available when the handler was registered wrapped in an
+    /// [`Arc`](crate::sync::Arc) -- the usual shape when the same data is
+    /// also accessed from process context. The returned clone keeps the
+    /// data alive independently of the registration, but the interrupt
+    /// itself still ends with the registration's teardown.
+    pub fn handler_arc(&self) -> crate::sync::Arc<H> {
+        self.handler().clone()
+    }
+}
+
+impl<T: Handler> Registration<T> {
+    /// Frees the irq now, consuming the registration.
+    ///
+    /// After this returns, the handler will never run again: `free_irq`
+    /// blocks until in-flight handlers complete. Use it when teardown
+    /// ordering matters (e.g. freeing the line before releasing
+    /// resources the handler touches, at a specific point in `remove`);
+    /// plain drop gives the same guarantee but at drop time.
+    pub fn shutdown(mut self) {
+        self.free();
+        // The subsequent drop sees `freed` and does nothing.
+    }
+
+    /// Frees the irq at most once.
+    fn free(&mut self) {
+        if core::mem::replace(&mut self.freed, true) {
+            return;
+        }
+        // SAFETY: The irq was requested in `register` and not yet freed.
+        // `free_irq` waits for running handlers, so the cookie outlives
+        // all uses.
+        unsafe { bindings::free_irq(self.irq, (&*self.handler as *const T as *mut T).cast()) };
+    }
+}
+
+impl<T: Handler> Drop for Registration<T> {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+/// # Safety
+///
+/// Called by the irq core with the cookie passed to `request_irq`, which is
+/// always a valid `*mut T` for this instantiation.
+unsafe extern "C" fn handle_irq_callback<T: Handler>(
+    _irq: i32,
+    ptr: *mut core::ffi::c_void,
+) -> bindings::irqreturn_t {
+    // SAFETY: Per the function contract.
+    let handler = unsafe { &*(ptr as *const T) };
+    T::handle(handler) as bindings::irqreturn_t
+}
+
+/// A handler for per-CPU interrupts.
+///
+/// Unlike [`Handler`], the cookie model is per-CPU: each CPU gets its own
+/// instance of the private data, and the handler runs on the CPU that owns
+/// the instance with the line masked, so no cross-CPU synchronisation is
+/// needed for the data itself (hence `&T` rather than a locked view).
+pub trait PerCpuHandler: Sync {
+    /// Per-CPU private data.
+    type Data: Send;
+
+    /// Called on the interrupted CPU with that CPU's instance of the data.
+    fn handle(data: &Self::Data) -> IrqReturn;
+}
+
+/// Per-CPU private data allocated with `alloc_percpu`.
+///
+/// # Invariants
+///
+/// `ptr` is a live `__percpu` allocation of `T` for every possible CPU.
+pub struct PerCpu<T> {
+    ptr: *mut T,
+}
+
+// SAFETY: Each CPU only touches its own copy.
+unsafe impl<T: Send> Send for PerCpu<T> {}
+// SAFETY: See above.
+unsafe impl<T: Send> Sync for PerCpu<T> {}
+
+impl<T> PerCpu<T> {
+    /// Allocates zero-initialised per-CPU data.
+    ///
+    /// `T` must tolerate the all-zeroes bit pattern, which per-CPU
+    /// allocations start from; this is the same contract the C users of
+    /// `alloc_percpu` rely on.
+    pub fn alloc_zeroed() -> Result<Self> {
+        // SAFETY: Size and alignment come from `T`'s layout.
+        let ptr = unsafe {
+            bindings::__alloc_percpu(core::mem::size_of::<T>(), core::mem::align_of::<T>())
+        };
+        if ptr.is_null() {
+            return Err(crate::error::code::ENOMEM);
+        }
+        // INVARIANT: The allocation succeeded.
+        Ok(Self { ptr: ptr.cast() })
+    }
+}
+
+impl<T> Drop for PerCpu<T> {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` is a live percpu allocation per the invariant.
+        unsafe { bindings::free_percpu(self.ptr.cast()) };
+    }
+}
+
+/// A registration of a per-CPU interrupt, wrapping `request_percpu_irq`.
+///
+/// Differences from [`Registration`]: the line is never shared, it must be
+/// enabled per CPU with [`enable_percpu`](Self::enable_percpu) (typically
+/// from a CPU-hotplug callback), and the handler receives the current
+/// CPU's instance of the private data.
+pub struct PerCpuRegistration<T: PerCpuHandler> {
+    irq: u32,
+    data: PerCpu<T::Data>,
+    _p: PhantomData<T>,
+}
+
+impl<T: PerCpuHandler> PerCpuRegistration<T> {
+    /// Registers a per-CPU handler for `irq`.
+    pub fn register(irq: u32, name: &'static CStr, data: PerCpu<T::Data>) -> Result<Self> {
+        // SAFETY: `data.ptr` is a live percpu allocation that outlives the
+        // registration, which frees the irq before dropping it.
+        to_result(unsafe {
+            bindings::request_percpu_irq(
+                irq,
+                Some(handle_percpu_irq_callback::<T>),
+                name.as_char_ptr(),
+                data.ptr.cast(),
+            )
+        })?;
+        Ok(Self {
+            irq,
+            data,
+            _p: PhantomData,
+        })
+    }
+
+    /// Enables the irq on the calling CPU.
+    pub fn enable_percpu(&self) {
+        // SAFETY: The irq was requested in `register`.
+        unsafe { bindings::enable_percpu_irq(self.irq, bindings::IRQ_TYPE_NONE) };
+    }
+
+    /// Disables the irq on the calling CPU.
+    pub fn disable_percpu(&self) {
+        // SAFETY: The irq was requested in `register`.
+        unsafe { bindings::disable_percpu_irq(self.irq) };
+    }
+}
+
+impl<T: PerCpuHandler> Drop for PerCpuRegistration<T> {
+    fn drop(&mut self) {
+        // SAFETY: The irq was requested in `register` with `data.ptr` as
+        // the percpu cookie.
+        unsafe { bindings::free_percpu_irq(self.irq, self.data.ptr.cast()) };
+    }
+}
+
+/// # Safety
+///
+/// Called by the irq core on the CPU that owns the percpu cookie handed to
+/// `request_percpu_irq`.
+unsafe extern "C" fn handle_percpu_irq_callback<T: PerCpuHandler>(
+    _irq: i32,
+    ptr: *mut core::ffi::c_void,
+) -> bindings::irqreturn_t {
+    // The genirq flow handler (`handle_percpu_devid_irq`) has already
+    // resolved the `__percpu` cookie to this CPU's instance before
+    // invoking us, so `ptr` *is* the instance -- applying a per-CPU
+    // offset again would index into unrelated memory.
+    // SAFETY: Per the above; the handler runs on the owning CPU with the
+    // line masked, so unlocked access to the instance is sound.
+    let data = unsafe { &*(ptr as *const T::Data) };
+    T::handle(data) as bindings::irqreturn_t
+}
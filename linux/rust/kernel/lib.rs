@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! The `kernel` crate.
+//!
+//! This crate contains the kernel APIs that have been ported or wrapped for
+//! usage by Rust code in the kernel and is shared by all of them.
+//!
+//! In other words, all the rest of the Rust code in the kernel (e.g. kernel
+//! modules written in Rust) depends on [`core`], [`alloc`] and this crate.
+
+#![no_std]
+#![feature(allocator_api)]
+
+extern crate alloc;
+
+pub mod align;
+pub mod alloc;
+pub mod clk;
+pub mod devfreq;
+pub mod device;
+pub mod dma;
+pub mod drm;
+pub mod error;
+pub mod file;
+pub mod fs;
+pub mod init;
+pub mod irq;
+pub mod list;
+pub mod maple_tree;
+pub mod miscdevice;
+pub mod mm;
+pub mod of;
+pub mod pages;
+pub mod platform;
+pub mod prelude;
+pub mod regulator;
+pub mod reset;
+pub mod print;
+pub mod seq_file;
+pub mod str;
+pub mod sync;
+pub mod task;
+pub mod time;
+pub mod types;
+pub mod user_ptr;
+
+#[doc(hidden)]
+pub use bindings;
+
+pub use error::{Error, Result};
+
+/// Page size defined in terms of the `PAGE_SHIFT` macro from C.
+pub const PAGE_SIZE: usize = 1 << bindings::PAGE_SHIFT;
+
+/// Produces a pointer to an object from a pointer to one of its fields.
+///
+/// # Safety
+///
+/// The pointer passed to this macro, and the pointer returned by this
+/// macro, can only be used for reads unless the original pointer was
+/// derived from a mutable place.
+///
+/// `ptr` must point to the `$field` field of an object of type `$type`.
+#[macro_export]
+macro_rules! container_of {
+    ($ptr:expr, $type:ty, $($f:tt)*) => {{
+        let ptr = $ptr as *const _ as *const u8;
+        let offset: usize = ::core::mem::offset_of!($type, $($f)*);
+        ptr.sub(offset) as *const $type
+    }}
+}
+
+/// Prints an error-level message prefixed with the given device's name.
+#[macro_export]
+macro_rules! dev_err {
+    ($dev:expr, $($arg:tt)*) => {
+        ($dev).printk(b"\x013\0", core::format_args!($($arg)*))
+    };
+}
+
+/// Prints a warning-level message prefixed with the given device's name.
+#[macro_export]
+macro_rules! dev_warn {
+    ($dev:expr, $($arg:tt)*) => {
+        ($dev).printk(b"\x014\0", core::format_args!($($arg)*))
+    };
+}
+
+/// Prints an info-level message prefixed with the given device's name.
+#[macro_export]
+macro_rules! dev_info {
+    ($dev:expr, $($arg:tt)*) => {
+        ($dev).printk(b"\x016\0", core::format_args!($($arg)*))
+    };
+}
+
+/// Prints a debug-level message prefixed with the given device's name.
+///
+/// Like the C `dev_dbg`, this compiles to nothing unless `CONFIG_DEBUG`
+/// (dynamic debug hookup is future work, as on the C side it is a much
+/// bigger mechanism).
+#[macro_export]
+macro_rules! dev_dbg {
+    ($dev:expr, $($arg:tt)*) => {
+        #[cfg(CONFIG_DEBUG)]
+        ($dev).printk(b"\x017\0", core::format_args!($($arg)*))
+    };
+}
+
+/// Debug-asserts that the given lock is held.
+///
+/// Compiles to nothing outside `CONFIG_DEBUG_KERNEL`. Useful at the top
+/// of functions that receive a lock implicitly, e.g. binder helpers
+/// documented as "caller holds `inner`".
+#[macro_export]
+macro_rules! assert_held {
+    ($lock:expr) => {
+        #[cfg(CONFIG_DEBUG_KERNEL)]
+        ($lock).debug_assert_held()
+    };
+}
@@ -0,0 +1,532 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A linked list implementation.
+
+use crate::{error::code::ENOMEM, error::Result, sync::{Arc, UniqueArc}};
+use alloc::vec::Vec;
+use core::{
+    marker::PhantomData,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The prev/next pointers for an item in a linked list.
+///
+/// # Invariants
+///
+/// The fields are null if and only if this item is not in a list.
+#[repr(C)]
+pub struct ListLinks<const ID: u64 = 0> {
+    next: *mut ListLinks<ID>,
+    prev: *mut ListLinks<ID>,
+}
+
+// SAFETY: The list code requires the caller to serialise access; the
+// pointers themselves carry no thread affinity.
+unsafe impl<const ID: u64> Send for ListLinks<ID> {}
+// SAFETY: See above.
+unsafe impl<const ID: u64> Sync for ListLinks<ID> {}
+
+impl<const ID: u64> ListLinks<ID> {
+    /// Creates new list links that are not in any list.
+    pub fn new() -> Self {
+        Self {
+            next: core::ptr::null_mut(),
+            prev: core::ptr::null_mut(),
+        }
+    }
+}
+
+impl<const ID: u64> Default for ListLinks<ID> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Types that can be inserted into a [`List`].
+///
+/// # Safety
+///
+/// Implementers must ensure that `links` returns a pointer to a
+/// [`ListLinks`] field inside `self` that is used by no other list with
+/// the same `ID`, and that [`LINKS_OFFSET`](Self::LINKS_OFFSET) is the
+/// byte offset of exactly that field.
+pub unsafe trait ListItem<const ID: u64 = 0>: ListArcSafe<ID> {
+    /// The byte offset of the links field inside `Self`.
+    ///
+    /// Spelled as a const (`offset_of!`) rather than derived from a
+    /// probe object, so recovering the item pointer from a links
+    /// pointer never has to touch uninitialised memory.
+    const LINKS_OFFSET: usize;
+
+    /// Returns the list links of this item.
+    fn links(&self) -> &ListLinks<ID>;
+}
+
+/// Tracking for whether a [`ListArc`] for an item currently exists.
+///
+/// The tracking bit is what makes it sound to treat a [`ListArc`] as the
+/// unique "list owner" of an item: at most one can exist at a time.
+pub trait ListArcSafe<const ID: u64 = 0> {
+    /// Returns the atomic flag tracking whether a `ListArc` exists.
+    fn tracker(&self) -> &AtomicBool;
+}
+
+/// A wrapper around [`Arc`] that asserts this is the unique handle allowed
+/// to insert the item into a [`List`].
+///
+/// # Invariants
+///
+/// At most one `ListArc` exists per item at any time, enforced through the
+/// [`ListArcSafe`] tracking bit.
+pub struct ListArc<T: ?Sized + ListArcSafe<ID>, const ID: u64 = 0> {
+    arc: Arc<T>,
+    _p: PhantomData<*mut ()>,
+}
+
+impl<T: ListArcSafe<ID>, const ID: u64> ListArc<T, ID> {
+    /// Converts a [`UniqueArc`] into a [`ListArc`].
+    ///
+    /// Infallible: a unique handle cannot have a `ListArc` outstanding.
+    pub fn from_unique(unique: UniqueArc<T>) -> Self {
+        let arc: Arc<T> = unique.into();
+        // A unique arc has no other handles, so the claim cannot fail.
+        arc.tracker().store(true, Ordering::Relaxed);
+        // INVARIANT: We just claimed the tracking bit.
+        Self {
+            arc,
+            _p: PhantomData,
+        }
+    }
+
+    /// Attempts to convert a plain [`Arc`] into the item's unique
+    /// [`ListArc`].
+    ///
+    /// Succeeds only if no `ListArc` for the item is currently
+    /// outstanding, claimed by atomically test-and-setting the tracking
+    /// bit; on failure the original `Arc` is handed back untouched. This
+    /// is the runtime guard against double-insertion: the second claim
+    /// of an item fails instead of corrupting the list.
+    pub fn try_from_arc(arc: Arc<T>) -> Result<Self, Arc<T>> {
+        // Acquire pairs with the release in `ListArc::drop`, so a
+        // successful claimant observes all list manipulation done under
+        // the previous claim.
+        if arc
+            .tracker()
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            // INVARIANT: We hold the freshly-claimed tracking bit.
+            Ok(Self {
+                arc,
+                _p: PhantomData,
+            })
+        } else {
+            Err(arc)
+        }
+    }
+
+    /// Clones a plain [`Arc`] to the same item.
+    ///
+    /// The clone is not a list owner; the `ListArc` invariant is
+    /// unaffected.
+    pub fn clone_arc(&self) -> Arc<T> {
+        self.arc.clone()
+    }
+}
+
+impl<T: ?Sized + ListArcSafe<ID>, const ID: u64> core::ops::Deref for ListArc<T, ID> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.arc
+    }
+}
+
+impl<T: ?Sized + ListArcSafe<ID>, const ID: u64> Drop for ListArc<T, ID> {
+    fn drop(&mut self) {
+        // Release the claim; pairs with the acquire in any later claim so
+        // the next owner observes this list epoch's writes.
+        self.arc.tracker().store(false, Ordering::Release);
+    }
+}
+
+/// An intrusive doubly-linked circular list.
+///
+/// Items are kept alive by their [`ListArc`] while inserted.
+///
+/// # Invariants
+///
+/// `first` is null for an empty list; otherwise it points at the links of
+/// the first element of a well-formed circular list whose elements are all
+/// owned by this list.
+pub struct List<T: ?Sized + ListItem<ID>, const ID: u64 = 0> {
+    first: *mut ListLinks<ID>,
+    /// Number of elements, maintained by every mutation path so that
+    /// [`List::len`] is O(1).
+    len: usize,
+    _p: PhantomData<ListArc<T, ID>>,
+}
+
+// SAFETY: The list owns its items, so it inherits their sendability.
+unsafe impl<T: ?Sized + ListItem<ID> + Send, const ID: u64> Send for List<T, ID> {}
+// SAFETY: See above.
+unsafe impl<T: ?Sized + ListItem<ID> + Sync, const ID: u64> Sync for List<T, ID> {}
+
+impl<T: ?Sized + ListItem<ID>, const ID: u64> List<T, ID> {
+    /// Creates a new empty list.
+    pub const fn new() -> Self {
+        Self {
+            first: core::ptr::null_mut(),
+            len: 0,
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements in the list in O(1).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn insert_inner(&mut self, item: ListArc<T, ID>, front: bool) {
+        // Transfer the `ListArc`'s own reference into the list: move the
+        // inner `Arc` out without running the `ListArc` drop, so the
+        // tracking bit stays claimed while the item is in the list and
+        // exactly one reference changes hands (reclaimed in `unlink`).
+        let item = core::mem::ManuallyDrop::new(item);
+        // SAFETY: `item` is ManuallyDrop, so the arc is moved out exactly
+        // once and no drop runs afterwards.
+        let arc = unsafe { core::ptr::read(&item.arc) };
+        let raw = Arc::into_raw(arc);
+        // SAFETY: `raw` is a valid item; its links are currently unused
+        // per the `ListArc` invariant.
+        let links = unsafe { (*raw).links() as *const ListLinks<ID> as *mut ListLinks<ID> };
+        if self.first.is_null() {
+            // SAFETY: The item is not in any list, so its links are ours
+            // to initialise.
+            unsafe {
+                (*links).next = links;
+                (*links).prev = links;
+            }
+            self.first = links;
+        } else {
+            let first = self.first;
+            // SAFETY: The list is well-formed per the type invariant.
+            unsafe {
+                let last = (*first).prev;
+                (*links).next = first;
+                (*links).prev = last;
+                (*last).next = links;
+                (*first).prev = links;
+            }
+            if front {
+                self.first = links;
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Appends an item to the back of the list.
+    pub fn push_back(&mut self, item: ListArc<T, ID>) {
+        self.insert_inner(item, false);
+    }
+
+    /// Prepends an item to the front of the list.
+    pub fn push_front(&mut self, item: ListArc<T, ID>) {
+        self.insert_inner(item, true);
+    }
+
+    /// Removes and returns the first element, if any.
+    pub fn pop_front(&mut self) -> Option<ListArc<T, ID>> {
+        if self.first.is_null() {
+            return None;
+        }
+        let links = self.first;
+        // SAFETY: `links` belongs to an item of this list per the type
+        // invariant.
+        Some(unsafe { self.unlink(links) })
+    }
+
+    /// Unlinks `links` from the list and reconstitutes the owning
+    /// `ListArc`.
+    ///
+    /// # Safety
+    ///
+    /// `links` must belong to an item currently in this list.
+    unsafe fn unlink(&mut self, links: *mut ListLinks<ID>) -> ListArc<T, ID> {
+        // SAFETY: Per the function contract the list is non-empty and
+        // `links` is part of it.
+        unsafe {
+            let next = (*links).next;
+            let prev = (*links).prev;
+            if next == links {
+                self.first = core::ptr::null_mut();
+            } else {
+                (*next).prev = prev;
+                (*prev).next = next;
+                if self.first == links {
+                    self.first = next;
+                }
+            }
+            (*links).next = core::ptr::null_mut();
+            (*links).prev = core::ptr::null_mut();
+        }
+        // Every removal funnels through here (pop_front, cursor removal),
+        // so the count stays consistent across all mutation paths.
+        self.len -= 1;
+        // SAFETY: The item was inserted via `insert_inner`, which leaked
+        // one `Arc` reference that we now take back; the `ListArc` claim
+        // travelled with it.
+        let item = unsafe { Arc::from_raw(container_of_links::<T, ID>(links)) };
+        ListArc {
+            arc: item,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized + ListItem<ID>, const ID: u64> Drop for List<T, ID> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+/// Recovers the item pointer from a pointer to its links field.
+///
+/// # Safety (of use)
+///
+/// `links` must point at the `ListLinks` returned by `<T as
+/// ListItem>::links` for a live item.
+fn container_of_links<T: ?Sized + ListItem<ID>, const ID: u64>(
+    links: *mut ListLinks<ID>,
+) -> *const T
+where
+    T: Sized,
+{
+    (links as usize - T::LINKS_OFFSET) as *const T
+}
+
+/// A cursor into a [`List`], pointing at one of its elements.
+pub struct Cursor<'a, T: ListItem<ID>, const ID: u64 = 0> {
+    list: &'a mut List<T, ID>,
+    current: NonNull<ListLinks<ID>>,
+}
+
+impl<T: ListItem<ID>, const ID: u64> List<T, ID> {
+    /// Clones every element's refcounted handle into a freshly-allocated
+    /// vector.
+    ///
+    /// The clones are plain [`Arc`]s, not [`ListArc`]s: list ownership
+    /// stays with the list and the snapshot is read-only by
+    /// construction, outliving any subsequent clearing of the list. The
+    /// caller holds whatever lock guards this list for the duration,
+    /// same as any traversal; this helper just replaces the
+    /// reserve-then-clone dance debug paths repeat by hand.
+    pub fn snapshot(&self) -> Result<Vec<Arc<T>>>
+    where
+        T: Sized,
+    {
+        let mut out = Vec::new();
+        out.try_reserve_exact(self.len).map_err(|_| ENOMEM)?;
+        if self.first.is_null() {
+            return Ok(out);
+        }
+        let mut links = self.first;
+        loop {
+            // SAFETY: The list is well-formed, so every links pointer
+            // belongs to a live element kept alive by the list's
+            // reference.
+            let item = unsafe { &*container_of_links::<T, ID>(links) };
+            // SAFETY: The list holds a reference to the element, so
+            // reconstituting it, cloning, and forgetting the original
+            // leaves the list's reference untouched while the clone owns
+            // a fresh increment (the `Arc::increment_strong_count`
+            // pattern).
+            let original = unsafe { Arc::from_raw(item as *const T) };
+            let clone = original.clone();
+            core::mem::forget(original);
+            out.push(clone);
+            // SAFETY: See above.
+            links = unsafe { (*links).next };
+            if links == self.first {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Returns a cursor at the first element, or [`None`] if empty.
+    pub fn cursor_front_mut(&mut self) -> Option<Cursor<'_, T, ID>> {
+        let first = NonNull::new(self.first)?;
+        Some(Cursor {
+            list: self,
+            current: first,
+        })
+    }
+
+    /// Returns a cursor at the last element, or [`None`] if empty.
+    ///
+    /// Together with [`Cursor::move_prev`] this allows walking the list
+    /// from the tail, e.g. to scan the most recently appended entries
+    /// first.
+    pub fn cursor_back_mut(&mut self) -> Option<Cursor<'_, T, ID>> {
+        let first = NonNull::new(self.first)?;
+        // SAFETY: The list is circular and non-empty, so the first
+        // element's `prev` is the last element.
+        let last = unsafe { (*first.as_ptr()).prev };
+        Some(Cursor {
+            list: self,
+            // SAFETY: Links of elements in a list are never null.
+            current: unsafe { NonNull::new_unchecked(last) },
+        })
+    }
+}
+
+impl<'a, T: ListItem<ID>, const ID: u64> Cursor<'a, T, ID> {
+    /// Returns a shared reference to the current element.
+    pub fn current(&self) -> &T {
+        // SAFETY: The cursor points at an element of the list per
+        // construction.
+        unsafe { &*container_of_links::<T, ID>(self.current.as_ptr()) }
+    }
+
+    /// Moves the cursor to the next element, returning [`None`] at the end
+    /// of the list.
+    pub fn move_next(self) -> Option<Cursor<'a, T, ID>> {
+        // SAFETY: The list is well-formed, so `next` is a valid element.
+        let next = unsafe { (*self.current.as_ptr()).next };
+        if next == self.list.first {
+            return None;
+        }
+        Some(Cursor {
+            list: self.list,
+            // SAFETY: Links in a list are never null.
+            current: unsafe { NonNull::new_unchecked(next) },
+        })
+    }
+
+    /// Moves the cursor to the previous element, returning [`None`] at
+    /// the front of the list.
+    ///
+    /// The mirror of [`Cursor::move_next`]; removal via
+    /// [`Cursor::remove_current`] works the same regardless of the
+    /// direction the cursor arrived from.
+    pub fn move_prev(self) -> Option<Cursor<'a, T, ID>> {
+        if self.current.as_ptr() == self.list.first {
+            return None;
+        }
+        // SAFETY: The list is well-formed, so `prev` is a valid element.
+        let prev = unsafe { (*self.current.as_ptr()).prev };
+        Some(Cursor {
+            list: self.list,
+            // SAFETY: Links in a list are never null.
+            current: unsafe { NonNull::new_unchecked(prev) },
+        })
+    }
+
+    /// Removes the current element from the list, consuming the cursor.
+    pub fn remove_current(self) -> ListArc<T, ID> {
+        // SAFETY: The cursor points at an element of this list.
+        unsafe { self.list.unlink(self.current.as_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::UniqueArc;
+    use core::sync::atomic::AtomicBool;
+
+    struct Item {
+        value: u32,
+        links: ListLinks,
+        tracker: AtomicBool,
+    }
+
+    impl Item {
+        fn new_arc(value: u32) -> ListArc<Item> {
+            ListArc::from_unique(
+                UniqueArc::try_new(Item {
+                    value,
+                    links: ListLinks::new(),
+                    tracker: AtomicBool::new(false),
+                })
+                .unwrap(),
+            )
+        }
+    }
+
+    impl ListArcSafe for Item {
+        fn tracker(&self) -> &AtomicBool {
+            &self.tracker
+        }
+    }
+
+    // SAFETY: `links` names the only links field, matching the offset.
+    unsafe impl ListItem for Item {
+        const LINKS_OFFSET: usize = core::mem::offset_of!(Item, links);
+
+        fn links(&self) -> &ListLinks {
+            &self.links
+        }
+    }
+
+    #[test]
+    fn len_tracks_mixed_mutation() {
+        let mut list = List::<Item>::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+
+        list.push_back(Item::new_arc(1));
+        list.push_back(Item::new_arc(2));
+        list.push_front(Item::new_arc(0));
+        assert_eq!(list.len(), 3);
+
+        assert_eq!(list.pop_front().unwrap().value, 0);
+        assert_eq!(list.len(), 2);
+
+        // Cursor removal funnels through the same unlink.
+        let cursor = list.cursor_front_mut().unwrap();
+        let removed = cursor.remove_current();
+        assert_eq!(removed.value, 1);
+        assert_eq!(list.len(), 1);
+
+        assert_eq!(list.pop_front().unwrap().value, 2);
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+        assert!(list.pop_front().is_none());
+    }
+
+    #[test]
+    fn backward_walk_and_unique_claim() {
+        let mut list = List::<Item>::new();
+        for v in [1u32, 2, 3] {
+            list.push_back(Item::new_arc(v));
+        }
+
+        // Walk backward: 3, 2, 1.
+        let mut seen = [0u32; 3];
+        let mut cursor = list.cursor_back_mut();
+        let mut i = 0;
+        while let Some(c) = cursor {
+            seen[i] = c.current().value;
+            i += 1;
+            cursor = c.move_prev();
+        }
+        assert_eq!(seen, [3, 2, 1]);
+
+        // try_from_arc: second claim on the same item fails.
+        let popped = list.pop_front().unwrap();
+        let arc = popped.clone_arc();
+        let relisted = ListArc::try_from_arc(arc.clone());
+        // `popped` still claims the bit.
+        assert!(relisted.is_err());
+        drop(popped);
+        assert!(ListArc::try_from_arc(arc).is_ok());
+    }
+}
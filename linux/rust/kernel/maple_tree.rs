@@ -0,0 +1,592 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Maple trees.
+//!
+//! C header: [`include/linux/maple_tree.h`](srctree/include/linux/maple_tree.h)
+
+use crate::{
+    alloc::Flags,
+    bindings,
+    error::{code::*, to_result, Result},
+    types::{ForeignOwnable, Opaque},
+};
+use core::{marker::PhantomData, ops::RangeBounds};
+
+/// Normalises generic range bounds to inclusive `(min, max)` limits.
+///
+/// Returns [`None`] for degenerate ranges, including the
+/// `Excluded(0)` end and `Excluded(usize::MAX)` start bounds whose
+/// naive arithmetic would wrap.
+fn bound_limits(range: impl RangeBounds<usize>) -> Option<(usize, usize)> {
+    use core::ops::Bound;
+    let min = match range.start_bound() {
+        Bound::Included(&b) => b,
+        Bound::Excluded(&b) => b.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let max = match range.end_bound() {
+        Bound::Included(&b) => b,
+        Bound::Excluded(&b) => b.checked_sub(1)?,
+        Bound::Unbounded => usize::MAX,
+    };
+    if min > max {
+        return None;
+    }
+    Some((min, max))
+}
+
+/// A maple tree whose entries borrow externally-owned objects.
+///
+/// An index-only structure: the tree maps ranges to `&'a T` and never
+/// drops or frees the referents -- erase and tree drop just forget the
+/// pointers (a reference's "foreign ownership" owns nothing). The
+/// lifetime is the load-bearing part: **every stored reference must
+/// outlive the tree**, which in practice means the referents live in an
+/// arena or pinned collection owned alongside it, torn down strictly
+/// after. Mutable borrows are never handed out; `load` yields `&T`.
+///
+/// This is the supported way to use the tree as a pure interval index
+/// over objects owned elsewhere; a runtime "don't drop" mode was
+/// rejected because ownership semantics belong in the type, not a flag.
+pub type MapleTreeRef<'a, T> = MapleTree<&'a T>;
+
+/// A maple tree storing values of type `T` over ranges of `usize` indices.
+///
+/// The tree takes ownership of the stored values via [`ForeignOwnable`];
+/// erasing an entry (or dropping the tree) reclaims and drops them.
+///
+/// # Invariants
+///
+/// Every non-null entry stored in `tree` is a pointer previously returned
+/// by `T::into_foreign` and not yet reclaimed.
+pub struct MapleTree<T: ForeignOwnable> {
+    pub(crate) tree: Opaque<bindings::maple_tree>,
+    pub(crate) _p: PhantomData<T>,
+}
+
+// SAFETY: The tree owns `T` values, so it is `Send`/`Sync` when they are.
+unsafe impl<T: ForeignOwnable + Send> Send for MapleTree<T> {}
+// SAFETY: See above; all access is internally locked by `ma_lock`.
+unsafe impl<T: ForeignOwnable + Sync> Sync for MapleTree<T> {}
+
+impl<T: ForeignOwnable> MapleTree<T> {
+    fn with_flags(flags: u32) -> Self {
+        let tree = Opaque::<bindings::maple_tree>::uninit();
+        // SAFETY: `tree` is valid for write and initialised before use.
+        unsafe { bindings::mt_init_flags(tree.get(), flags) };
+        // INVARIANT: An empty tree trivially satisfies the invariant.
+        Self {
+            tree,
+            _p: PhantomData,
+        }
+    }
+
+    /// Creates a new, empty maple tree.
+    pub fn new() -> Self {
+        Self::with_flags(0)
+    }
+
+    /// Inserts `value` covering the inclusive index range `first..=last`.
+    ///
+    /// Fails with `EEXIST`-shaped errors from the C side if the range
+    /// overlaps an existing entry, in which case `value` is dropped.
+    pub fn insert_range(&self, first: usize, last: usize, value: T, gfp: Flags) -> Result {
+        if first > last {
+            return Err(EINVAL);
+        }
+        let ptr = value.into_foreign();
+        // SAFETY: The tree is initialised, and `ptr` came from
+        // `into_foreign` just above.
+        let ret = unsafe {
+            bindings::mtree_insert_range(
+                self.tree.get(),
+                first as _,
+                last as _,
+                ptr,
+                gfp.as_raw(),
+            )
+        };
+        if ret != 0 {
+            // SAFETY: Insertion failed, so ownership returns to us.
+            drop(unsafe { T::from_foreign(ptr) });
+        }
+        to_result(ret)
+    }
+
+    /// Removes and returns the entry covering `index`, if any.
+    pub fn erase(&self, index: usize) -> Option<T> {
+        // SAFETY: The tree is initialised.
+        let ptr = unsafe { bindings::mtree_erase(self.tree.get(), index as _) };
+        if ptr.is_null() {
+            return None;
+        }
+        // SAFETY: Non-null entries are `into_foreign` pointers per the
+        // type invariant, and erasing removed it from the tree.
+        Some(unsafe { T::from_foreign(ptr) })
+    }
+
+    /// Invokes `f` for every stored entry with its inclusive range and a
+    /// shared borrow of the value.
+    ///
+    /// Internal iteration (a callback, not an `Iterator`): the borrows
+    /// are bounded by the lock this method holds across the walk, which
+    /// an external iterator cannot express without a self-referential
+    /// guard. Ranges are inclusive for the same losslessness reason as
+    /// [`MapleLock::load_range`]. The borrow is shared -- `Arc`-stored
+    /// values can be cloned out, nothing can be mutated -- which is the
+    /// contract separating this from the locked mutable handles
+    /// ([`MapleTreeAlloc::alloc_range_locked`], `load_range`): pick this
+    /// for read-mostly enumeration, those for in-place update.
+    ///
+    /// The callback runs under a spinlock: no sleeping.
+    pub fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(core::ops::RangeInclusive<usize>, T::Borrowed<'_>),
+    {
+        let _guard = self.lock();
+        // SAFETY: The tree is initialised and locked for the whole walk.
+        unsafe {
+            let mut mas = core::mem::zeroed::<bindings::ma_state>();
+            mas.tree = self.tree.get();
+            mas.index = 0;
+            mas.last = 0;
+            mas.node = bindings::MAS_START;
+            loop {
+                let entry = bindings::mas_find(&mut mas, usize::MAX as _);
+                if entry.is_null() {
+                    break;
+                }
+                // SAFETY: Non-null entries are `into_foreign` pointers,
+                // and the held lock outlives the borrow handed to `f`.
+                f(
+                    mas.index as usize..=mas.last as usize,
+                    T::borrow(entry),
+                );
+            }
+        }
+    }
+
+    /// Removes every entry overlapping `range`, returning how many were
+    /// removed.
+    ///
+    /// Entry-granularity semantics, like [`erase`](Self::erase): an
+    /// entry that merely overlaps the span is removed *whole*, not
+    /// split -- splitting a partially-covered entry would require
+    /// duplicating the stored `T`, which [`ForeignOwnable`] cannot do.
+    /// (This differs from C's `mtree_store_range(NULL)` overwrite
+    /// model; callers needing split semantics must re-insert the
+    /// remainder themselves.) Reclaimed values are dropped after the
+    /// tree lock is released, so arbitrary `T::drop` code never runs
+    /// under the spinlock.
+    pub fn erase_range(&self, range: impl RangeBounds<usize>) -> Result<usize> {
+        let (min, max) = bound_limits(range).ok_or(EINVAL)?;
+
+        let mut reclaimed = alloc::vec::Vec::new();
+        {
+            let _guard = self.lock();
+            // SAFETY: The tree is initialised and locked; the ma_state
+            // walks [min, max] and erases each hit in place.
+            unsafe {
+                let mut mas = core::mem::zeroed::<bindings::ma_state>();
+                mas.tree = self.tree.get();
+                mas.index = min as _;
+                mas.last = min as _;
+                mas.node = bindings::MAS_START;
+                loop {
+                    let entry = bindings::mas_find(&mut mas, max as _);
+                    if entry.is_null() {
+                        break;
+                    }
+                    bindings::mas_erase(&mut mas);
+                    if reclaimed.try_reserve(1).is_err() {
+                        // Reclaim inline rather than fail: dropping under
+                        // the lock is the lesser evil versus leaking.
+                        drop(T::from_foreign(entry));
+                    } else {
+                        reclaimed.push(entry as usize);
+                    }
+                }
+            }
+        }
+        let count = reclaimed.len();
+        for ptr in reclaimed {
+            // SAFETY: Each pointer was erased from the tree above and is
+            // an `into_foreign` pointer per the type invariant.
+            drop(unsafe { T::from_foreign(ptr as *mut core::ffi::c_void) });
+        }
+        Ok(count)
+    }
+
+    /// Dumps every stored range into `m`, one line per entry.
+    ///
+    /// Debug-only (compiled out without `CONFIG_DEBUG_KERNEL`): the tree
+    /// lock is held for the whole walk, so this is for debugfs-style
+    /// inspection, not production paths. Wire it into a `debug_print`
+    /// implementation the same way the binder driver dumps its state.
+    #[cfg(CONFIG_DEBUG_KERNEL)]
+    pub fn dump(&self, m: &mut crate::seq_file::SeqFile) {
+        let _guard = self.lock();
+        // SAFETY: The tree is initialised and locked; the `ma_state` is
+        // local and set up for a full walk.
+        unsafe {
+            let mut mas = core::mem::zeroed::<bindings::ma_state>();
+            mas.tree = self.tree.get();
+            mas.index = 0;
+            mas.last = 0;
+            mas.node = bindings::MAS_START;
+            loop {
+                let entry = bindings::mas_find(&mut mas, usize::MAX as _);
+                if entry.is_null() {
+                    break;
+                }
+                crate::seq_print!(m, "  [{:#x}, {:#x}]
+", mas.index, mas.last);
+            }
+        }
+    }
+
+    /// Like [`dump`](Self::dump), but also prints each value with its
+    /// `Debug` representation.
+    #[cfg(CONFIG_DEBUG_KERNEL)]
+    pub fn dump_values(&self, m: &mut crate::seq_file::SeqFile)
+    where
+        for<'a> T::Borrowed<'a>: core::fmt::Debug,
+    {
+        let _guard = self.lock();
+        // SAFETY: As in `dump`; borrowing each entry is sound while the
+        // lock is held.
+        unsafe {
+            let mut mas = core::mem::zeroed::<bindings::ma_state>();
+            mas.tree = self.tree.get();
+            mas.index = 0;
+            mas.last = 0;
+            mas.node = bindings::MAS_START;
+            loop {
+                let entry = bindings::mas_find(&mut mas, usize::MAX as _);
+                if entry.is_null() {
+                    break;
+                }
+                let value = T::borrow(entry);
+                crate::seq_print!(m, "  [{:#x}, {:#x}]: {:?}
+", mas.index, mas.last, value);
+            }
+        }
+    }
+
+    /// Takes the internal tree lock, allowing entries to be borrowed.
+    pub fn lock(&self) -> MapleLock<'_, T> {
+        // SAFETY: The tree is initialised; `ma_lock` is its spinlock.
+        unsafe { bindings::spin_lock(core::ptr::addr_of_mut!((*self.tree.get()).ma_lock)) };
+        MapleLock { tree: self }
+    }
+}
+
+impl<T: ForeignOwnable> Drop for MapleTree<T> {
+    fn drop(&mut self) {
+        let mut index: core::ffi::c_ulong = 0;
+        loop {
+            // SAFETY: The tree is initialised and nobody else can access
+            // it during drop.
+            let ptr = unsafe { bindings::mt_find(self.tree.get(), &mut index, usize::MAX as _) };
+            if ptr.is_null() {
+                break;
+            }
+            // SAFETY: Per the type invariant; the entry is dead once the
+            // tree is destroyed below.
+            drop(unsafe { T::from_foreign(ptr) });
+            if index == usize::MAX as core::ffi::c_ulong {
+                break;
+            }
+            index += 1;
+        }
+        // SAFETY: The tree is initialised and all entries were reclaimed.
+        unsafe { bindings::__mt_destroy(self.tree.get()) };
+    }
+}
+
+/// A guard holding the maple tree's internal spinlock.
+///
+/// Entries borrowed through the guard stay valid while it is held, because
+/// no mutation can race the borrow.
+#[must_use = "the lock is released immediately when the guard is unused"]
+pub struct MapleLock<'a, T: ForeignOwnable> {
+    tree: &'a MapleTree<T>,
+}
+
+impl<'a, T: ForeignOwnable> MapleLock<'a, T> {
+    /// Borrows the value covering `index`, if any.
+    pub fn load(&mut self, index: usize) -> Option<T::BorrowedMut<'_>> {
+        // SAFETY: The tree is initialised and locked by this guard.
+        let ptr = unsafe { bindings::mtree_load(self.tree.tree.get(), index as _) };
+        if ptr.is_null() {
+            return None;
+        }
+        // SAFETY: Non-null entries are `into_foreign` pointers, and the
+        // guard prevents concurrent mutation for the borrow's lifetime.
+        Some(unsafe { T::borrow_mut(ptr) })
+    }
+}
+
+impl<'a, T: ForeignOwnable> MapleLock<'a, T> {
+    /// Borrows the value covering `index` together with the inclusive
+    /// range it covers.
+    ///
+    /// The range is reported as `RangeInclusive` because maple tree
+    /// entries are inclusive on both ends and `last == usize::MAX` has
+    /// no exclusive-range representation; callers wanting a size can use
+    /// `last - first + 1` when they know it cannot wrap. This is what
+    /// allocators need when an existing mapping's extent matters (e.g.
+    /// reserving an existing buffer and reporting its size).
+    pub fn load_range(
+        &mut self,
+        index: usize,
+    ) -> Option<(core::ops::RangeInclusive<usize>, T::BorrowedMut<'_>)> {
+        // SAFETY: The tree is initialised and locked by this guard; the
+        // `ma_state` is local and positioned at `index`.
+        unsafe {
+            let mut mas = core::mem::zeroed::<bindings::ma_state>();
+            mas.tree = self.tree.tree.get();
+            mas.index = index as _;
+            mas.last = index as _;
+            mas.node = bindings::MAS_START;
+            let entry = bindings::mas_walk(&mut mas);
+            if entry.is_null() {
+                return None;
+            }
+            // SAFETY: Non-null entries are `into_foreign` pointers and
+            // the guard prevents mutation for the borrow's lifetime.
+            Some((
+                mas.index as usize..=mas.last as usize,
+                T::borrow_mut(entry),
+            ))
+        }
+    }
+}
+
+impl<T: ForeignOwnable> Drop for MapleLock<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: This guard holds the lock.
+        unsafe { bindings::spin_unlock(core::ptr::addr_of_mut!((*self.tree.tree.get()).ma_lock)) };
+    }
+}
+
+/// A maple tree in allocation mode (`MT_FLAGS_ALLOC_RANGE`), able to find
+/// and claim free ranges.
+pub struct MapleTreeAlloc<T: ForeignOwnable> {
+    tree: MapleTree<T>,
+}
+
+impl<T: ForeignOwnable> MapleTreeAlloc<T> {
+    /// Creates a new allocation tree.
+    pub fn new() -> Self {
+        Self {
+            tree: MapleTree::with_flags(bindings::MT_FLAGS_ALLOC_RANGE),
+        }
+    }
+
+
+    /// Allocates `size` consecutive units inside `range`, storing `value`
+    /// there, and returns the chosen start index.
+    pub fn alloc_range(
+        &self,
+        value: T,
+        size: usize,
+        range: impl RangeBounds<usize>,
+        gfp: Flags,
+    ) -> Result<usize> {
+        let (index, entry) = self.alloc_range_locked(value, size, range, gfp)?;
+        drop(entry);
+        Ok(index)
+    }
+
+    /// Like [`alloc_range`](Self::alloc_range), but keeps the tree locked
+    /// and returns a handle to the just-inserted value so it can be
+    /// initialised before any other user can observe the index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::alloc::flags::GFP_KERNEL;
+    /// use kernel::maple_tree::MapleTreeAlloc;
+    ///
+    /// let tree = MapleTreeAlloc::<Box<u32>>::new();
+    /// let (index, mut entry) = tree.alloc_range_locked(Box::try_new(0)?, 4, .., GFP_KERNEL)?;
+    /// *entry.value() = 7; // Initialise while nobody can see the index.
+    /// drop(entry); // Releases the tree lock.
+    /// assert_eq!(*tree.tree().lock().load(index).unwrap(), 7);
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn alloc_range_locked(
+        &self,
+        value: T,
+        size: usize,
+        range: impl RangeBounds<usize>,
+        gfp: Flags,
+    ) -> Result<(usize, LockedEntry<'_, T>)> {
+        let Some((min, max)) = bound_limits(range) else {
+            return Err(EINVAL);
+        };
+        if size == 0 {
+            return Err(EINVAL);
+        }
+        let ptr = value.into_foreign();
+
+        let guard = self.tree.lock();
+        // SAFETY: The tree is initialised and locked by `guard`; the
+        // `ma_state` is local, configured for a gap search followed by a
+        // store of the found range. The `mas_*` primitives expect the
+        // caller to hold `ma_lock`; `mtree_alloc_range` takes the lock
+        // itself and would self-deadlock here.
+        let result = unsafe {
+            let mut mas = core::mem::zeroed::<bindings::ma_state>();
+            mas.tree = self.tree.tree.get();
+            mas.node = bindings::MAS_START;
+            let ret = bindings::mas_empty_area(&mut mas, min as _, max as _, size as _);
+            if ret == 0 {
+                mas.last = mas.index + (size as core::ffi::c_ulong) - 1;
+                let ret = bindings::mas_store_gfp(&mut mas, ptr, gfp.as_raw());
+                if ret == 0 {
+                    Ok(mas.index as usize)
+                } else {
+                    Err(ret)
+                }
+            } else {
+                Err(ret)
+            }
+        };
+        match result {
+            Ok(index) => Ok((index, LockedEntry { guard, ptr })),
+            Err(ret) => {
+                drop(guard);
+                // SAFETY: The store did not happen, so ownership returns
+                // to us.
+                drop(unsafe { T::from_foreign(ptr) });
+                Err(crate::error::Error::from_errno(ret))
+            }
+        }
+    }
+
+    /// Finds, without claiming it, the lowest index in `range` at which
+    /// `size` consecutive units are free.
+    ///
+    /// Advisory by nature: the answer can be stale the moment the
+    /// internal lock drops, so use it for inspection and sizing
+    /// heuristics only -- callers that need the gap must go through
+    /// [`alloc_range`](Self::alloc_range), which finds and claims
+    /// atomically.
+    ///
+    /// Returns [`None`] when no gap of `size` fits in `range` (including
+    /// for degenerate inputs such as a zero size or an empty range).
+    pub fn first_free(&self, size: usize, range: impl RangeBounds<usize>) -> Option<usize> {
+        let (min, max) = bound_limits(range)?;
+        if size == 0 {
+            return None;
+        }
+        let _guard = self.tree.lock();
+        // SAFETY: The tree is initialised and locked; the `ma_state` is
+        // local and configured for a gap search over [min, max].
+        unsafe {
+            let mut mas = core::mem::zeroed::<bindings::ma_state>();
+            mas.tree = self.tree.tree.get();
+            mas.node = bindings::MAS_START;
+            let ret = bindings::mas_empty_area(&mut mas, min as _, max as _, size as _);
+            if ret == 0 {
+                Some(mas.index as usize)
+            } else {
+                // `-EBUSY` means no gap; other errors are degenerate
+                // inputs, also reported as absence.
+                None
+            }
+        }
+    }
+
+    /// Returns the underlying tree, for lookups and erasure.
+    pub fn tree(&self) -> &MapleTree<T> {
+        &self.tree
+    }
+}
+
+/// A freshly-allocated entry, borrowed while the tree lock is still held.
+///
+/// Dropping it releases the lock, publishing the entry.
+#[must_use = "the lock is released immediately when the entry is unused"]
+pub struct LockedEntry<'a, T: ForeignOwnable> {
+    guard: MapleLock<'a, T>,
+    ptr: *mut core::ffi::c_void,
+}
+
+impl<T: ForeignOwnable> LockedEntry<'_, T> {
+    /// Borrows the stored value mutably.
+    pub fn value(&mut self) -> T::BorrowedMut<'_> {
+        // SAFETY: The entry was stored under the lock held by `guard`, so
+        // it cannot be reclaimed while this borrow lives.
+        unsafe { T::borrow_mut(self.ptr) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::flags::GFP_KERNEL;
+    use alloc::boxed::Box;
+
+    #[test]
+    fn bound_normalisation() {
+        assert_eq!(bound_limits(0..=10), Some((0, 10)));
+        assert_eq!(bound_limits(2..10), Some((2, 9)));
+        assert_eq!(bound_limits(..), Some((0, usize::MAX)));
+        // The degenerate forms whose naive arithmetic would wrap.
+        assert_eq!(bound_limits(0..0), None);
+        assert_eq!(
+            bound_limits((
+                core::ops::Bound::Excluded(usize::MAX),
+                core::ops::Bound::Unbounded,
+            )),
+            None,
+        );
+        assert_eq!(bound_limits(5..3), None);
+    }
+
+    #[test]
+    fn alloc_range_locked_initialises_before_publication() {
+        let tree = MapleTreeAlloc::<Box<u32>>::new();
+        let (index, mut entry) = tree
+            .alloc_range_locked(Box::try_new(0u32).unwrap(), 4, .., GFP_KERNEL)
+            .unwrap();
+        *entry.value() = 7;
+        drop(entry); // Releases the lock, publishing the entry.
+        assert_eq!(**tree.tree().lock().load(index).unwrap(), 7);
+    }
+
+    #[test]
+    fn first_free_and_erase_range() {
+        let tree = MapleTreeAlloc::<Box<u32>>::new();
+        let a = tree
+            .alloc_range(Box::try_new(1u32).unwrap(), 4, .., GFP_KERNEL)
+            .unwrap();
+        let b = tree
+            .alloc_range(Box::try_new(2u32).unwrap(), 4, .., GFP_KERNEL)
+            .unwrap();
+        // Free the first range; the gap it leaves is what first_free
+        // must report for a fitting request.
+        assert!(tree.tree().erase(a).is_some());
+        assert_eq!(tree.first_free(4, ..), Some(a));
+        // Nothing larger than the remaining space fits.
+        assert_eq!(tree.first_free(usize::MAX, ..), None);
+
+        // erase_range removes the overlapped entry whole.
+        assert_eq!(tree.tree().erase_range(b..b + 1).unwrap(), 1);
+        assert!(tree.tree().lock().load(b).is_none());
+    }
+
+    #[test]
+    fn load_range_reports_extent() {
+        let tree = MapleTree::<Box<u32>>::new();
+        tree.insert_range(10, 20, Box::try_new(5u32).unwrap(), GFP_KERNEL)
+            .unwrap();
+        let mut guard = tree.lock();
+        let (range, value) = guard.load_range(15).unwrap();
+        assert_eq!(range, 10..=20);
+        assert_eq!(**value, 5);
+    }
+}
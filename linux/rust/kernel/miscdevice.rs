@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Miscdevice support.
+//!
+//! C header: [`include/linux/miscdevice.h`](srctree/include/linux/miscdevice.h)
+
+use crate::{
+    bindings,
+    error::{code::*, to_result, Result},
+    file::{File, LocalFile},
+    str::CStr,
+    types::ForeignOwnable,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{marker::PhantomData, marker::PhantomPinned, pin::Pin};
+
+/// Options for creating a misc device.
+#[derive(Copy, Clone)]
+pub struct MiscDeviceOptions {
+    /// The name of the miscdevice.
+    pub name: &'static CStr,
+}
+
+impl MiscDeviceOptions {
+    /// Create a raw `struct miscdevice` ready for registration.
+    pub const fn into_raw(self) -> bindings::miscdevice {
+        // SAFETY: All zeros is valid for `struct miscdevice`.
+        let mut result: bindings::miscdevice = unsafe { core::mem::zeroed() };
+        result.minor = bindings::MISC_DYNAMIC_MINOR as _;
+        result.name = self.name.as_char_ptr();
+        result
+    }
+}
+
+/// Trait implemented by the private data of an open misc device.
+pub trait MiscDevice {
+    /// What kind of pointer should [`Self`] be wrapped in.
+    type Ptr: ForeignOwnable + Send + Sync;
+
+    /// Data shared by all files opened against one registration.
+    ///
+    /// Use this (via [`MiscDeviceRegistration::register_with_data`] and
+    /// [`MiscDevice::open_with_data`]) when each `open` needs access to
+    /// per-registration context, e.g. a shared `Arc` to driver state. It
+    /// plays the same role as `OpenData` does for `file::Operations`.
+    type RegData: Sync + 'static;
+
+    /// Called when the misc device is opened.
+    ///
+    /// The returned pointer will be stored as the private data for the
+    /// file.
+    fn open(_file: &File) -> Result<Self::Ptr> {
+        Err(EINVAL)
+    }
+
+    /// Like [`MiscDevice::open`], but also receives the data passed to
+    /// [`MiscDeviceRegistration::register_with_data`].
+    ///
+    /// The default implementation delegates to [`MiscDevice::open`], so
+    /// devices that registered without data need not implement this.
+    fn open_with_data(_data: &Self::RegData, file: &File) -> Result<Self::Ptr> {
+        Self::open(file)
+    }
+
+    /// Called when the misc device is released.
+    fn release(device: Self::Ptr, _file: &File) {
+        drop(device);
+    }
+
+    /// Whether this device implements `ioctl`.
+    const HAS_IOCTL: bool = false;
+
+    /// Whether this device implements `llseek`.
+    const HAS_LLSEEK: bool = false;
+
+    /// Repositions the file, typically by delegating to
+    /// [`generic_llseek`](crate::fs::generic_llseek) with the device's
+    /// size.
+    fn llseek(
+        _device: <Self::Ptr as ForeignOwnable>::Borrowed<'_>,
+        _file: &LocalFile,
+        _offset: i64,
+        _whence: i32,
+    ) -> Result<i64> {
+        Err(EINVAL)
+    }
+
+    /// Whether this device overrides mmap address selection.
+    const HAS_GET_UNMAPPED_AREA: bool = false;
+
+    /// Picks the address at which an mmap of this device will be placed.
+    ///
+    /// Only consulted when [`HAS_GET_UNMAPPED_AREA`](Self::HAS_GET_UNMAPPED_AREA)
+    /// is set; otherwise the core's default placement runs. The address
+    /// returned here is what the subsequent `mmap` callback receives, so
+    /// alignment constraints (huge pages, hardware windows) belong here,
+    /// while enforcement of the mapping's contents stays in `mmap`.
+    fn get_unmapped_area(
+        _device: <Self::Ptr as ForeignOwnable>::Borrowed<'_>,
+        _file: &File,
+        _addr: usize,
+        _len: usize,
+        _pgoff: usize,
+        _flags: usize,
+    ) -> Result<usize> {
+        Err(EINVAL)
+    }
+
+    /// Handler for ioctls.
+    fn ioctl(
+        _device: <Self::Ptr as ForeignOwnable>::Borrowed<'_>,
+        _file: &File,
+        _cmd: u32,
+        _arg: usize,
+    ) -> Result<isize> {
+        Err(ENOTTY)
+    }
+}
+
+/// A registration of a miscdevice.
+///
+/// # Invariants
+///
+/// While pinned and until dropped, `inner` is registered with the C misc
+/// core, whose callbacks recover this struct via `container_of` from the
+/// `inner` field.
+pub struct MiscDeviceRegistration<T: MiscDevice> {
+    inner: bindings::miscdevice,
+    data: T::RegData,
+    _pin: PhantomPinned,
+    _t: PhantomData<T>,
+}
+
+// SAFETY: The registration is only mutated during construction/teardown;
+// `RegData: Sync` covers the data shared with the open callbacks.
+unsafe impl<T: MiscDevice> Sync for MiscDeviceRegistration<T> {}
+// SAFETY: See above.
+unsafe impl<T: MiscDevice> Send for MiscDeviceRegistration<T> where T::RegData: Send {}
+
+impl<T: MiscDevice> MiscDeviceRegistration<T> {
+    /// Registers a misc device without per-registration data.
+    pub fn register(opts: MiscDeviceOptions) -> Result<Pin<Box<Self>>>
+    where
+        T: MiscDevice<RegData = ()>,
+    {
+        Self::register_with_data(opts, ())
+    }
+
+    /// Registers a misc device carrying `data`, which every open can
+    /// retrieve via [`MiscDevice::open_with_data`].
+    pub fn register_with_data(opts: MiscDeviceOptions, data: T::RegData) -> Result<Pin<Box<Self>>> {
+        let mut this = Pin::from(Box::try_new(Self {
+            inner: opts.into_raw(),
+            data,
+            _pin: PhantomPinned,
+            _t: PhantomData,
+        })?);
+
+        // SAFETY: We never move out of `this` after registration.
+        let inner = unsafe { &mut this.as_mut().get_unchecked_mut().inner };
+        inner.fops = &VtableHolder::<T>::VTABLE;
+
+        // SAFETY: `inner` is pinned for the lifetime of the box and
+        // unregistered in `Drop`, fulfilling the registration contract.
+        to_result(unsafe { bindings::misc_register(inner) })?;
+        Ok(this)
+    }
+
+    /// Returns the data passed at registration time.
+    pub fn data(&self) -> &T::RegData {
+        &self.data
+    }
+}
+
+impl<T: MiscDevice> Drop for MiscDeviceRegistration<T> {
+    fn drop(&mut self) {
+        // SAFETY: The device was registered in `register_with_data` and is
+        // being unregistered exactly once.
+        unsafe { bindings::misc_deregister(&mut self.inner) };
+    }
+}
+
+struct VtableHolder<T: MiscDevice>(PhantomData<T>);
+
+impl<T: MiscDevice> VtableHolder<T> {
+    const VTABLE: bindings::file_operations = bindings::file_operations {
+        open: Some(fops_open::<T>),
+        release: Some(fops_release::<T>),
+        unlocked_ioctl: if T::HAS_IOCTL {
+            Some(fops_ioctl::<T>)
+        } else {
+            None
+        },
+        get_unmapped_area: if T::HAS_GET_UNMAPPED_AREA {
+            Some(fops_get_unmapped_area::<T>)
+        } else {
+            None
+        },
+        llseek: if T::HAS_LLSEEK {
+            Some(fops_llseek::<T>)
+        } else {
+            None
+        },
+        // SAFETY: All zeros is a valid value for `struct file_operations`.
+        ..unsafe { core::mem::zeroed() }
+    };
+}
+
+/// # Safety
+///
+/// Called by the misc core with a valid inode and file; `file->private_data`
+/// holds the `struct miscdevice` pointer at this point.
+unsafe extern "C" fn fops_open<T: MiscDevice>(
+    _inode: *mut bindings::inode,
+    raw_file: *mut bindings::file,
+) -> core::ffi::c_int {
+    // SAFETY: The misc core stashes the `miscdevice` pointer in
+    // `private_data` before calling open.
+    let misc = unsafe { (*raw_file).private_data }.cast::<bindings::miscdevice>();
+    // SAFETY: `misc` is the `inner` field of a live registration per the
+    // registration invariant.
+    let reg = unsafe { crate::container_of!(misc, MiscDeviceRegistration<T>, inner) };
+    // SAFETY: The file is valid for the duration of this call.
+    let file = unsafe { File::from_ptr(raw_file) };
+
+    // SAFETY: The registration outlives every open file on it.
+    let data = unsafe { &(*reg).data };
+
+    let ptr = match T::open_with_data(data, file) {
+        Ok(ptr) => ptr,
+        Err(err) => return err.to_errno(),
+    };
+
+    // SAFETY: The open callback owns `private_data` from here on.
+    unsafe { (*raw_file).private_data = ptr.into_foreign() };
+    0
+}
+
+/// # Safety
+///
+/// Called by the VFS on the last close of a file whose `private_data` was
+/// set by `fops_open<T>`.
+unsafe extern "C" fn fops_release<T: MiscDevice>(
+    _inode: *mut bindings::inode,
+    raw_file: *mut bindings::file,
+) -> core::ffi::c_int {
+    // SAFETY: `private_data` was set by `fops_open` and is not used again.
+    let ptr = unsafe { <T::Ptr as ForeignOwnable>::from_foreign((*raw_file).private_data) };
+    // SAFETY: The file is valid for the duration of this call.
+    T::release(ptr, unsafe { File::from_ptr(raw_file) });
+    0
+}
+
+/// # Safety
+///
+/// Called by the VFS with the fdget guarantees on a file whose
+/// `private_data` was set by `fops_open<T>`.
+unsafe extern "C" fn fops_llseek<T: MiscDevice>(
+    raw_file: *mut bindings::file,
+    offset: bindings::loff_t,
+    whence: core::ffi::c_int,
+) -> bindings::loff_t {
+    // SAFETY: `private_data` was set by `fops_open` and outlives this
+    // call.
+    let device = unsafe { <T::Ptr as ForeignOwnable>::borrow((*raw_file).private_data) };
+    // SAFETY: The VFS invokes llseek with the no-concurrent-f_pos
+    // guarantee LocalFile encodes.
+    let file = unsafe { LocalFile::from_ptr(raw_file) };
+    match T::llseek(device, file, offset, whence) {
+        Ok(pos) => pos,
+        Err(err) => err.to_errno() as bindings::loff_t,
+    }
+}
+
+/// # Safety
+///
+/// Called by the mm core during mmap on a file whose `private_data` was
+/// set by `fops_open<T>`.
+unsafe extern "C" fn fops_get_unmapped_area<T: MiscDevice>(
+    raw_file: *mut bindings::file,
+    addr: core::ffi::c_ulong,
+    len: core::ffi::c_ulong,
+    pgoff: core::ffi::c_ulong,
+    flags: core::ffi::c_ulong,
+) -> core::ffi::c_ulong {
+    // SAFETY: `private_data` was set by `fops_open` and outlives this
+    // call.
+    let device = unsafe { <T::Ptr as ForeignOwnable>::borrow((*raw_file).private_data) };
+    // SAFETY: The file is valid for the duration of this call.
+    let file = unsafe { File::from_ptr(raw_file) };
+    match T::get_unmapped_area(device, file, addr as _, len as _, pgoff as _, flags as _) {
+        // A non-error address is returned as-is, per the C convention
+        // where the result doubles as an address or a negative errno.
+        Ok(chosen) => chosen as _,
+        Err(err) => err.to_errno() as core::ffi::c_long as _,
+    }
+}
+
+/// # Safety
+///
+/// Called by the VFS on a file whose `private_data` was set by
+/// `fops_open<T>`.
+unsafe extern "C" fn fops_ioctl<T: MiscDevice>(
+    raw_file: *mut bindings::file,
+    cmd: core::ffi::c_uint,
+    arg: core::ffi::c_ulong,
+) -> core::ffi::c_long {
+    // SAFETY: `private_data` was set by `fops_open` and outlives this call.
+    let device = unsafe { <T::Ptr as ForeignOwnable>::borrow((*raw_file).private_data) };
+    // SAFETY: The file is valid for the duration of this call.
+    let file = unsafe { File::from_ptr(raw_file) };
+    match T::ioctl(device, file, cmd, arg as usize) {
+        Ok(ret) => ret as _,
+        Err(err) => err.to_errno() as _,
+    }
+}
+
+/// A group of misc devices sharing one implementation, distinguishable in
+/// `open` via the minor number the misc core assigns each entry.
+///
+/// All devices register on construction; a failure part-way deregisters
+/// the already-registered entries in reverse before returning, so a group
+/// either exists fully or not at all. Drop deregisters all, also in
+/// reverse.
+pub struct MiscDeviceGroup<T: MiscDevice> {
+    entries: Vec<Pin<Box<MiscDeviceRegistration<T>>>>,
+}
+
+impl<T: MiscDevice> MiscDeviceGroup<T> {
+    /// Registers one device per entry of `names`, all sharing the group's
+    /// fops and `RegData`-producing closure.
+    pub fn register(
+        names: &'static [&'static CStr],
+        mut data: impl FnMut() -> Result<T::RegData>,
+    ) -> Result<Self> {
+        let mut entries = Vec::new();
+        entries.try_reserve(names.len()).map_err(|_| ENOMEM)?;
+        for name in names {
+            let opts = MiscDeviceOptions { name };
+            match MiscDeviceRegistration::<T>::register_with_data(opts, data()?) {
+                Ok(reg) => entries.push(reg),
+                Err(err) => {
+                    // Unwind in reverse: dropping each registration
+                    // deregisters it, so clearing the vector back-to-front
+                    // rolls the partial registration back.
+                    while entries.pop().is_some() {}
+                    return Err(err);
+                }
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Returns the number of registered devices.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the group is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T: MiscDevice> Drop for MiscDeviceGroup<T> {
+    fn drop(&mut self) {
+        // Deregister in reverse registration order.
+        while self.entries.pop().is_some() {}
+    }
+}
@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Memory management.
+//!
+//! C header: [`include/linux/mm.h`](srctree/include/linux/mm.h)
+
+pub mod virt;
+
+use crate::{
+    bindings,
+    task::Task,
+    types::{ARef, AlwaysRefCounted, Opaque},
+};
+use core::ptr::NonNull;
+
+/// A `struct mm_struct` reference held via `mmgrab`.
+///
+/// An `mmgrab` reference keeps the `mm_struct` allocation alive but does
+/// not keep the address space (page tables, VMAs) from being torn down;
+/// see [`MmWithUser`] for the stronger `mmget` form.
+#[repr(transparent)]
+pub struct Mm(Opaque<bindings::mm_struct>);
+
+// SAFETY: The mm core allows these to be handled from any thread.
+unsafe impl Send for Mm {}
+// SAFETY: See above.
+unsafe impl Sync for Mm {}
+
+// SAFETY: `mmgrab`/`mmdrop` provide the always-refcounted contract.
+unsafe impl AlwaysRefCounted for Mm {
+    fn inc_ref(&self) {
+        // SAFETY: The refcount is non-zero for the borrow's duration.
+        unsafe { bindings::mmgrab(self.as_raw()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee a matching grab.
+        unsafe { bindings::mmdrop(obj.cast().as_ptr()) }
+    }
+}
+
+impl Mm {
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::mm_struct {
+        self.0.get()
+    }
+
+    /// Grabs the current task's mm.
+    pub fn mmgrab_current() -> Option<ARef<Mm>> {
+        // SAFETY: Reading `current->mm` is sound from process context, and
+        // the value is stable for the current task.
+        let mm = unsafe { (*bindings::get_current()).mm };
+        let mm = NonNull::new(mm)?;
+        // SAFETY: The current task's mm cannot be freed while we run.
+        unsafe { bindings::mmgrab(mm.as_ptr()) };
+        // SAFETY: We own the grab performed above.
+        Some(unsafe { ARef::from_raw(mm.cast()) })
+    }
+}
+
+/// A `struct mm_struct` reference held via `mmget`.
+///
+/// Unlike [`Mm`], an mm-users reference keeps the whole address space
+/// alive, so user memory can be accessed through it.
+#[repr(transparent)]
+pub struct MmWithUser(Mm);
+
+// SAFETY: `mmget`/`mmput` provide the always-refcounted contract.
+unsafe impl AlwaysRefCounted for MmWithUser {
+    fn inc_ref(&self) {
+        // SAFETY: The users count is non-zero for the borrow's duration.
+        unsafe { bindings::mmget(self.as_raw()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee a matching get.
+        unsafe { bindings::mmput(obj.cast().as_ptr()) }
+    }
+}
+
+impl MmWithUser {
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::mm_struct {
+        self.0.as_raw()
+    }
+
+    /// Wraps a raw pointer whose mm-users count the caller owns one unit
+    /// of.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold (and transfer) one `mmget` reference on
+    /// `ptr`.
+    pub(crate) unsafe fn from_raw_owned(ptr: NonNull<bindings::mm_struct>) -> ARef<MmWithUser> {
+        // SAFETY: Per the function contract.
+        unsafe { ARef::from_raw(ptr.cast()) }
+    }
+
+    /// Reads up to `buf.len()` bytes from this address space at `addr`.
+    ///
+    /// Returns the number of bytes transferred, mirroring
+    /// `access_remote_vm` faithfully: a short count is partial success
+    /// at an unmapped hole, data rather than an error, and callers that
+    /// need more loop. The only manufactured errno is `EFAULT` when
+    /// zero bytes transfer, so `Ok(0)` never masquerades as progress.
+    ///
+    /// May fault and takes the mmap lock for reading internally; it can
+    /// sleep, so never call it under a spinlock.
+    pub fn access_remote(&self, addr: usize, buf: &mut [u8]) -> crate::error::Result<usize> {
+        // SAFETY: The mm is valid per the type invariant and the buffer
+        // bounds are passed through.
+        let n = unsafe {
+            bindings::access_remote_vm(
+                self.as_raw(),
+                addr as _,
+                buf.as_mut_ptr().cast(),
+                buf.len() as _,
+                0,
+            )
+        };
+        if n <= 0 && !buf.is_empty() {
+            return Err(crate::error::code::EFAULT);
+        }
+        Ok(n.max(0) as usize)
+    }
+
+    /// Writes `buf` into this address space at `addr`.
+    ///
+    /// Separate from [`access_remote`](Self::access_remote) rather than
+    /// a `write: bool` flag so the borrow direction matches the data
+    /// flow. Same return contract and locking/sleeping notes as the
+    /// read side.
+    pub fn access_remote_write(&self, addr: usize, buf: &[u8]) -> crate::error::Result<usize> {
+        // SAFETY: As in `access_remote`; FOLL_WRITE requests the write
+        // direction, and the C side does not modify the source buffer.
+        let n = unsafe {
+            bindings::access_remote_vm(
+                self.as_raw(),
+                addr as _,
+                buf.as_ptr() as *mut core::ffi::c_void,
+                buf.len() as _,
+                bindings::FOLL_WRITE,
+            )
+        };
+        if n <= 0 && !buf.is_empty() {
+            return Err(crate::error::code::EFAULT);
+        }
+        Ok(n.max(0) as usize)
+    }
+
+    /// Returns the mm of `task`, with the address space kept alive.
+    ///
+    /// Wraps `get_task_mm`: under `task_lock` it refuses kernel threads
+    /// and tasks whose mm is already gone (exiting tasks), and performs
+    /// `mmget` otherwise -- so, unlike [`Mm::mmgrab_current`], the
+    /// returned reference pins the address space itself, not just the
+    /// `mm_struct` allocation. Returns [`None`] for kernel threads and
+    /// exiting tasks.
+    pub fn from_task(task: &Task) -> Option<ARef<MmWithUser>> {
+        // SAFETY: `task` is valid for the duration of the call.
+        let mm = unsafe { bindings::get_task_mm(task.as_ptr()) };
+        let mm = NonNull::new(mm)?;
+        // SAFETY: `get_task_mm` returned with one mm-users reference that
+        // we now own.
+        Some(unsafe { Self::from_raw_owned(mm) })
+    }
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Virtual memory areas.
+//!
+//! C header: [`include/linux/mm.h`](srctree/include/linux/mm.h)
+
+use crate::{bindings, types::Opaque};
+
+/// A reference to a virtual memory area.
+///
+/// # Invariants
+///
+/// References are only created while the owning mm's mmap lock is held
+/// (read or write), which is what keeps the vma pointer stable.
+#[repr(transparent)]
+pub struct VmAreaRef(Opaque<bindings::vm_area_struct>);
+
+impl VmAreaRef {
+    /// Creates a reference from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid, and the mmap lock of the owning mm must be
+    /// held for the lifetime of the returned reference.
+    pub unsafe fn from_raw<'a>(ptr: *mut bindings::vm_area_struct) -> &'a Self {
+        // SAFETY: `VmAreaRef` is a transparent wrapper.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::vm_area_struct {
+        self.0.get()
+    }
+
+    /// Returns the start address of the area.
+    pub fn start(&self) -> usize {
+        // SAFETY: The vma is valid and stable per the type invariant.
+        unsafe { (*self.as_raw()).vm_start as usize }
+    }
+
+    /// Returns the end address (exclusive) of the area.
+    pub fn end(&self) -> usize {
+        // SAFETY: See `start`.
+        unsafe { (*self.as_raw()).vm_end as usize }
+    }
+
+    fn check_range(&self, start: usize, end: usize) {
+        debug_assert!(start <= end);
+        debug_assert!(start >= self.start() && end <= self.end());
+    }
+
+    /// Performs cache maintenance for `start..end` after PTEs in the
+    /// range changed.
+    ///
+    /// A no-op on architectures with coherent caches (x86 among them);
+    /// needed on VIVT/VIPT architectures after manual PTE surgery such
+    /// as binder's mapping insert/zap paths. Over-flushing is correct
+    /// but slow. Requires the mmap lock, which the existence of `self`
+    /// already encodes.
+    pub fn flush_cache_range(&self, start: usize, end: usize) {
+        self.check_range(start, end);
+        // SAFETY: The vma is valid per the type invariant and the range
+        // was checked against its bounds.
+        unsafe { bindings::flush_cache_range(self.as_raw(), start as _, end as _) };
+    }
+
+    /// Flushes the TLB for `start..end` after PTEs in the range changed.
+    ///
+    /// Most core-mm paths batch their own TLB flushing; this is for
+    /// drivers editing PTEs directly. Same locking and over-flushing
+    /// notes as [`flush_cache_range`](Self::flush_cache_range).
+    pub fn flush_tlb_range(&self, start: usize, end: usize) {
+        self.check_range(start, end);
+        // SAFETY: As in `flush_cache_range`.
+        unsafe { bindings::flush_tlb_range(self.as_raw(), start as _, end as _) };
+    }
+}
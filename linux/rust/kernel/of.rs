@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Devicetree and Open Firmware abstractions.
+//!
+//! C header: [`include/linux/of.h`](srctree/include/linux/of.h)
+
+use crate::{
+    bindings,
+    str::CStr,
+    types::{ARef, AlwaysRefCounted, Opaque},
+};
+use core::ptr::NonNull;
+
+/// A devicetree node.
+///
+/// # Invariants
+///
+/// References are only created to nodes with an elevated refcount held
+/// for the reference's duration.
+///
+/// The refcounting is a real constraint only under `CONFIG_OF_DYNAMIC`
+/// (overlays), where nodes genuinely come and go; elsewhere it is a
+/// formality. Callers must treat it as real either way.
+#[repr(transparent)]
+pub struct DeviceNode(Opaque<bindings::device_node>);
+
+// SAFETY: The OF core allows nodes to be used from any thread.
+unsafe impl Send for DeviceNode {}
+// SAFETY: See above.
+unsafe impl Sync for DeviceNode {}
+
+// SAFETY: `of_node_get`/`of_node_put` provide the refcounting contract.
+unsafe impl AlwaysRefCounted for DeviceNode {
+    fn inc_ref(&self) {
+        // SAFETY: The node is valid per the type invariant.
+        unsafe { bindings::of_node_get(self.as_raw()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee a matching get.
+        unsafe { bindings::of_node_put(obj.cast().as_ptr()) }
+    }
+}
+
+impl DeviceNode {
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::device_node {
+        self.0.get()
+    }
+
+    /// Adopts a raw node pointer whose refcount the caller owns one unit
+    /// of; returns [`None`] for null.
+    pub(crate) unsafe fn from_raw_owned(ptr: *mut bindings::device_node) -> Option<ARef<Self>> {
+        let ptr = NonNull::new(ptr)?;
+        // SAFETY: Per the caller's ownership transfer.
+        Some(unsafe { ARef::from_raw(ptr.cast()) })
+    }
+
+    /// Resolves the `index`-th phandle in the property `name`.
+    ///
+    /// Returns [`None`] when the property is absent or the index is out
+    /// of range.
+    pub fn parse_phandle(&self, name: &CStr, index: u32) -> Option<ARef<DeviceNode>> {
+        // SAFETY: The node is valid and `of_parse_phandle` returns an
+        // already-elevated refcount, which `from_raw_owned` adopts.
+        unsafe {
+            Self::from_raw_owned(bindings::of_parse_phandle(
+                self.as_raw(),
+                name.as_char_ptr(),
+                index as _,
+            ))
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel page allocation and management.
+
+use crate::{
+    bindings,
+    error::{code::*, Result},
+    PAGE_SIZE,
+};
+
+/// A set of physical pages.
+///
+/// `Pages` holds a reference to a set of pages of order `ORDER`, and has
+/// ownership of them.
+pub struct Pages<const ORDER: u32> {
+    pub(crate) pages: *mut bindings::page,
+}
+
+// SAFETY: The pages are owned and access helpers map/unmap around each use.
+unsafe impl<const ORDER: u32> Send for Pages<ORDER> {}
+// SAFETY: See above.
+unsafe impl<const ORDER: u32> Sync for Pages<ORDER> {}
+
+impl<const ORDER: u32> Pages<ORDER> {
+    /// Allocates a new set of contiguous pages.
+    pub fn new() -> Result<Self> {
+        // SAFETY: No requirements beyond a valid gfp mask and order.
+        let pages = unsafe {
+            bindings::alloc_pages(bindings::GFP_KERNEL | bindings::__GFP_ZERO, ORDER)
+        };
+        if pages.is_null() {
+            return Err(ENOMEM);
+        }
+        Ok(Self { pages })
+    }
+
+    /// Runs `f` with the page mapped into the kernel address space.
+    fn with_mapped<T>(&self, f: impl FnOnce(*mut u8) -> T) -> T {
+        // SAFETY: The page is owned and live.
+        let ptr = unsafe { bindings::kmap_local_page(self.pages) };
+        let ret = f(ptr.cast());
+        // SAFETY: `ptr` came from `kmap_local_page` just above.
+        unsafe { bindings::kunmap_local(ptr) };
+        ret
+    }
+
+    /// Copies `data` into the page at `offset`.
+    pub fn write(&self, data: &[u8], offset: usize) -> Result {
+        let end = offset.checked_add(data.len()).ok_or(EINVAL)?;
+        if end > (PAGE_SIZE << ORDER as usize) {
+            return Err(EINVAL);
+        }
+        self.with_mapped(|ptr| {
+            // SAFETY: The bounds were checked above and the mapping is
+            // valid for the page size.
+            unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), ptr.add(offset), data.len()) };
+        });
+        Ok(())
+    }
+
+    /// Copies from the page at `offset` into `out`.
+    pub fn read(&self, out: &mut [u8], offset: usize) -> Result {
+        let end = offset.checked_add(out.len()).ok_or(EINVAL)?;
+        if end > (PAGE_SIZE << ORDER as usize) {
+            return Err(EINVAL);
+        }
+        self.with_mapped(|ptr| {
+            // SAFETY: See `write`.
+            unsafe { core::ptr::copy_nonoverlapping(ptr.add(offset), out.as_mut_ptr(), out.len()) };
+        });
+        Ok(())
+    }
+
+    /// Zeroes `len` bytes of the page starting at `offset`.
+    pub fn fill_zero(&self, offset: usize, len: usize) -> Result {
+        let end = offset.checked_add(len).ok_or(EINVAL)?;
+        if end > (PAGE_SIZE << ORDER as usize) {
+            return Err(EINVAL);
+        }
+        self.with_mapped(|ptr| {
+            // SAFETY: The bounds were checked above.
+            unsafe { core::ptr::write_bytes(ptr.add(offset), 0, len) };
+        });
+        Ok(())
+    }
+
+    /// Fills part of the page directly from an [`IovIter`]
+    /// (`copy_page_from_iter`), avoiding a kernel bounce buffer.
+    pub fn copy_from_iter(
+        &self,
+        iter: &mut crate::user_ptr::IovIter<'_>,
+        offset: usize,
+        len: usize,
+    ) -> Result {
+        let end = offset.checked_add(len).ok_or(EINVAL)?;
+        if end > (PAGE_SIZE << ORDER as usize) {
+            return Err(EINVAL);
+        }
+        // SAFETY: The page is owned and the bounds were checked; the iter
+        // is a valid import per `IovIter`'s invariant.
+        let copied = unsafe {
+            bindings::copy_page_from_iter(self.pages, offset as _, len as _, iter.as_raw())
+        };
+        if copied != len as _ {
+            return Err(EFAULT);
+        }
+        Ok(())
+    }
+}
+
+impl<const ORDER: u32> Drop for Pages<ORDER> {
+    fn drop(&mut self) {
+        // SAFETY: The pages were allocated in `new` and are unaliased.
+        unsafe { bindings::__free_pages(self.pages, ORDER) };
+    }
+}
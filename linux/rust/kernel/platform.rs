@@ -0,0 +1,274 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Platform devices and drivers.
+//!
+//! C header: [`include/linux/platform_device.h`](srctree/include/linux/platform_device.h)
+
+use crate::{
+    bindings,
+    clk::Clk,
+    device,
+    error::{from_err_ptr, to_result, Result},
+    str::CStr,
+    types::{ForeignOwnable, Opaque},
+};
+use alloc::boxed::Box;
+use core::{marker::PhantomData, pin::Pin, ptr};
+
+/// A platform device.
+///
+/// # Invariants
+///
+/// The pointer wrapped by a `&Device` is valid for the lifetime of the
+/// reference; callbacks receive it from the platform core for their own
+/// duration.
+#[repr(transparent)]
+pub struct Device(Opaque<bindings::platform_device>);
+
+impl Device {
+    /// Creates a reference to a platform device from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the
+    /// lifetime of the returned reference.
+    pub unsafe fn from_raw<'a>(ptr: *mut bindings::platform_device) -> &'a Self {
+        // SAFETY: `Device` is a transparent wrapper.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_raw(&self) -> *mut bindings::platform_device {
+        self.0.get()
+    }
+
+    /// Returns the generic device embedded in this platform device.
+    pub fn device(&self) -> &device::Device {
+        // SAFETY: `dev` is embedded in a live `platform_device` and shares
+        // its lifetime.
+        unsafe { device::Device::from_raw(ptr::addr_of_mut!((*self.as_raw()).dev)) }
+    }
+
+    /// Returns how many interrupt lines this device declares.
+    ///
+    /// `Result`, not a bare count, because the C call reports errors as
+    /// negative returns -- notably `EPROBE_DEFER` while the irqchip is
+    /// not ready, which probe paths should propagate so the driver core
+    /// retries. `Ok(0)` is a legitimate answer (the device declares no
+    /// interrupts), not a failure.
+    ///
+    /// The iteration idiom:
+    ///
+    /// ```ignore
+    /// for i in 0..pdev.irq_count()? {
+    ///     let irq = pdev.irq_by_index(i)?;
+    ///     // ...
+    /// }
+    /// ```
+    pub fn irq_count(&self) -> Result<u32> {
+        // SAFETY: The device is valid for the duration of the call.
+        let ret = unsafe { bindings::platform_irq_count(self.as_raw()) };
+        if ret < 0 {
+            return Err(crate::error::Error::from_errno(ret));
+        }
+        Ok(ret as u32)
+    }
+
+    /// Returns the devicetree node describing this device, if any.
+    ///
+    /// [`None`] for devices not described by devicetree (e.g. ACPI or
+    /// board-file platforms). The returned reference is counted via
+    /// `of_node_get`/`of_node_put`.
+    pub fn of_node(&self) -> Option<crate::types::ARef<crate::of::DeviceNode>> {
+        // SAFETY: The device is valid for the duration of this call;
+        // `dev_of_node` just reads the pointer.
+        let node = unsafe { bindings::dev_of_node(self.device().as_raw()) };
+        let node = core::ptr::NonNull::new(node)?;
+        // SAFETY: The device holds its of_node for its lifetime, so the
+        // node is valid here; take our own reference before wrapping.
+        unsafe {
+            bindings::of_node_get(node.as_ptr());
+            crate::of::DeviceNode::from_raw_owned(node.as_ptr())
+        }
+    }
+
+    /// Acquires the regulator supplying `id` for this device.
+    ///
+    /// Uses `devm_regulator_get`, so release belongs to devres and the
+    /// returned [`Regulator`](crate::regulator::Regulator) must not
+    /// outlive the device. Missing optional supplies come back as dummy
+    /// regulators; see [`regulator_get_optional`](Self::regulator_get_optional).
+    pub fn regulator_get(&self, id: &CStr) -> Result<crate::regulator::Regulator> {
+        // SAFETY: The device is valid for the duration of the call and
+        // `id` is a valid C string.
+        let reg = from_err_ptr(unsafe {
+            bindings::devm_regulator_get(self.device().as_raw(), id.as_char_ptr())
+        })?;
+        // INVARIANT: `devm_regulator_get` returned a non-error pointer.
+        Ok(crate::regulator::Regulator(reg))
+    }
+
+    /// Like [`regulator_get`](Self::regulator_get), but reports an absent
+    /// supply as `Ok(None)` instead of a dummy regulator.
+    pub fn regulator_get_optional(&self, id: &CStr) -> Result<Option<crate::regulator::Regulator>> {
+        // SAFETY: As above.
+        match from_err_ptr(unsafe {
+            bindings::devm_regulator_get_optional(self.device().as_raw(), id.as_char_ptr())
+        }) {
+            Ok(reg) => Ok(Some(crate::regulator::Regulator(reg))),
+            Err(e) if e == crate::error::code::ENODEV => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Acquires the exclusive reset control named `id` (or the sole
+    /// unnamed one) for this device.
+    ///
+    /// Exclusive by default, deliberately: assert/deassert on a *shared*
+    /// control have counted semantics (deassert only guarantees
+    /// "deasserted at least once"; asserts are refused while other
+    /// sharers hold it) that surprise drivers expecting direct line
+    /// control. Use [`reset_control_get_shared`](Self::reset_control_get_shared)
+    /// only when that model is wanted. Devres-managed, like the clk and
+    /// regulator helpers.
+    pub fn reset_control_get(&self, id: Option<&CStr>) -> Result<crate::reset::ResetControl> {
+        let id_ptr = id.map_or(ptr::null(), CStr::as_char_ptr);
+        // SAFETY: The device is valid for the duration of the call and
+        // `id_ptr` is null or a valid C string.
+        let rc = from_err_ptr(unsafe {
+            bindings::devm_reset_control_get_exclusive(self.device().as_raw(), id_ptr)
+        })?;
+        // INVARIANT: A non-error pointer was returned.
+        Ok(crate::reset::ResetControl(rc))
+    }
+
+    /// Shared-counted variant of [`reset_control_get`](Self::reset_control_get);
+    /// see there for the semantic difference.
+    pub fn reset_control_get_shared(&self, id: Option<&CStr>) -> Result<crate::reset::ResetControl> {
+        let id_ptr = id.map_or(ptr::null(), CStr::as_char_ptr);
+        // SAFETY: As above.
+        let rc = from_err_ptr(unsafe {
+            bindings::devm_reset_control_get_shared(self.device().as_raw(), id_ptr)
+        })?;
+        // INVARIANT: A non-error pointer was returned.
+        Ok(crate::reset::ResetControl(rc))
+    }
+
+    /// Acquires the clock named `name` (or the sole unnamed clock) for this
+    /// device.
+    ///
+    /// Uses `devm_clk_get`, so the clock is released automatically when the
+    /// device is unbound; the returned [`Clk`] must not be used past that
+    /// point.
+    pub fn clk_get(&self, name: Option<&CStr>) -> Result<Clk> {
+        let name_ptr = name.map_or(ptr::null(), CStr::as_char_ptr);
+        // SAFETY: `self.device()` is a valid device for the duration of the
+        // call, and `name_ptr` is null or a valid C string.
+        let clk =
+            from_err_ptr(unsafe { bindings::devm_clk_get(self.device().as_raw(), name_ptr) })?;
+        // INVARIANT: `devm_clk_get` returned a valid non-error pointer.
+        Ok(Clk(clk))
+    }
+}
+
+/// A platform driver.
+pub trait Driver {
+    /// Per-device data created in [`probe`](Self::probe) and dropped after
+    /// [`remove`](Self::remove).
+    type Data: ForeignOwnable + Send + Sync;
+
+    /// Called when a matching device is bound to the driver.
+    fn probe(dev: &Device) -> Result<Self::Data>;
+
+    /// Called when the device is unbound.
+    ///
+    /// Receives the device alongside the data so cleanup can talk to the
+    /// hardware (read registers, gate clocks explicitly, ...). It runs
+    /// before devres teardown, so devm-acquired resources (clocks,
+    /// regulators, resets) are still live here. The default delegates to
+    /// [`remove_data`](Self::remove_data) for drivers written against the
+    /// old data-only signature.
+    fn remove(dev: &Device, data: &Self::Data) {
+        let _ = dev;
+        Self::remove_data(data);
+    }
+
+    /// Device-less cleanup hook kept for drivers that predate
+    /// [`remove`](Self::remove) receiving the device.
+    fn remove_data(_data: &Self::Data) {}
+}
+
+/// A registration of a platform driver.
+///
+/// # Invariants
+///
+/// `pdrv` is registered with the platform core until dropped.
+pub struct Registration<T: Driver> {
+    pdrv: Pin<Box<bindings::platform_driver>>,
+    _p: PhantomData<T>,
+}
+
+impl<T: Driver> Registration<T> {
+    /// Registers the driver under `name`.
+    pub fn register(name: &'static CStr) -> Result<Self> {
+        let mut pdrv = Pin::from(Box::try_new(
+            // SAFETY: All zeros is valid for `struct platform_driver`.
+            unsafe { core::mem::zeroed::<bindings::platform_driver>() },
+        )?);
+        // SAFETY: The struct is pinned and outlives the registration.
+        let raw = unsafe { pdrv.as_mut().get_unchecked_mut() };
+        raw.probe = Some(probe_callback::<T>);
+        raw.remove = Some(remove_callback::<T>);
+        raw.driver.name = name.as_char_ptr();
+        // SAFETY: `raw` stays valid until `Drop` unregisters it.
+        to_result(unsafe { bindings::__platform_driver_register(raw, core::ptr::null_mut()) })?;
+        Ok(Self {
+            pdrv,
+            _p: PhantomData,
+        })
+    }
+}
+
+impl<T: Driver> Drop for Registration<T> {
+    fn drop(&mut self) {
+        // SAFETY: The driver was registered in `register`.
+        unsafe {
+            bindings::platform_driver_unregister(
+                self.pdrv.as_mut().get_unchecked_mut(),
+            )
+        };
+    }
+}
+
+/// # Safety
+///
+/// Called by the platform core with a valid device being bound.
+unsafe extern "C" fn probe_callback<T: Driver>(
+    pdev: *mut bindings::platform_device,
+) -> core::ffi::c_int {
+    // SAFETY: The device is valid for the duration of the callback, per
+    // the driver-core contract.
+    let dev = unsafe { Device::from_raw(pdev) };
+    match T::probe(dev) {
+        Ok(data) => {
+            // SAFETY: drvdata is ours between probe and remove.
+            unsafe { bindings::platform_set_drvdata(pdev, data.into_foreign()) };
+            0
+        }
+        Err(e) => e.to_errno(),
+    }
+}
+
+/// # Safety
+///
+/// Called by the platform core when the device probed above is unbound.
+unsafe extern "C" fn remove_callback<T: Driver>(pdev: *mut bindings::platform_device) {
+    // SAFETY: The device is valid for the duration of the callback; the
+    // same contract probe relied on (reconstructed the same way).
+    let dev = unsafe { Device::from_raw(pdev) };
+    // SAFETY: drvdata was set in `probe_callback` and is consumed exactly
+    // once, here.
+    let data = unsafe { T::Data::from_foreign(bindings::platform_get_drvdata(pdev)) };
+    T::remove(dev, &data);
+    drop(data);
+}
@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! The `kernel` prelude.
+//!
+//! These are the most common items used by Rust code in the kernel,
+//! intended to be imported by all Rust code, for convenience.
+//!
+//! # Usage
+//!
+//! ```
+//! use kernel::prelude::*;
+//! ```
+
+pub use alloc::{boxed::Box, vec::Vec};
+
+pub use crate::error::{code::*, Error, Result};
+
+pub use crate::{c_str, container_of, pr_err, pr_info, pr_warn};
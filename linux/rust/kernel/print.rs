@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Printing facilities.
+//!
+//! C header: [`include/linux/printk.h`](srctree/include/linux/printk.h)
+
+use core::fmt;
+
+#[doc(hidden)]
+pub fn call_printk(level: &'static [u8], args: fmt::Arguments<'_>) {
+    // `_printk` does not climb the stack, so the format pointer may point
+    // at this frame.
+    struct Writer([u8; 512], usize);
+    impl fmt::Write for Writer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let remaining = self.0.len() - 1 - self.1;
+            let n = s.len().min(remaining);
+            self.0[self.1..self.1 + n].copy_from_slice(&s.as_bytes()[..n]);
+            self.1 += n;
+            Ok(())
+        }
+    }
+    let mut w = Writer([0; 512], 0);
+    let _ = fmt::Write::write_fmt(&mut w, args);
+    // SAFETY: The format string and buffer are NUL-terminated C strings.
+    unsafe {
+        crate::bindings::_printk(
+            b"%s%s\0".as_ptr().cast(),
+            level.as_ptr(),
+            w.0.as_ptr(),
+        );
+    }
+}
+
+/// Prints an error-level message.
+#[macro_export]
+macro_rules! pr_err {
+    ($($arg:tt)*) => {
+        $crate::print::call_printk(b"\x013\0", core::format_args!($($arg)*))
+    };
+}
+
+/// Prints a warning-level message.
+#[macro_export]
+macro_rules! pr_warn {
+    ($($arg:tt)*) => {
+        $crate::print::call_printk(b"\x014\0", core::format_args!($($arg)*))
+    };
+}
+
+/// Prints an info-level message.
+#[macro_export]
+macro_rules! pr_info {
+    ($($arg:tt)*) => {
+        $crate::print::call_printk(b"\x016\0", core::format_args!($($arg)*))
+    };
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Voltage and current regulators.
+//!
+//! C header: [`include/linux/regulator/consumer.h`](srctree/include/linux/regulator/consumer.h)
+
+use crate::{bindings, error::to_result, error::Result};
+
+/// A regulator obtained through the device-managed interface.
+///
+/// # Invariants
+///
+/// The inner pointer was returned by a successful `devm_regulator_get`
+/// and stays valid while the owning device is bound.
+///
+/// Like [`Clk`](crate::clk::Clk), there is no `Drop`: release is owned by
+/// devres, so a [`Regulator`] must not outlive its device.
+///
+/// Note that `devm_regulator_get` never returns NULL -- absent optional
+/// supplies come back as dummy regulators that accept every operation.
+/// Use [`Device::regulator_get_optional`](crate::platform::Device::regulator_get_optional)
+/// when the driver must distinguish a real supply from a dummy.
+pub struct Regulator(pub(crate) *mut bindings::regulator);
+
+// SAFETY: The regulator API is internally locked.
+unsafe impl Send for Regulator {}
+
+impl Regulator {
+    /// Enables the regulator.
+    ///
+    /// Enables and disables are counted by the regulator core and must
+    /// balance.
+    pub fn enable(&self) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::regulator_enable(self.0) })
+    }
+
+    /// Disables the regulator.
+    ///
+    /// Can fail (e.g. if the supply refuses while shared); must balance a
+    /// previous [`enable`](Self::enable).
+    pub fn disable(&self) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::regulator_disable(self.0) })
+    }
+
+    /// Requests an output voltage in the inclusive `[min_uv, max_uv]`
+    /// window.
+    pub fn set_voltage(&self, min_uv: i32, max_uv: i32) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::regulator_set_voltage(self.0, min_uv, max_uv) })
+    }
+
+    /// Returns the current output voltage in microvolts.
+    pub fn get_voltage(&self) -> Result<i32> {
+        // SAFETY: The pointer is valid per the type invariant.
+        let uv = unsafe { bindings::regulator_get_voltage(self.0) };
+        if uv < 0 {
+            return Err(crate::error::Error::from_errno(uv));
+        }
+        Ok(uv)
+    }
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Reset controllers.
+//!
+//! C header: [`include/linux/reset.h`](srctree/include/linux/reset.h)
+
+use crate::{bindings, error::to_result, error::Result};
+
+/// A reset control obtained through the device-managed interface.
+///
+/// # Invariants
+///
+/// The inner pointer was returned by a successful
+/// `devm_reset_control_get_exclusive` (or the shared variant) and stays
+/// valid while the owning device is bound.
+///
+/// As with [`Clk`](crate::clk::Clk) and
+/// [`Regulator`](crate::regulator::Regulator), release is owned by
+/// devres, so there is no `Drop` and the control must not outlive its
+/// device.
+pub struct ResetControl(pub(crate) *mut bindings::reset_control);
+
+// SAFETY: The reset framework is internally locked.
+unsafe impl Send for ResetControl {}
+
+impl ResetControl {
+    /// Asserts the reset line.
+    pub fn assert(&self) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::reset_control_assert(self.0) })
+    }
+
+    /// Deasserts the reset line.
+    pub fn deassert(&self) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::reset_control_deassert(self.0) })
+    }
+
+    /// Pulses the reset line (assert, delay, deassert), where the
+    /// controller supports it.
+    pub fn reset(&self) -> Result {
+        // SAFETY: The pointer is valid per the type invariant.
+        to_result(unsafe { bindings::reset_control_reset(self.0) })
+    }
+}
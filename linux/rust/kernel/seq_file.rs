@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Seq file bindings.
+//!
+//! C header: [`include/linux/seq_file.h`](srctree/include/linux/seq_file.h)
+
+use crate::{bindings, types::Opaque};
+
+/// A helper for implementing special files, where the complete contents can
+/// be generated on each access.
+#[repr(transparent)]
+pub struct SeqFile(Opaque<bindings::seq_file>);
+
+impl SeqFile {
+    /// Creates a new [`SeqFile`] from a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid for the duration of the
+    /// returned borrow, with exclusive access to the seq file.
+    pub unsafe fn from_raw<'a>(ptr: *mut bindings::seq_file) -> &'a mut SeqFile {
+        // SAFETY: `SeqFile` is a transparent wrapper.
+        unsafe { &mut *ptr.cast() }
+    }
+
+    /// Used by the [`seq_print`] macro.
+    #[doc(hidden)]
+    pub fn call_printf(&mut self, args: core::fmt::Arguments<'_>) {
+        use core::fmt::Write;
+        struct Adapter<'a>(&'a mut SeqFile);
+        impl Write for Adapter<'_> {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                // SAFETY: The seq file is valid per the `from_raw`
+                // contract; `seq_write` copies the buffer immediately.
+                unsafe {
+                    bindings::seq_write(
+                        self.0 .0.get(),
+                        s.as_ptr().cast(),
+                        s.len() as _,
+                    )
+                };
+                Ok(())
+            }
+        }
+        let _ = Adapter(self).write_fmt(args);
+    }
+
+    /// Returns whether the internal buffer overflowed, in which case the
+    /// seq machinery will retry with a bigger buffer.
+    pub fn has_overflowed(&self) -> bool {
+        // SAFETY: The seq file is valid per the `from_raw` contract.
+        unsafe {
+            let m = self.0.get();
+            (*m).count == (*m).size
+        }
+    }
+}
+
+/// Writes to a [`SeqFile`] with the ordinary `format!` syntax.
+#[macro_export]
+macro_rules! seq_print {
+    ($m:expr, $($arg:tt)+) => {
+        $m.call_printf(core::format_args!($($arg)+))
+    };
+}
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! String representations.
+
+use crate::error::{code::*, Error, Result};
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+/// A string that is guaranteed to have exactly one `NUL` byte, which is at
+/// the end.
+///
+/// Used for interoperability with kernel APIs that take C strings.
+#[repr(transparent)]
+pub struct CStr([u8]);
+
+impl CStr {
+    /// Returns the length of this string excluding `NUL`.
+    pub const fn len(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    /// Returns `true` if the string only includes `NUL`.
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Wraps a raw C string pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a valid pointer to a `NUL`-terminated C string, and it
+    /// must remain valid and unmodified for the lifetime `'a`.
+    pub unsafe fn from_char_ptr<'a>(ptr: *const core::ffi::c_char) -> &'a Self {
+        // SAFETY: The safety precondition guarantees `ptr` is a valid
+        // NUL-terminated string.
+        let len = unsafe { crate::bindings::strlen(ptr) } + 1;
+        // SAFETY: The string is valid for `len` bytes and NUL-terminated.
+        unsafe {
+            Self::from_bytes_with_nul_unchecked(core::slice::from_raw_parts(ptr.cast(), len as _))
+        }
+    }
+
+    /// Creates a [`CStr`] from a `[u8]`, checking for the `NUL` terminator.
+    pub const fn from_bytes_with_nul(bytes: &[u8]) -> Result<&Self, Error> {
+        let mut i = 0;
+        while i + 1 < bytes.len() {
+            if bytes[i] == 0 {
+                return Err(EINVAL);
+            }
+            i += 1;
+        }
+        if bytes.is_empty() || bytes[bytes.len() - 1] != 0 {
+            return Err(EINVAL);
+        }
+        // SAFETY: Just checked.
+        Ok(unsafe { Self::from_bytes_with_nul_unchecked(bytes) })
+    }
+
+    /// Creates a [`CStr`] from a `[u8]` without performing any sanity
+    /// checks.
+    ///
+    /// # Safety
+    ///
+    /// `bytes` must end with a `NUL` byte and contain no other `NUL` bytes.
+    pub const unsafe fn from_bytes_with_nul_unchecked(bytes: &[u8]) -> &Self {
+        // SAFETY: `CStr` is a transparent wrapper around `[u8]`.
+        unsafe { core::mem::transmute(bytes) }
+    }
+
+    /// Returns a C pointer to the string.
+    pub const fn as_char_ptr(&self) -> *const core::ffi::c_char {
+        self.0.as_ptr().cast()
+    }
+
+    /// Returns the string without the `NUL` terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[..self.len()]
+    }
+
+    /// Returns the string including the `NUL` terminator.
+    pub const fn as_bytes_with_nul(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Deref for CStr {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Creates a new [`CStr`] from a string literal, appending the `NUL` byte.
+#[macro_export]
+macro_rules! c_str {
+    ($str:expr) => {{
+        const S: &str = concat!($str, "\0");
+        const C: &$crate::str::CStr =
+            match $crate::str::CStr::from_bytes_with_nul(S.as_bytes()) {
+                Ok(v) => v,
+                Err(_) => panic!("string contains interior NUL"),
+            };
+        C
+    }};
+}
+
+/// An owned string that is guaranteed to have exactly one `NUL` byte, at
+/// the end.
+pub struct CString {
+    buf: Vec<u8>,
+}
+
+impl CString {
+    /// Creates a [`CString`] from a byte slice, appending the `NUL`
+    /// terminator, with fallible allocation.
+    ///
+    /// Fails with `EINVAL` if `bytes` contains an interior `NUL`.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.contains(&0) {
+            return Err(EINVAL);
+        }
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(bytes.len() + 1).map_err(|_| ENOMEM)?;
+        buf.extend_from_slice(bytes);
+        buf.push(0);
+        Ok(Self { buf })
+    }
+}
+
+impl core::ops::Deref for CString {
+    type Target = CStr;
+
+    fn deref(&self) -> &CStr {
+        // SAFETY: The buffer ends with `NUL` and has no interior `NUL`
+        // by construction.
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.buf) }
+    }
+}
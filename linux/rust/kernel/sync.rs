@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Synchronisation primitives.
+//!
+//! This module contains the kernel APIs related to synchronisation that have
+//! been ported or wrapped for usage by Rust code in the kernel.
+
+pub mod arc;
+pub mod condvar;
+pub mod lock;
+pub mod locked_by;
+pub mod poll;
+
+pub use arc::{Arc, ArcBorrow, Ref, UniqueArc, UniqueRef, Weak};
+pub use condvar::CondVar;
+pub use lock::{mutex::Mutex, spinlock::SpinLock, spinlock::SpinLockIrqBackend, Guard};
+pub use locked_by::LockedBy;
+pub use poll::{PollCondVar, PollTable};
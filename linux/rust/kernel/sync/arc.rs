@@ -0,0 +1,677 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A reference-counted pointer.
+//!
+//! This module implements a way for users to create reference-counted
+//! objects and references to them that can be shared, that is, when the
+//! reference count reaches zero, the underlying object is automatically
+//! freed and the memory is deallocated once the last [`Weak`] reference is
+//! also gone.
+//!
+//! It is different from the standard library's [`Arc`] in a few ways:
+//! 1. It is backed by the kernel's `refcount_t` type.
+//! 2. It does not support unsized types for now.
+//! 3. It saturates the reference count instead of aborting when it goes over
+//!    a threshold.
+//!
+//! [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+
+use crate::{
+    alloc::{AllocError, Flags},
+    bindings,
+    types::{ForeignOwnable, Opaque},
+};
+use core::{
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ops::Deref,
+    pin::Pin,
+    ptr::NonNull,
+};
+
+/// A reference-counted pointer to an instance of `T`.
+///
+/// The reference count is incremented when new instances of [`Arc`] are
+/// created, and decremented when they are dropped. When the count reaches
+/// zero, the underlying `T` is dropped; the allocation itself is freed once
+/// the weak count also reaches zero (see [`Weak`]).
+///
+/// # Invariants
+///
+/// The reference counts on an instance of [`Arc`] are always non-zero for as
+/// long as the [`Arc`] exists.
+pub struct Arc<T: ?Sized> {
+    ptr: NonNull<ArcInner<T>>,
+    _p: PhantomData<ArcInner<T>>,
+}
+
+/// The old name of [`Arc`], kept while `drivers/android` migrates.
+pub type Ref<T> = Arc<T>;
+
+#[repr(C)]
+struct ArcInner<T: ?Sized> {
+    /// The number of `Arc` handles plus one while any `Arc` exists.
+    ///
+    /// Uses the kernel `refcount_t` semantics: increments are unordered,
+    /// the final decrement provides release ordering, and observing the
+    /// final decrement provides acquire ordering, so the thread that frees
+    /// the object sees all prior accesses through other handles.
+    refcount: Opaque<bindings::refcount_t>,
+    /// The number of `Weak` handles, plus one held collectively by all
+    /// strong handles (as in the standard library `Arc`).
+    ///
+    /// The allocation is freed when this count reaches zero; `T` itself is
+    /// dropped earlier, when the strong count reaches zero. The same
+    /// `refcount_t` ordering rules as above apply, which is what makes it
+    /// sound for the freeing thread to deallocate: it observes the final
+    /// weak decrement with acquire ordering.
+    weakcount: Opaque<bindings::refcount_t>,
+    data: T,
+}
+
+// SAFETY: It is safe to send `Arc<T>` to another thread when the underlying
+// `T` is `Sync` because it effectively means sharing `&T` (which is safe
+// because `T` is `Sync`); additionally, it needs `T` to be `Send` because
+// any thread that has an `Arc<T>` may ultimately access `T` directly.
+unsafe impl<T: ?Sized + Sync + Send> Send for Arc<T> {}
+
+// SAFETY: It is safe to send `&Arc<T>` to another thread when the underlying
+// `T` is `Sync` for the same reason as above. `T` needs to be `Send` as well
+// because a thread can clone an `&Arc<T>` into an `Arc<T>`, which may lead
+// to `T` being accessed by the same reasoning as above.
+unsafe impl<T: ?Sized + Sync + Send> Sync for Arc<T> {}
+
+impl<T> Arc<T> {
+    /// Constructs a new reference counted instance of `T`, failing if the
+    /// allocation fails.
+    ///
+    /// Allocates with `GFP_KERNEL` and therefore may sleep; see
+    /// [`Arc::try_new_flags`] for restricted contexts.
+    pub fn try_new(contents: T) -> Result<Self, AllocError> {
+        Self::try_new_flags(contents, crate::alloc::flags::GFP_KERNEL)
+    }
+
+    /// Constructs a new reference counted instance of `T` using the given
+    /// allocation flags.
+    ///
+    /// Paths that allocate while holding spinlocks or while in reclaim
+    /// must not use the plain [`Arc::try_new`]: its implicit `GFP_KERNEL`
+    /// may sleep or recurse into the filesystem. Pass `GFP_ATOMIC` or
+    /// `GFP_NOFS` here instead, as appropriate for the calling context.
+    pub fn try_new_flags(contents: T, flags: Flags) -> Result<Self, AllocError> {
+        let value = ArcInner {
+            // INVARIANT: The refcount is initialised to a non-zero value.
+            refcount: Opaque::new(new_refcount()),
+            // INVARIANT: All strong handles together hold one weak
+            // increment.
+            weakcount: Opaque::new(new_refcount()),
+            data: contents,
+        };
+
+        let layout = core::alloc::Layout::new::<ArcInner<T>>();
+        // SAFETY: The layout has non-zero size because of the refcounts.
+        let ptr = unsafe { bindings::krealloc(core::ptr::null(), layout.size(), flags.as_raw()) }
+            as *mut ArcInner<T>;
+        let Some(inner) = NonNull::new(ptr) else {
+            return Err(AllocError);
+        };
+        // SAFETY: The allocation is big enough for `ArcInner<T>` and
+        // freshly owned by us.
+        unsafe { inner.as_ptr().write(value) };
+
+        // SAFETY: We just created `inner` with a reference count of 1,
+        // which is owned by the new `Arc` object.
+        Ok(unsafe { Self::from_inner(inner) })
+    }
+
+    /// Deconstructs this [`Arc`] into a raw pointer.
+    ///
+    /// The caller becomes responsible for the strong reference previously
+    /// owned by this handle; it can be re-assembled with
+    /// [`Arc::from_raw`].
+    pub fn into_raw(self) -> *const T {
+        let me = ManuallyDrop::new(self);
+        // SAFETY: The pointer is valid per the type invariant.
+        unsafe { core::ptr::addr_of!((*me.ptr.as_ptr()).data) }
+    }
+
+    /// Recreates an [`Arc`] instance previously deconstructed via
+    /// [`Arc::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by a previous call to
+    /// [`Arc::into_raw`], and must not be used afterwards.
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        let offset = core::mem::offset_of!(ArcInner<T>, data);
+        // SAFETY: By the safety requirement, `ptr` points at the `data`
+        // field of a live `ArcInner<T>`.
+        let inner = unsafe { (ptr.cast::<u8>().sub(offset) as *mut ArcInner<T>) };
+        // SAFETY: By the safety requirements we own one strong reference.
+        unsafe { Self::from_inner(NonNull::new_unchecked(inner)) }
+    }
+}
+
+impl<T> Arc<[T]> {
+    /// Builds a refcounted slice of exactly `len` items taken from
+    /// `iter`, in a single fallible allocation.
+    ///
+    /// `len` is explicit rather than trusted from `ExactSizeIterator`:
+    /// the layout maths feed an unsafe allocation, so a lying iterator
+    /// must be handled, not assumed away. An iterator that runs short
+    /// drops the initialised prefix, frees the allocation, and fails
+    /// with `EINVAL`; surplus items are simply not consumed.
+    ///
+    /// This replaces the collect-into-`Vec`-then-convert pattern, which
+    /// holds both the vector and the refcounted copy at its peak --
+    /// binder's page-array construction being the motivating case.
+    pub fn from_iter_fallible<I: Iterator<Item = T>>(
+        mut iter: I,
+        len: usize,
+    ) -> crate::error::Result<Self> {
+        let header = core::alloc::Layout::new::<ArcInner<[T; 0]>>();
+        let array = core::alloc::Layout::array::<T>(len)
+            .map_err(|_| crate::error::code::EINVAL)?;
+        let (layout, data_offset) = header
+            .extend(array)
+            .map_err(|_| crate::error::code::EINVAL)?;
+        let layout = layout.pad_to_align();
+
+        // SAFETY: The layout has non-zero size because of the header.
+        let raw = unsafe {
+            bindings::krealloc(
+                core::ptr::null(),
+                layout.size(),
+                crate::alloc::flags::GFP_KERNEL.as_raw(),
+            )
+        } as *mut u8;
+        if raw.is_null() {
+            return Err(crate::error::code::ENOMEM);
+        }
+
+        // SAFETY: The allocation covers the header; field offsets match
+        // `ArcInner<[T; 0]>`, whose header layout is shared by every
+        // `ArcInner<[T]>`.
+        unsafe {
+            let hdr = raw as *mut ArcInner<[T; 0]>;
+            core::ptr::addr_of_mut!((*hdr).refcount).write(Opaque::new(new_refcount()));
+            core::ptr::addr_of_mut!((*hdr).weakcount).write(Opaque::new(new_refcount()));
+        }
+
+        let data = unsafe { raw.add(data_offset) } as *mut T;
+        for i in 0..len {
+            match iter.next() {
+                // SAFETY: `data` has room for `len` items per the layout.
+                Some(item) => unsafe { data.add(i).write(item) },
+                None => {
+                    // The iterator lied about its length; unwind.
+                    // SAFETY: Exactly `i` items were initialised above.
+                    unsafe {
+                        core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(data, i));
+                        bindings::kfree(raw.cast());
+                    }
+                    return Err(crate::error::code::EINVAL);
+                }
+            }
+        }
+
+        // Build the fat pointer: slice metadata over the inner struct.
+        let fat =
+            core::ptr::slice_from_raw_parts_mut(raw as *mut T, len) as *mut ArcInner<[T]>;
+        // SAFETY: The allocation is fully initialised with refcounts of
+        // one, owned by the new `Arc`.
+        Ok(unsafe { Self::from_inner(NonNull::new_unchecked(fat)) })
+    }
+}
+
+impl<T: ?Sized> Arc<T> {
+    /// Constructs an `Arc` from an inner pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `inner` points to a valid location and
+    /// transfers ownership of one strong reference to the new [`Arc`].
+    unsafe fn from_inner(inner: NonNull<ArcInner<T>>) -> Self {
+        // INVARIANT: By the safety requirements, the invariants hold.
+        Arc {
+            ptr: inner,
+            _p: PhantomData,
+        }
+    }
+
+    /// Determines if two reference-counted pointers point to the same
+    /// underlying instance of `T`.
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        core::ptr::eq(a.ptr.as_ptr(), b.ptr.as_ptr())
+    }
+
+    /// Returns a raw pointer to the data, usable as an identity token.
+    ///
+    /// The pointer must not be dereferenced without an outstanding strong
+    /// reference, and comparisons are only meaningful while something
+    /// pins the identity: once the allocation is freed, a later
+    /// allocation may reuse the address (ABA). Store it for logging or
+    /// keyed lookups, not as a substitute for a reference.
+    pub fn as_ptr(this: &Self) -> *const T {
+        // SAFETY: Field projection on a valid allocation; nothing is
+        // dereferenced.
+        unsafe { core::ptr::addr_of!((*this.ptr.as_ptr()).data) }
+    }
+
+    /// Creates a [`Weak`] reference to this object, incrementing the weak
+    /// count.
+    ///
+    /// A weak reference does not keep `T` alive: once every [`Arc`] to the
+    /// object is dropped, `T` is dropped, and later calls to
+    /// [`Weak::upgrade`] return [`None`]. The allocation itself stays until
+    /// the last [`Weak`] is also dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kernel::sync::Arc;
+    ///
+    /// let strong = Arc::try_new(42)?;
+    /// let weak = Arc::downgrade(&strong);
+    ///
+    /// // The object is still alive, so upgrading succeeds.
+    /// assert_eq!(*weak.upgrade().unwrap(), 42);
+    ///
+    /// drop(strong);
+    ///
+    /// // All strong references are gone; upgrading now fails.
+    /// assert!(weak.upgrade().is_none());
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        // SAFETY: The pointer is valid per the type invariant. The increment
+        // needs no ordering (this thread holds a strong reference, so the
+        // allocation is live); `refcount_inc` provides that and saturates on
+        // overflow instead of wrapping.
+        unsafe { bindings::refcount_inc(this.weakcount().get()) };
+        // INVARIANT: We incremented the weak count above, and the new
+        // `Weak` takes over that increment.
+        Weak {
+            ptr: this.ptr,
+            _p: PhantomData,
+        }
+    }
+
+    fn refcount(&self) -> &Opaque<bindings::refcount_t> {
+        // SAFETY: The pointer is valid per the type invariant.
+        unsafe { &self.ptr.as_ref().refcount }
+    }
+
+    fn weakcount(&self) -> &Opaque<bindings::refcount_t> {
+        // SAFETY: The pointer is valid per the type invariant.
+        unsafe { &self.ptr.as_ref().weakcount }
+    }
+}
+
+impl<T: ?Sized> Deref for Arc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: By the type invariant, there is necessarily a reference to
+        // the object, so it is safe to dereference it.
+        unsafe { &self.ptr.as_ref().data }
+    }
+}
+
+impl<T: ?Sized> Clone for Arc<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: By the type invariant, there is necessarily a reference to
+        // the object, so it is safe to increment the refcount.
+        unsafe { bindings::refcount_inc(self.refcount().get()) };
+
+        // SAFETY: We just incremented the refcount. This increment is now
+        // owned by the new `Arc`.
+        unsafe { Self::from_inner(self.ptr) }
+    }
+}
+
+impl<T: ?Sized> Drop for Arc<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariant, there is necessarily a reference to
+        // the object. `refcount_dec_and_test` provides release ordering on
+        // the decrement and acquire ordering when it observes zero, so the
+        // thread dropping `T` below sees all accesses made through other
+        // handles.
+        let is_zero = unsafe { bindings::refcount_dec_and_test(self.refcount().get()) };
+        if is_zero {
+            let inner = self.ptr.as_ptr();
+            // SAFETY: The strong count reached zero, so nothing can reach
+            // `data` any more (`Weak::upgrade` refuses to resurrect it).
+            unsafe { core::ptr::drop_in_place(core::ptr::addr_of_mut!((*inner).data)) };
+
+            // Release the weak reference held collectively by the strong
+            // handles; this frees the allocation if no `Weak` is left.
+            // SAFETY: As above; the allocation is still valid because the
+            // weak count is non-zero until this call.
+            let weak_zero =
+                unsafe { bindings::refcount_dec_and_test(Opaque::raw_get(core::ptr::addr_of!((*inner).weakcount))) };
+            if weak_zero {
+                // SAFETY: Both counts are zero, so nobody can reach the
+                // allocation; it came from `krealloc` in `try_new_flags`.
+                unsafe { bindings::kfree(inner.cast()) };
+            }
+        }
+    }
+}
+
+/// A weak reference to an instance of `T` managed by an [`Arc`].
+///
+/// A `Weak` does not keep the object alive; it only keeps the allocation
+/// alive so that [`Weak::upgrade`] can safely inspect the strong count. This
+/// is what binder-style cyclic structures need: the cycle member holds a
+/// `Weak` and upgrades on use, so the cycle does not leak.
+///
+/// # Invariants
+///
+/// The weak count on the underlying allocation is non-zero for as long as
+/// the `Weak` exists.
+pub struct Weak<T: ?Sized> {
+    ptr: NonNull<ArcInner<T>>,
+    _p: PhantomData<ArcInner<T>>,
+}
+
+// SAFETY: Same justification as for `Arc`: a `Weak` can be upgraded to an
+// `Arc` on any thread it is sent to.
+unsafe impl<T: ?Sized + Sync + Send> Send for Weak<T> {}
+// SAFETY: See above.
+unsafe impl<T: ?Sized + Sync + Send> Sync for Weak<T> {}
+
+impl<T: ?Sized> Weak<T> {
+    /// Attempts to promote this weak reference to a strong one.
+    ///
+    /// Returns [`None`] if the object has already been dropped, that is, if
+    /// the strong count has reached zero. The increment is performed with
+    /// `refcount_inc_not_zero`, which refuses to move the count from zero to
+    /// one and provides acquire ordering on success, so the new strong
+    /// reference observes the fully-constructed object.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        // SAFETY: By the type invariant the allocation is alive (the weak
+        // count is non-zero), so the strong refcount may be inspected.
+        let got = unsafe { bindings::refcount_inc_not_zero(self.refcount().get()) };
+        if got {
+            // SAFETY: We successfully incremented the strong count above,
+            // and the new `Arc` takes over that increment.
+            Some(unsafe { Arc::from_inner(self.ptr) })
+        } else {
+            None
+        }
+    }
+
+    fn refcount(&self) -> &Opaque<bindings::refcount_t> {
+        // SAFETY: The allocation is valid per the type invariant.
+        unsafe { &self.ptr.as_ref().refcount }
+    }
+
+    /// Determines whether two weak references point at the same
+    /// allocation.
+    ///
+    /// Well-defined even when the strong count is zero: weak references
+    /// keep the allocation (though not the value) alive, so the
+    /// identities being compared cannot have been reused.
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        core::ptr::eq(a.ptr.as_ptr(), b.ptr.as_ptr())
+    }
+}
+
+impl<T: ?Sized> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: The allocation is valid per the type invariant, and the
+        // unordered increment is sufficient for the same reason as in
+        // `Arc::downgrade`.
+        unsafe { bindings::refcount_inc(self.ptr.as_ref().weakcount.get()) };
+        // INVARIANT: The new handle owns the increment performed above.
+        Weak {
+            ptr: self.ptr,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for Weak<T> {
+    fn drop(&mut self) {
+        // SAFETY: The allocation is valid per the type invariant. See
+        // `Arc::drop` for the ordering argument; the acquire on the final
+        // decrement is what allows this thread to free the memory.
+        let is_zero =
+            unsafe { bindings::refcount_dec_and_test(self.ptr.as_ref().weakcount.get()) };
+        if is_zero {
+            // SAFETY: The strong count dropped to zero first (it holds a
+            // collective weak increment), so `data` was already dropped in
+            // `Arc::drop`; only the allocation remains, which came from
+            // `krealloc`.
+            unsafe { bindings::kfree(self.ptr.as_ptr().cast()) };
+        }
+    }
+}
+
+/// A borrowed reference to an [`Arc`] instance.
+///
+/// For cases when one doesn't ever need to increment the refcount on the
+/// allocation, it is advantageous to use `ArcBorrow` instead of `&Arc`
+/// because the latter results in a double-indirection.
+#[derive(Clone, Copy)]
+pub struct ArcBorrow<'a, T: ?Sized> {
+    inner: NonNull<ArcInner<T>>,
+    _p: PhantomData<&'a ()>,
+}
+
+impl<T: ?Sized> From<ArcBorrow<'_, T>> for Arc<T> {
+    fn from(b: ArcBorrow<'_, T>) -> Self {
+        // SAFETY: The existence of the borrow guarantees a non-zero strong
+        // count for its duration, so the count can be incremented.
+        unsafe { bindings::refcount_inc((*b.inner.as_ptr()).refcount.get()) };
+        // SAFETY: The new `Arc` takes over the increment performed above.
+        unsafe { Self::from_inner(b.inner) }
+    }
+}
+
+impl<T: ?Sized> Deref for ArcBorrow<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: By the type invariant, the underlying object is still
+        // alive with a non-zero reference count.
+        unsafe { &self.inner.as_ref().data }
+    }
+}
+
+/// A refcounted object that is known to have a refcount of 1.
+///
+/// It is mutable and can be converted to an [`Arc`] so that it can be
+/// shared.
+pub struct UniqueArc<T: ?Sized> {
+    inner: Arc<T>,
+}
+
+/// The old name of [`UniqueArc`], kept while `drivers/android` migrates.
+pub type UniqueRef<T> = UniqueArc<T>;
+
+impl<T> UniqueArc<T> {
+    /// Tries to allocate a new [`UniqueArc`] instance.
+    pub fn try_new(value: T) -> Result<Self, AllocError> {
+        Self::try_new_flags(value, crate::alloc::flags::GFP_KERNEL)
+    }
+
+    /// Tries to allocate a new [`UniqueArc`] instance with the given
+    /// allocation flags; see [`Arc::try_new_flags`] for when this is
+    /// required.
+    pub fn try_new_flags(value: T, flags: Flags) -> Result<Self, AllocError> {
+        Ok(Self {
+            // INVARIANT: The newly-created object has a refcount of 1.
+            inner: Arc::try_new_flags(value, flags)?,
+        })
+    }
+}
+
+impl<T> UniqueArc<T> {
+    /// Constructs the value in place with a pinned initialiser,
+    /// returning a pinned unique handle.
+    ///
+    /// This is what lets a type carrying an embedded lock or list links
+    /// be built without the construct-then-fixup dance: the initialiser
+    /// runs at the value's final address (the `Arc` allocation never
+    /// moves), and the handle is still unique, so further setup can
+    /// happen before the flip to a shared [`Arc`].
+    ///
+    /// A failing initialiser frees the allocation without running `T`'s
+    /// drop, per the [`PinInit`](crate::init::PinInit) contract.
+    pub fn pin_init<E>(init: impl crate::init::PinInit<T, E>) -> Result<Pin<Self>, E>
+    where
+        E: From<AllocError>,
+    {
+        let layout = core::alloc::Layout::new::<ArcInner<T>>();
+        // SAFETY: The layout has non-zero size because of the refcounts.
+        let raw = unsafe {
+            bindings::krealloc(
+                core::ptr::null(),
+                layout.size(),
+                crate::alloc::flags::GFP_KERNEL.as_raw(),
+            )
+        } as *mut ArcInner<T>;
+        let Some(inner) = NonNull::new(raw) else {
+            return Err(AllocError.into());
+        };
+        // SAFETY: The allocation covers the header fields.
+        unsafe {
+            core::ptr::addr_of_mut!((*raw).refcount).write(Opaque::new(new_refcount()));
+            core::ptr::addr_of_mut!((*raw).weakcount).write(Opaque::new(new_refcount()));
+        }
+        let slot = unsafe { core::ptr::addr_of_mut!((*raw).data) };
+        // SAFETY: `slot` is valid for writes and will not move: the
+        // allocation is the value's final address.
+        if let Err(e) = unsafe { init.__pinned_init(slot) } {
+            // SAFETY: Per the `PinInit` contract nothing in the slot
+            // needs dropping; only the shell remains.
+            unsafe { bindings::kfree(raw.cast()) };
+            return Err(e);
+        }
+        // SAFETY: Fully initialised with both counts at one, owned by
+        // the unique handle.
+        let unique = Self {
+            inner: unsafe { Arc::from_inner(inner) },
+        };
+        Ok(unique.into())
+    }
+}
+
+impl<T: ?Sized> From<UniqueArc<T>> for Arc<T> {
+    fn from(item: UniqueArc<T>) -> Self {
+        item.inner
+    }
+}
+
+impl<T> From<UniqueArc<T>> for Pin<UniqueArc<T>> {
+    fn from(obj: UniqueArc<T>) -> Self {
+        // SAFETY: It is not possible to move/replace `T` inside a
+        // `Pin<UniqueArc<T>>` (unless `T` is `Unpin`), so it is ok to
+        // convert it to `Pin<UniqueArc<T>>`.
+        unsafe { Pin::new_unchecked(obj) }
+    }
+}
+
+impl<T: ?Sized> Deref for UniqueArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.deref()
+    }
+}
+
+impl<T: ?Sized> core::ops::DerefMut for UniqueArc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: The refcount is 1, so it is ok to provide mutable access.
+        unsafe { &mut self.inner.ptr.as_mut().data }
+    }
+}
+
+// SAFETY: An `Arc<T>` is a pointer-sized value that remains valid while the
+// foreign owner holds it, because the `into_foreign` caller's strong
+// reference is not dropped.
+unsafe impl<T: 'static + Send + Sync> ForeignOwnable for Arc<T> {
+    type Borrowed<'a> = ArcBorrow<'a, T>;
+    type BorrowedMut<'a> = ArcBorrow<'a, T>;
+
+    fn into_foreign(self) -> *mut core::ffi::c_void {
+        ManuallyDrop::new(self).ptr.as_ptr().cast()
+    }
+
+    unsafe fn from_foreign(ptr: *mut core::ffi::c_void) -> Self {
+        // SAFETY: By the safety requirements, `ptr` came from
+        // `into_foreign`, whose strong reference we now take back over.
+        unsafe { Self::from_inner(NonNull::new_unchecked(ptr.cast())) }
+    }
+
+    unsafe fn borrow<'a>(ptr: *mut core::ffi::c_void) -> ArcBorrow<'a, T> {
+        // SAFETY: By the safety requirements, the object stays alive for
+        // the duration of 'a.
+        ArcBorrow {
+            inner: unsafe { NonNull::new_unchecked(ptr.cast()) },
+            _p: PhantomData,
+        }
+    }
+
+    unsafe fn borrow_mut<'a>(ptr: *mut core::ffi::c_void) -> ArcBorrow<'a, T> {
+        // SAFETY: As for `borrow` above; `Arc` only ever hands out shared
+        // access, so the mutable borrow is the same shape.
+        unsafe { Self::borrow(ptr) }
+    }
+}
+
+/// Returns a `refcount_t` initialised to 1.
+fn new_refcount() -> bindings::refcount_t {
+    bindings::refcount_t {
+        refs: bindings::atomic_t { counter: 1 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrade_and_upgrade() {
+        let strong = Arc::try_new(42u32).unwrap();
+        let weak = Arc::downgrade(&strong);
+
+        // Object alive: upgrading succeeds and sees the value.
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(*upgraded, 42);
+        drop(upgraded);
+
+        // Last strong ref gone: the value is dead, upgrade refuses.
+        drop(strong);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn identity_tokens() {
+        let a = Arc::try_new(1u32).unwrap();
+        let b = a.clone();
+        let c = Arc::try_new(1u32).unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(Arc::as_ptr(&a), Arc::as_ptr(&b));
+        assert_ne!(Arc::as_ptr(&a), Arc::as_ptr(&c));
+
+        let wa = Arc::downgrade(&a);
+        let wb = Arc::downgrade(&b);
+        let wc = Arc::downgrade(&c);
+        assert!(Weak::ptr_eq(&wa, &wb));
+        assert!(!Weak::ptr_eq(&wa, &wc));
+    }
+
+    #[test]
+    fn slice_from_iter() {
+        let arc = Arc::<[u32]>::from_iter_fallible(0..8u32, 8).unwrap();
+        assert_eq!(&arc[..], &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // A short iterator must fail cleanly, not UB.
+        assert!(Arc::<[u32]>::from_iter_fallible(0..3u32, 8).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A condition variable.
+//!
+//! This module allows Rust code to use the kernel's `wait_queue_head` as a
+//! condition variable.
+
+use super::lock::{Backend, Guard};
+use crate::{bindings, str::CStr, time::Ktime, types::Opaque};
+use core::marker::PhantomPinned;
+
+/// A conditional variable.
+///
+/// Allows wait/wake-up functionality. A thread can wait on it while holding
+/// some lock's guard; waiting releases the lock and reacquires it before
+/// returning.
+pub struct CondVar {
+    pub(crate) wait_queue_head: Opaque<bindings::wait_queue_head>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `CondVar` only uses a `wait_queue_head`, which is safe to use on
+// any thread.
+unsafe impl Send for CondVar {}
+// SAFETY: See above.
+unsafe impl Sync for CondVar {}
+
+impl CondVar {
+    /// Constructs an uninitialised condition variable.
+    ///
+    /// # Safety
+    ///
+    /// [`CondVar::init_once`] must be called before first use, and the
+    /// value must not move afterwards.
+    pub unsafe fn new_uninit() -> Self {
+        Self {
+            wait_queue_head: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the C wait queue.
+    ///
+    /// # Safety
+    ///
+    /// Must be called exactly once before first use.
+    pub unsafe fn init_once(&self, _name: &'static CStr) {
+        // SAFETY: Per the function contract.
+        unsafe { bindings::init_waitqueue_head(self.wait_queue_head.get()) };
+    }
+
+    fn wait_internal<T: ?Sized, B: Backend>(&self, state: u32, guard: &mut Guard<'_, T, B>) {
+        let mut wait = Opaque::<bindings::wait_queue_entry>::uninit();
+
+        // SAFETY: `wait` points to valid memory.
+        unsafe { bindings::init_wait(wait.get()) };
+
+        // SAFETY: Both `wait` and `wait_queue_head` point to valid memory.
+        unsafe {
+            bindings::prepare_to_wait_exclusive(self.wait_queue_head.get(), wait.get(), state as _)
+        };
+
+        // SAFETY: The guard is dropped (releasing the lock) around the
+        // schedule and reconstructed afterwards; no access to the data
+        // happens in between.
+        unsafe { B::unlock(guard.lock.state.get(), &guard.state) };
+        // SAFETY: No locks are held at this point.
+        unsafe { bindings::schedule() };
+        guard.state = unsafe { B::lock(guard.lock.state.get()) };
+
+        // SAFETY: Both `wait` and `wait_queue_head` point to valid memory.
+        unsafe { bindings::finish_wait(self.wait_queue_head.get(), wait.get()) };
+    }
+
+    /// Releases the lock and waits for a notification in uninterruptible
+    /// sleep.
+    pub fn wait<T: ?Sized, B: Backend>(&self, guard: &mut Guard<'_, T, B>) {
+        self.wait_internal(bindings::TASK_UNINTERRUPTIBLE, guard);
+    }
+
+    /// Releases the lock and waits for a notification in interruptible
+    /// sleep.
+    ///
+    /// Returns whether there is a signal pending.
+    #[must_use = "wait_interruptible returns if a signal is pending, so the caller must check the return value"]
+    pub fn wait_interruptible<T: ?Sized, B: Backend>(&self, guard: &mut Guard<'_, T, B>) -> bool {
+        self.wait_internal(bindings::TASK_INTERRUPTIBLE, guard);
+        // SAFETY: No requirements on calling context.
+        unsafe { bindings::signal_pending(bindings::get_current()) != 0 }
+    }
+
+    fn wait_timeout_internal<T: ?Sized, B: Backend>(
+        &self,
+        state: u32,
+        guard: &mut Guard<'_, T, B>,
+        timeout: Ktime,
+    ) -> bool {
+        let mut wait = Opaque::<bindings::wait_queue_entry>::uninit();
+
+        // SAFETY: `wait` points to valid memory.
+        unsafe { bindings::init_wait(wait.get()) };
+
+        // SAFETY: Both `wait` and `wait_queue_head` point to valid memory.
+        unsafe {
+            bindings::prepare_to_wait_exclusive(self.wait_queue_head.get(), wait.get(), state as _)
+        };
+
+        // SAFETY: The lock is released around the sleep and reacquired
+        // below; no access to the protected data happens in between.
+        unsafe { B::unlock(guard.lock.state.get(), &guard.state) };
+        // A zero return means the full timeout elapsed without a wake-up.
+        // SAFETY: No locks are held at this point.
+        let remaining = unsafe {
+            bindings::schedule_timeout(bindings::nsecs_to_jiffies(timeout.to_ns() as _) as _)
+        };
+        guard.state = unsafe { B::lock(guard.lock.state.get()) };
+
+        // SAFETY: Both `wait` and `wait_queue_head` point to valid memory.
+        unsafe { bindings::finish_wait(self.wait_queue_head.get(), wait.get()) };
+
+        remaining == 0
+    }
+
+    /// Releases the lock and waits for a notification in uninterruptible
+    /// sleep, giving up after `timeout`.
+    ///
+    /// Returns the reacquired guard and `true` if the wait timed out
+    /// without a notification. Signals are not delivered during the wait;
+    /// use [`CondVar::wait_interruptible_timeout`] where the caller must
+    /// remain killable.
+    #[must_use = "the timeout result must be checked to distinguish wake-up from expiry"]
+    pub fn wait_timeout<'a, T: ?Sized, B: Backend>(
+        &self,
+        mut guard: Guard<'a, T, B>,
+        timeout: Ktime,
+    ) -> (Guard<'a, T, B>, bool) {
+        let timed_out =
+            self.wait_timeout_internal(bindings::TASK_UNINTERRUPTIBLE, &mut guard, timeout);
+        (guard, timed_out)
+    }
+
+    /// Like [`CondVar::wait_timeout`], but in interruptible sleep.
+    ///
+    /// Returns `Err(ERESTARTSYS)` if a signal arrived first; the lock is
+    /// reacquired (and the guard dropped) before returning in that case.
+    pub fn wait_interruptible_timeout<'a, T: ?Sized, B: Backend>(
+        &self,
+        mut guard: Guard<'a, T, B>,
+        timeout: Ktime,
+    ) -> crate::error::Result<(Guard<'a, T, B>, bool)> {
+        let timed_out =
+            self.wait_timeout_internal(bindings::TASK_INTERRUPTIBLE, &mut guard, timeout);
+        // SAFETY: No requirements on calling context.
+        if unsafe { bindings::signal_pending(bindings::get_current()) != 0 } {
+            return Err(crate::error::code::ERESTARTSYS);
+        }
+        Ok((guard, timed_out))
+    }
+
+    fn notify(&self, count: i32) {
+        // SAFETY: `wait_queue_head` points to valid memory.
+        unsafe {
+            bindings::__wake_up(
+                self.wait_queue_head.get(),
+                bindings::TASK_NORMAL,
+                count,
+                core::ptr::null_mut(),
+            )
+        };
+    }
+
+    /// Wakes a single waiter up, if any.
+    pub fn notify_one(&self) {
+        self.notify(1);
+    }
+
+    /// Wakes all waiters up, if any.
+    pub fn notify_all(&self) {
+        self.notify(0);
+    }
+}
@@ -0,0 +1,311 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Generic kernel lock and guard.
+//!
+//! It contains a generic Rust lock and guard that allow for different
+//! backends (e.g., mutexes, spinlocks, raw spinlocks) to be provided with
+//! minimal effort.
+
+use crate::{str::CStr, types::Opaque};
+use core::{cell::UnsafeCell, marker::PhantomData, marker::PhantomPinned, pin::Pin};
+
+pub mod mutex;
+pub mod spinlock;
+
+/// The "backend" of a lock.
+///
+/// It is the actual implementation of the lock, without the need to repeat
+/// patterns used in all locks.
+///
+/// # Safety
+///
+/// - Implementers must ensure that only one thread/CPU may access the
+///   protected data once the lock is owned, that is, between calls to
+///   [`lock`](Backend::lock) and [`unlock`](Backend::unlock).
+/// - Implementers must also ensure that [`relock`](Backend::relock) uses
+///   the same locking method as the original lock operation.
+pub unsafe trait Backend {
+    /// The state required by the lock.
+    type State;
+
+    /// The state required to be kept between [`lock`](Backend::lock) and
+    /// [`unlock`](Backend::unlock).
+    type GuardState;
+
+    /// Initialises the lock.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for write for the duration of the call, while
+    /// `name` and `key` must remain valid for read indefinitely.
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut crate::bindings::lock_class_key,
+    );
+
+    /// Acquires the lock, making the caller its owner.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`Backend::init`] has been previously
+    /// called.
+    #[must_use]
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState;
+
+    /// Releases the lock, giving up its ownership.
+    ///
+    /// # Safety
+    ///
+    /// It must only be called by the current owner of the lock.
+    unsafe fn unlock(ptr: *mut Self::State, guard_state: &Self::GuardState);
+
+    /// Debug-asserts that the lock is held.
+    ///
+    /// Backends without usable introspection keep this default no-op, so
+    /// the assertion can never fire spuriously; backends that can check
+    /// override it. There is deliberately no `is_held() -> bool` to
+    /// branch on -- like `lockdep_assert_held`, this is assertion-only.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that [`Backend::init`] has been previously
+    /// called.
+    unsafe fn debug_assert_is_held(_ptr: *mut Self::State) {}
+}
+
+/// A mutual exclusion primitive.
+///
+/// Exposes one of the kernel locking primitives. Which one is exposed
+/// depends on the lock [`Backend`] specified as the generic parameter `B`.
+pub struct Lock<T: ?Sized, B: Backend> {
+    /// The kernel lock object.
+    state: Opaque<B::State>,
+
+    /// Some locks are known to be self-referential (e.g. mutexes), while
+    /// others are architecture-specific. This is always `!Unpin` to be on
+    /// the safe side.
+    _pin: PhantomPinned,
+
+    /// The data protected by the lock.
+    pub(crate) data: UnsafeCell<T>,
+}
+
+// SAFETY: `Lock` can be transferred across thread boundaries iff the data
+// it protects can.
+unsafe impl<T: ?Sized + Send, B: Backend> Send for Lock<T, B> {}
+
+// SAFETY: `Lock` serialises the interior mutability it provides.
+unsafe impl<T: ?Sized + Send, B: Backend> Sync for Lock<T, B> {}
+
+impl<T, B: Backend> Lock<T, B> {
+    /// Constructs a new lock initialiser.
+    ///
+    /// The returned lock must be pinned before use; initialisation of the
+    /// C state happens on first pin via [`Lock::init_once`] from the
+    /// `new_mutex!`/`new_spinlock!` helpers.
+    pub fn new(t: T, name: &'static CStr) -> impl FnOnce() -> Pin<alloc::boxed::Box<Self>> {
+        let this = Self {
+            state: Opaque::uninit(),
+            _pin: PhantomPinned,
+            data: UnsafeCell::new(t),
+        };
+        move || {
+            let boxed = Pin::from(alloc::boxed::Box::try_new(this).unwrap());
+            // SAFETY: `state` is pinned from here on and valid for write.
+            unsafe {
+                B::init(
+                    Opaque::raw_get(core::ptr::addr_of!(boxed.as_ref().get_ref().state)),
+                    name.as_char_ptr(),
+                    core::ptr::null_mut(),
+                )
+            };
+            boxed
+        }
+    }
+
+    /// Constructs a lock in place inside an enclosing structure.
+    ///
+    /// # Safety
+    ///
+    /// The returned value must not be moved after `init_once` is called on
+    /// it, and `init_once` must be called before first use.
+    pub unsafe fn new_uninit(t: T) -> Self {
+        Self {
+            state: Opaque::uninit(),
+            _pin: PhantomPinned,
+            data: UnsafeCell::new(t),
+        }
+    }
+
+    /// Initialises the C lock state.
+    ///
+    /// # Safety
+    ///
+    /// Must be called exactly once, before any call to [`Lock::lock`], and
+    /// the lock must not move afterwards.
+    pub unsafe fn init_once(&self, name: &'static CStr) {
+        // SAFETY: Per the function contract.
+        unsafe { B::init(self.state.get(), name.as_char_ptr(), core::ptr::null_mut()) };
+    }
+}
+
+impl<T: ?Sized, B: Backend> Lock<T, B> {
+    /// Acquires the lock and gives the caller access to the data protected
+    /// by it.
+    pub fn lock(&self) -> Guard<'_, T, B> {
+        // SAFETY: The constructors guarantee that `init` was called.
+        let state = unsafe { B::lock(self.state.get()) };
+        // SAFETY: The lock was just acquired.
+        unsafe { Guard::new(self, state) }
+    }
+}
+
+impl<T: ?Sized, B: Backend> Lock<T, B> {
+    /// Locks, runs `f` on the protected data, and unlocks.
+    ///
+    /// This buys scoping discipline, not new semantics: the guard's drop
+    /// is the unlock, so early returns inside `f` release correctly and
+    /// there is no named guard whose lifetime can be accidentally
+    /// extended. (The kernel builds with `panic=abort`, so unwinding is
+    /// not a consideration.) Intended for the one-and-two-statement
+    /// critical sections that otherwise spell out a guard binding.
+    pub fn with_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut guard = self.lock();
+        f(&mut guard)
+    }
+}
+
+impl<T: ?Sized, B: Backend> Lock<T, B> {
+    /// Debug-asserts that this lock is currently held.
+    ///
+    /// Use through the [`assert_held!`](crate::assert_held) macro, which
+    /// compiles to nothing outside `CONFIG_DEBUG_KERNEL`. Strength
+    /// depends on the backend: mutexes can check ownership where the C
+    /// debug config tracks it, spinlocks can only check "locked by
+    /// someone" (and nothing at all on !SMP, where `spin_is_locked` is
+    /// constant-false).
+    pub fn debug_assert_held(&self) {
+        // SAFETY: The constructors guarantee that `init` was called.
+        unsafe { B::debug_assert_is_held(self.state.get()) };
+    }
+}
+
+/// A lock guard.
+///
+/// Allows mutual exclusion primitives that implement the [`Backend`] trait
+/// to automatically unlock when a guard goes out of scope. It also provides
+/// a safe and convenient way to access the data protected by the lock.
+#[must_use = "the lock unlocks immediately when the guard is unused"]
+pub struct Guard<'a, T: ?Sized, B: Backend> {
+    pub(crate) lock: &'a Lock<T, B>,
+    pub(crate) state: B::GuardState,
+    _not_send: PhantomData<*mut ()>,
+}
+
+// SAFETY: `Guard` is sync when the data protected by the lock is also sync.
+unsafe impl<T: Sync + ?Sized, B: Backend> Sync for Guard<'_, T, B> {}
+
+impl<T: ?Sized, B: Backend> core::ops::Deref for Guard<'_, T, B> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The caller owns the lock, so it is safe to deref the
+        // protected data.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, B: Backend> core::ops::DerefMut for Guard<'_, T, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The caller owns the lock, so it is safe to modify the
+        // protected data.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized, B: Backend> Drop for Guard<'_, T, B> {
+    fn drop(&mut self) {
+        // SAFETY: The caller owns the lock, so it is safe to unlock it.
+        unsafe { B::unlock(self.lock.state.get(), &self.state) };
+    }
+}
+
+impl<'a, T: ?Sized, B: Backend> Guard<'a, T, B> {
+    /// Projects the guard to a sub-field of the protected data.
+    ///
+    /// Associated-fn calling convention (like `Arc::as_ptr`) since the
+    /// guard derefs to `T`. The closure runs while the lock is held and
+    /// the returned [`MappedGuard`] keeps it held, unlocking on drop
+    /// exactly as the original would have; lifetimes prevent the
+    /// reference escaping the projection. No `try_map` or re-mapping of
+    /// a `MappedGuard` yet -- they compose trivially when a caller
+    /// appears.
+    pub fn map<U: ?Sized, F>(this: Self, f: F) -> MappedGuard<'a, T, U, B>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let mut this = core::mem::ManuallyDrop::new(this);
+        // SAFETY: The lock is held, so projecting the protected data is
+        // as sound as DerefMut.
+        let value: *mut U = f(unsafe { &mut *this.lock.data.get() });
+        // SAFETY: `state` is moved out exactly once; the original guard
+        // is ManuallyDrop so there is exactly one unlock, owned by the
+        // mapped guard.
+        let state = unsafe { core::ptr::read(&this.state) };
+        MappedGuard {
+            lock_state: this.lock.state.get(),
+            state,
+            value,
+            _p: PhantomData,
+        }
+    }
+
+    /// Constructs a new immediately-unlocking guard.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that it owns the lock.
+    pub(crate) unsafe fn new(lock: &'a Lock<T, B>, state: B::GuardState) -> Self {
+        Self {
+            lock,
+            state,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// A guard projected to a sub-field of the protected data; see
+/// [`Guard::map`]. Releases the lock on drop.
+#[must_use = "the lock unlocks immediately when the guard is unused"]
+pub struct MappedGuard<'a, T: ?Sized, U: ?Sized, B: Backend> {
+    lock_state: *mut B::State,
+    state: B::GuardState,
+    value: *mut U,
+    _p: PhantomData<(&'a Lock<T, B>, &'a mut U)>,
+}
+
+impl<T: ?Sized, U: ?Sized, B: Backend> core::ops::Deref for MappedGuard<'_, T, U, B> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        // SAFETY: The lock is held and the projection came from the
+        // protected data.
+        unsafe { &*self.value }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized, B: Backend> core::ops::DerefMut for MappedGuard<'_, T, U, B> {
+    fn deref_mut(&mut self) -> &mut U {
+        // SAFETY: See `deref`; exclusivity comes from `&mut self`.
+        unsafe { &mut *self.value }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized, B: Backend> Drop for MappedGuard<'_, T, U, B> {
+    fn drop(&mut self) {
+        // SAFETY: This guard took over the original guard's unlock
+        // obligation in `Guard::map`.
+        unsafe { B::unlock(self.lock_state, &self.state) };
+    }
+}
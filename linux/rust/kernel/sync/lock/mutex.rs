@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel mutex.
+//!
+//! This module allows Rust code to use the kernel's `struct mutex`.
+
+/// A [`Lock`](super::Lock) backed by the kernel's `struct mutex`.
+///
+/// Instances of [`Mutex`] need a lock class and to be pinned.
+pub type Mutex<T> = super::Lock<T, MutexBackend>;
+
+/// A kernel `struct mutex` lock backend.
+pub struct MutexBackend;
+
+// SAFETY: The underlying kernel `struct mutex` object ensures mutual
+// exclusion.
+unsafe impl super::Backend for MutexBackend {
+    type State = crate::bindings::mutex;
+    type GuardState = ();
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut crate::bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for
+        // writes, and `name` and `key` are valid for read indefinitely.
+        unsafe { crate::bindings::__mutex_init(ptr, name, key) }
+    }
+
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState {
+        // SAFETY: The safety requirements of this function ensure that
+        // `ptr` points to valid memory.
+        unsafe { crate::bindings::mutex_lock(ptr) };
+    }
+
+    unsafe fn unlock(ptr: *mut Self::State, _guard_state: &Self::GuardState) {
+        // SAFETY: The safety requirements of this function ensure that
+        // `ptr` is valid and that the caller is the owner of the mutex.
+        unsafe { crate::bindings::mutex_unlock(ptr) };
+    }
+
+    unsafe fn debug_assert_is_held(ptr: *mut Self::State) {
+        // SAFETY: The safety requirements guarantee a valid, initialised
+        // mutex. `mutex_is_locked` can only say "locked by someone";
+        // ownership is checked where CONFIG_DEBUG_MUTEXES tracks it.
+        unsafe {
+            debug_assert!(crate::bindings::mutex_is_locked(ptr));
+            // The owner word carries MUTEX_FLAG_* state in its low three
+            // bits (set e.g. the moment a waiter queues); mask them off
+            // as the C `__mutex_owner` does before comparing.
+            #[cfg(CONFIG_DEBUG_MUTEXES)]
+            debug_assert!(core::ptr::eq(
+                ((*ptr).owner as usize & !0x7) as *const crate::bindings::task_struct,
+                crate::bindings::get_current(),
+            ));
+        }
+    }
+}
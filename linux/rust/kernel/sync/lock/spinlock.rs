@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel spinlock.
+//!
+//! This module allows Rust code to use the kernel's `spinlock_t`.
+
+/// A [`Lock`](super::Lock) backed by the kernel's `spinlock_t`.
+pub type SpinLock<T> = super::Lock<T, SpinLockBackend>;
+
+/// A kernel `spinlock_t` lock backend.
+pub struct SpinLockBackend;
+
+// SAFETY: The underlying kernel `spinlock_t` object ensures mutual
+// exclusion.
+unsafe impl super::Backend for SpinLockBackend {
+    type State = crate::bindings::spinlock_t;
+    type GuardState = ();
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut crate::bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements ensure that `ptr` is valid for
+        // writes, and `name` and `key` are valid for read indefinitely.
+        unsafe { crate::bindings::__spin_lock_init(ptr, name, key) }
+    }
+
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState {
+        // SAFETY: The safety requirements of this function ensure that
+        // `ptr` points to valid memory.
+        unsafe { crate::bindings::spin_lock(ptr) }
+    }
+
+    unsafe fn unlock(ptr: *mut Self::State, _guard_state: &Self::GuardState) {
+        // SAFETY: The safety requirements of this function ensure that
+        // `ptr` is valid and that the caller is the owner of the spinlock.
+        unsafe { crate::bindings::spin_unlock(ptr) }
+    }
+
+    unsafe fn debug_assert_is_held(ptr: *mut Self::State) {
+        // `spin_is_locked` is constant-false on !SMP, so asserting there
+        // would always fire; the check is SMP-only and, even then, only
+        // proves *someone* holds the lock.
+        #[cfg(CONFIG_SMP)]
+        // SAFETY: The safety requirements guarantee a valid, initialised
+        // spinlock.
+        unsafe {
+            debug_assert!(crate::bindings::spin_is_locked(ptr))
+        };
+        #[cfg(not(CONFIG_SMP))]
+        let _ = ptr;
+    }
+}
+
+/// A spinlock backend that saves and restores interrupt state.
+///
+/// Guards from this backend restore the saved IRQ flags on drop
+/// (`spin_unlock_irqrestore`), making the critical section safe against
+/// deadlock with interrupt handlers taking the same lock on this CPU.
+pub struct SpinLockIrqBackend;
+
+// SAFETY: The same `spinlock_t` provides mutual exclusion; only the
+// irq-state handling differs.
+unsafe impl super::Backend for SpinLockIrqBackend {
+    type State = crate::bindings::spinlock_t;
+    type GuardState = core::ffi::c_ulong;
+
+    unsafe fn init(
+        ptr: *mut Self::State,
+        name: *const core::ffi::c_char,
+        key: *mut crate::bindings::lock_class_key,
+    ) {
+        // SAFETY: As for `SpinLockBackend::init`.
+        unsafe { crate::bindings::__spin_lock_init(ptr, name, key) }
+    }
+
+    unsafe fn lock(ptr: *mut Self::State) -> Self::GuardState {
+        // SAFETY: The safety requirements of this function ensure that
+        // `ptr` points to valid memory.
+        unsafe { crate::bindings::spin_lock_irqsave(ptr) }
+    }
+
+    unsafe fn unlock(ptr: *mut Self::State, guard_state: &Self::GuardState) {
+        // SAFETY: The caller owns the lock and `guard_state` holds the
+        // flags saved when it was taken.
+        unsafe { crate::bindings::spin_unlock_irqrestore(ptr, *guard_state) }
+    }
+}
+
+impl<T: ?Sized> SpinLock<T> {
+    /// Acquires the lock with interrupts disabled, returning a guard that
+    /// restores the previous interrupt state on drop.
+    ///
+    /// The same `spinlock_t` backs both acquisition modes; mixing them on
+    /// one lock is allowed only if every plain `lock()` site runs in a
+    /// context the irq handler cannot interrupt (e.g. with that irq
+    /// disabled), the usual C rule.
+    pub fn lock_irqsave(&self) -> super::Guard<'_, T, SpinLockIrqBackend> {
+        // SAFETY: `Lock<T, SpinLockBackend>` and
+        // `Lock<T, SpinLockIrqBackend>` share the same `State` type and
+        // layout; only the guard behaviour differs, so reinterpreting the
+        // reference is sound.
+        let irq_lock = unsafe {
+            &*(self as *const SpinLock<T> as *const super::Lock<T, SpinLockIrqBackend>)
+        };
+        irq_lock.lock()
+    }
+}
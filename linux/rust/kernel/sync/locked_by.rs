@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A wrapper for data protected by a lock that does not wrap it.
+
+use super::lock::{Backend, Lock};
+use core::cell::UnsafeCell;
+
+/// Allows access to some data to be serialised by a lock that does not
+/// wrap it.
+///
+/// In most cases, data protected by a lock is wrapped by the appropriate
+/// lock type. However, this is not possible when the data is embedded in a
+/// structure different from the one containing the lock. The classic
+/// example in this tree is binder's `Node`, whose fields are protected by
+/// the owning `Process`'s lock.
+///
+/// # Owner stability
+///
+/// [`LockedBy::new`] captures the *address* of the owner's protected data
+/// as the identity proof that `access` later checks against. The owner
+/// must therefore not move for as long as any `LockedBy` refers to it; in
+/// practice owners live behind `Arc`s or in pinned driver data, which is
+/// what the binder nodes rely on. A moved owner makes the stored address
+/// stale and every later access panic.
+pub struct LockedBy<T: ?Sized, U: ?Sized> {
+    owner: *const U,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: `LockedBy` serialises access through the owner's lock.
+unsafe impl<T: ?Sized + Send, U: ?Sized> Send for LockedBy<T, U> {}
+// SAFETY: See above.
+unsafe impl<T: ?Sized + Send, U: ?Sized> Sync for LockedBy<T, U> {}
+
+impl<T, U> LockedBy<T, U> {
+    /// Constructs a new instance of [`LockedBy`].
+    ///
+    /// It stores the raw pointer of the owner's data as the identity that
+    /// accessors must present; see the type docs for the stability
+    /// requirement this places on the owner.
+    pub fn new<B: Backend>(owner: &Lock<U, B>, data: T) -> Self {
+        // A zero-sized owner has no unique address, so the identity
+        // check in `access*` would accept any guard -- reject the
+        // configuration at build time.
+        const {
+            assert!(
+                core::mem::size_of::<U>() > 0,
+                "`U` cannot be a ZST because `LockedBy` must compare owner addresses",
+            )
+        };
+        Self {
+            owner: owner.data.get(),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized, U> LockedBy<T, U> {
+    /// Returns a reference to the protected data when the caller provides
+    /// evidence (via a shared borrow of the owner's protected data) that
+    /// the owner's lock is held.
+    ///
+    /// Panics if `owner` is not the owner recorded at construction time.
+    /// The two likely causes are passing a guard of the wrong lock and an
+    /// owner that has moved since [`LockedBy::new`]; both are driver
+    /// bugs.
+    pub fn access<'a>(&'a self, owner: &'a U) -> &'a T {
+        self.assert_same_owner(owner);
+        // SAFETY: The owner's lock is held (the caller has a shared
+        // borrow of the protected data), serialising all accessors.
+        unsafe { &*self.data.get() }
+    }
+
+    /// Mutable counterpart of [`LockedBy::access`].
+    pub fn access_mut<'a>(&'a self, owner: &'a mut U) -> &'a mut T {
+        self.assert_same_owner(owner);
+        // SAFETY: The owner's lock is held exclusively (the caller has a
+        // mutable borrow of the protected data).
+        unsafe { &mut *self.data.get() }
+    }
+
+    fn assert_same_owner(&self, owner: &U) {
+        if !core::ptr::eq(owner, self.owner) {
+            panic!(
+                "LockedBy owner mismatch: wrong lock's guard, or the owner moved after new() \
+                 (expected {:p}, got {:p})",
+                self.owner, owner as *const U,
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Utilities for working with `struct poll_table`.
+
+use super::CondVar;
+use crate::{bindings, file::File, str::CStr, types::Opaque};
+
+/// Wraps the kernel's `struct poll_table`.
+#[repr(transparent)]
+pub struct PollTable(Opaque<bindings::poll_table_struct>);
+
+impl PollTable {
+    /// Creates a reference to a [`PollTable`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid (or null, which the C helpers accept) for the
+    /// duration of the returned borrow.
+    pub unsafe fn from_ptr<'a>(ptr: *mut bindings::poll_table_struct) -> &'a mut PollTable {
+        // SAFETY: `PollTable` is a transparent wrapper.
+        unsafe { &mut *ptr.cast() }
+    }
+
+    /// Registers the poll table with a condition variable's wait queue,
+    /// so notifications on the condvar wake the polling task.
+    pub fn register_wait(&mut self, file: &File, cv: &PollCondVar) {
+        // SAFETY: The table and file are valid for this call, and
+        // `poll_wait` tolerates a null queueing function.
+        unsafe {
+            bindings::poll_wait(
+                file.as_ptr(),
+                cv.inner.wait_queue_head.get(),
+                self.0.get(),
+            )
+        };
+    }
+}
+
+/// A condition variable usable from `poll` implementations.
+///
+/// Waiters registered through [`PollTable::register_wait`] carry the
+/// event mask they asked for as their wake key; the `notify_*` methods
+/// select how much of the queue wakes.
+pub struct PollCondVar {
+    pub(crate) inner: CondVar,
+}
+
+impl PollCondVar {
+    /// Constructs an uninitialised poll condvar.
+    ///
+    /// # Safety
+    ///
+    /// [`PollCondVar::init_once`] must be called before first use, and
+    /// the value must not move afterwards.
+    pub unsafe fn new_uninit() -> Self {
+        Self {
+            // SAFETY: Forwarded contract.
+            inner: unsafe { CondVar::new_uninit() },
+        }
+    }
+
+    /// Initialises the wait queue.
+    ///
+    /// # Safety
+    ///
+    /// Must be called exactly once before first use.
+    pub unsafe fn init_once(&self, name: &'static CStr) {
+        // SAFETY: Forwarded contract.
+        unsafe { self.inner.init_once(name) };
+    }
+
+    /// Wakes all waiters regardless of the events they polled for.
+    pub fn notify_all(&self) {
+        self.inner.notify_all();
+    }
+
+    /// Wakes the waiters interested in any event in `mask`.
+    ///
+    /// Poll waiters are enqueued by `poll_wait` with `pollwake` as their
+    /// wake function, which drops waiters whose requested events do not
+    /// intersect the wake key -- so `notify_poll(EPOLLOUT)` skips
+    /// pure-`EPOLLIN` pollers, while a null key (the plain
+    /// [`notify_all`](Self::notify_all)) wakes everyone. Wakeups remain
+    /// hints: the driver's `poll` callback must still return the true
+    /// event mask when the woken task re-polls.
+    pub fn notify_poll(&self, mask: u32) {
+        // SAFETY: The wait queue is initialised per the `init_once`
+        // contract; the key is the `__poll_t` mask, as `wake_up_poll`
+        // passes it.
+        unsafe {
+            bindings::__wake_up(
+                self.inner.wait_queue_head.get(),
+                bindings::TASK_NORMAL,
+                0,
+                mask as usize as *mut core::ffi::c_void,
+            )
+        };
+    }
+}
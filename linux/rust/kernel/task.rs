@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Tasks (threads and processes).
+//!
+//! C header: [`include/linux/sched.h`](srctree/include/linux/sched.h)
+
+use crate::{
+    bindings,
+    types::{AlwaysRefCounted, Opaque},
+};
+use core::ptr::NonNull;
+
+/// Wraps the kernel's `struct task_struct`.
+///
+/// # Invariants
+///
+/// All instances are valid tasks created by the C portion of the kernel;
+/// a shared reference guarantees the refcount is non-zero for its
+/// duration.
+#[repr(transparent)]
+pub struct Task(Opaque<bindings::task_struct>);
+
+// SAFETY: The only situation in which a task can be accessed mutably is
+// via the refcount, which is atomic.
+unsafe impl Sync for Task {}
+// SAFETY: See above.
+unsafe impl Send for Task {}
+
+impl Task {
+    /// Returns a reference to the current task.
+    ///
+    /// The lifetime is tied to the borrow of the returned reference, which
+    /// is sound because the current task cannot exit while running this
+    /// code.
+    pub fn current<'a>() -> &'a Task {
+        // SAFETY: `current` is always a valid task for the duration of the
+        // calling context.
+        unsafe { &*(bindings::get_current() as *const Task) }
+    }
+
+    /// Returns a raw pointer to the inner C struct.
+    pub fn as_ptr(&self) -> *mut bindings::task_struct {
+        self.0.get()
+    }
+
+    /// Returns the effective UID of this task.
+    pub fn euid(&self) -> u32 {
+        // SAFETY: The task is valid per the type invariant; `cred` is RCU
+        // but reading our own task's euid value is the standard pattern
+        // the C `current_euid()` macro expands to.
+        unsafe { (*(*self.as_ptr()).cred).euid.val }
+    }
+}
+
+// SAFETY: The type invariants guarantee that `Task` is always ref-counted.
+unsafe impl AlwaysRefCounted for Task {
+    fn inc_ref(&self) {
+        // SAFETY: The refcount is non-zero per the type invariant.
+        unsafe { bindings::get_task_struct(self.as_ptr()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee a matching increment.
+        unsafe { bindings::put_task_struct(obj.cast().as_ptr()) }
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Time-related primitives.
+//!
+//! C header: [`include/linux/ktime.h`](srctree/include/linux/ktime.h)
+
+use crate::bindings;
+
+/// A kernel `ktime_t`: a time value in nanoseconds.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ktime {
+    inner: bindings::ktime_t,
+}
+
+impl Ktime {
+    /// Returns the current monotonic time.
+    pub fn ktime_get() -> Self {
+        // SAFETY: No requirements on calling context.
+        Self {
+            inner: unsafe { bindings::ktime_get() },
+        }
+    }
+
+    /// Creates a time value from nanoseconds.
+    pub const fn from_ns(ns: i64) -> Self {
+        Self { inner: ns }
+    }
+
+    /// Creates a time value from milliseconds.
+    pub const fn from_ms(ms: i64) -> Self {
+        Self {
+            inner: ms * 1_000_000,
+        }
+    }
+
+    /// Returns the value in nanoseconds.
+    pub const fn to_ns(self) -> i64 {
+        self.inner
+    }
+
+    /// Returns the value rounded down to milliseconds.
+    pub const fn to_ms(self) -> i64 {
+        self.inner / 1_000_000
+    }
+}
+
+impl core::ops::Sub for Ktime {
+    type Output = Ktime;
+
+    fn sub(self, other: Ktime) -> Ktime {
+        Ktime {
+            inner: self.inner - other.inner,
+        }
+    }
+}
+
+impl core::ops::Add for Ktime {
+    type Output = Ktime;
+
+    fn add(self, other: Ktime) -> Ktime {
+        Ktime {
+            inner: self.inner + other.inner,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel types.
+
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// Stores an opaque value.
+///
+/// `Opaque<T>` is meant to be used with FFI objects that are never
+/// interpreted by Rust code, e.g. because they contain self-referential
+/// pointers or are mutated by C behind our back.
+#[repr(transparent)]
+pub struct Opaque<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> Opaque<T> {
+    /// Creates a new opaque value.
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(MaybeUninit::new(value)),
+        }
+    }
+
+    /// Creates an uninitialised value.
+    pub const fn uninit() -> Self {
+        Self {
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns a raw pointer to the opaque data.
+    pub const fn get(&self) -> *mut T {
+        UnsafeCell::get(&self.value).cast::<T>()
+    }
+
+    /// Gets the value behind `this`.
+    ///
+    /// This function is useful to get access to the value without creating
+    /// intermediate references.
+    pub const fn raw_get(this: *const Self) -> *mut T {
+        UnsafeCell::raw_get(this.cast::<UnsafeCell<MaybeUninit<T>>>()).cast::<T>()
+    }
+}
+
+// SAFETY: `Opaque<T>` is used for FFI state that the C side synchronises.
+unsafe impl<T> Sync for Opaque<T> {}
+// SAFETY: See above.
+unsafe impl<T> Send for Opaque<T> {}
+
+/// Types that are _always_ reference counted.
+///
+/// It allows such types to define their own custom ref increment and
+/// decrement functions. Additionally, it allows users to convert from a
+/// shared reference `&T` to an owned reference [`ARef<T>`].
+///
+/// # Safety
+///
+/// Implementers must ensure that increments to the reference count keep the
+/// object alive in memory at least until matching decrements are performed.
+/// Implementers must also ensure that all instances are reference-counted;
+/// that is, they must not allow destruction without a matching decrement.
+pub unsafe trait AlwaysRefCounted {
+    /// Increments the reference count on the object.
+    fn inc_ref(&self);
+
+    /// Decrements the reference count on the object.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that there was a previous matching increment to
+    /// the reference count, and that the object is no longer used after its
+    /// reference count is decremented.
+    unsafe fn dec_ref(obj: NonNull<Self>);
+}
+
+/// An owned reference to an always-reference-counted object.
+///
+/// The object's reference count is automatically decremented when an
+/// instance of [`ARef`] is dropped.
+pub struct ARef<T: AlwaysRefCounted> {
+    ptr: NonNull<T>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: An `ARef<T>` is just a reference-counted handle; it is `Send` when
+// a shared reference to the underlying object is.
+unsafe impl<T: AlwaysRefCounted + Sync + Send> Send for ARef<T> {}
+// SAFETY: See above.
+unsafe impl<T: AlwaysRefCounted + Sync + Send> Sync for ARef<T> {}
+
+impl<T: AlwaysRefCounted> ARef<T> {
+    /// Creates a new instance of [`ARef`].
+    ///
+    /// It takes over an increment of the reference count on the underlying
+    /// object.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that the reference count was incremented at least
+    /// once, and that they are properly relinquishing one increment.
+    pub unsafe fn from_raw(ptr: NonNull<T>) -> Self {
+        // INVARIANT: The safety requirements guarantee that the new instance
+        // now owns the increment on the refcount.
+        Self {
+            ptr,
+            _p: PhantomData,
+        }
+    }
+
+    /// Consumes the `ARef`, returning a raw pointer.
+    ///
+    /// The caller becomes responsible for the reference count increment this
+    /// `ARef` owned.
+    pub fn into_raw(me: Self) -> NonNull<T> {
+        let ptr = me.ptr;
+        core::mem::forget(me);
+        ptr
+    }
+}
+
+impl<T: AlwaysRefCounted> Clone for ARef<T> {
+    fn clone(&self) -> Self {
+        self.inc_ref();
+        // SAFETY: We incremented the refcount above.
+        unsafe { Self::from_raw(self.ptr) }
+    }
+}
+
+impl<T: AlwaysRefCounted> Deref for ARef<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The type invariants guarantee that the object is valid.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: AlwaysRefCounted> From<&T> for ARef<T> {
+    fn from(b: &T) -> Self {
+        b.inc_ref();
+        // SAFETY: We just incremented the refcount above.
+        unsafe { Self::from_raw(NonNull::from(b)) }
+    }
+}
+
+impl<T: AlwaysRefCounted> Drop for ARef<T> {
+    fn drop(&mut self) {
+        // SAFETY: The type invariants guarantee that the `ARef` owns the
+        // reference we're about to decrement.
+        unsafe { T::dec_ref(self.ptr) };
+    }
+}
+
+/// Types that can be converted to and from a pointer usable by foreign (C)
+/// code.
+///
+/// # Safety
+///
+/// Implementers must ensure that [`into_foreign`](Self::into_foreign)
+/// returns a pointer that [`from_foreign`](Self::from_foreign) can convert
+/// back to the original value, and that the pointer stays valid in between.
+pub unsafe trait ForeignOwnable: Sized {
+    /// Type used to immutably borrow a value that is currently foreign-owned.
+    type Borrowed<'a>;
+
+    /// Type used to mutably borrow a value that is currently foreign-owned.
+    type BorrowedMut<'a>;
+
+    /// Converts a Rust-owned object to a foreign-owned one.
+    fn into_foreign(self) -> *mut core::ffi::c_void;
+
+    /// Converts a foreign-owned object back to a Rust-owned one.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by a previous call to `into_foreign`,
+    /// and must not be used after this call.
+    unsafe fn from_foreign(ptr: *mut core::ffi::c_void) -> Self;
+
+    /// Borrows a foreign-owned object immutably.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by a previous call to `into_foreign`
+    /// without a matching `from_foreign`, and the returned borrow must end
+    /// before one happens.
+    unsafe fn borrow<'a>(ptr: *mut core::ffi::c_void) -> Self::Borrowed<'a>;
+
+    /// Borrows a foreign-owned object mutably.
+    ///
+    /// # Safety
+    ///
+    /// As for [`borrow`](Self::borrow), and additionally there must be no
+    /// concurrent borrows while the returned borrow lives.
+    unsafe fn borrow_mut<'a>(ptr: *mut core::ffi::c_void) -> Self::BorrowedMut<'a>;
+}
+
+/// Runs a cleanup function/closure when dropped.
+///
+/// The [`ScopeGuard::dismiss`] function prevents the cleanup function from
+/// running.
+pub struct ScopeGuard<T, F: FnOnce(T)>(Option<(T, F)>);
+
+impl<T, F: FnOnce(T)> ScopeGuard<T, F> {
+    /// Creates a new guarded object wrapping the given data and with the
+    /// given cleanup function.
+    pub fn new_with_data(data: T, cleanup_func: F) -> Self {
+        Self(Some((data, cleanup_func)))
+    }
+
+    /// Prevents the cleanup function from running and returns the guarded
+    /// data.
+    pub fn dismiss(mut self) -> T {
+        let (data, _) = self.0.take().unwrap();
+        data
+    }
+}
+
+impl ScopeGuard<(), fn(())> {
+    /// Creates a new guarded object with the given cleanup function.
+    pub fn new(cleanup: impl FnOnce()) -> ScopeGuard<(), impl FnOnce(())> {
+        ScopeGuard::new_with_data((), move |()| cleanup())
+    }
+}
+
+impl<T, F: FnOnce(T)> Deref for ScopeGuard<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0.as_ref().unwrap().0
+    }
+}
+
+impl<T, F: FnOnce(T)> DerefMut for ScopeGuard<T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0.as_mut().unwrap().0
+    }
+}
+
+impl<T, F: FnOnce(T)> Drop for ScopeGuard<T, F> {
+    fn drop(&mut self) {
+        // Run the cleanup function if one is still present.
+        if let Some((data, cleanup)) = self.0.take() {
+            cleanup(data)
+        }
+    }
+}
+
+// SAFETY: A `Box` converts to and from a raw heap pointer losslessly, and
+// the pointed-to value is not touched while foreign-owned.
+unsafe impl<T: 'static> ForeignOwnable for alloc::boxed::Box<T> {
+    type Borrowed<'a> = &'a T;
+    type BorrowedMut<'a> = &'a mut T;
+
+    fn into_foreign(self) -> *mut core::ffi::c_void {
+        alloc::boxed::Box::into_raw(self).cast()
+    }
+
+    unsafe fn from_foreign(ptr: *mut core::ffi::c_void) -> Self {
+        // SAFETY: Per the trait contract, `ptr` came from `into_foreign`.
+        unsafe { alloc::boxed::Box::from_raw(ptr.cast()) }
+    }
+
+    unsafe fn borrow<'a>(ptr: *mut core::ffi::c_void) -> &'a T {
+        // SAFETY: Per the trait contract, the value outlives the borrow.
+        unsafe { &*ptr.cast() }
+    }
+
+    unsafe fn borrow_mut<'a>(ptr: *mut core::ffi::c_void) -> &'a mut T {
+        // SAFETY: Per the trait contract, the borrow is exclusive.
+        unsafe { &mut *ptr.cast() }
+    }
+}
+
+// SAFETY: A shared reference is a thin pointer that round-trips through
+// the raw-pointer conversions losslessly, and "foreign ownership" of a
+// borrow owns nothing: reclaiming it drops nothing.
+unsafe impl<'a, T: Sync> ForeignOwnable for &'a T {
+    type Borrowed<'b> = &'b T;
+    type BorrowedMut<'b> = &'b T;
+
+    fn into_foreign(self) -> *mut core::ffi::c_void {
+        self as *const T as *mut core::ffi::c_void
+    }
+
+    unsafe fn from_foreign(ptr: *mut core::ffi::c_void) -> Self {
+        // SAFETY: Per the trait contract, `ptr` came from `into_foreign`
+        // on a reference whose lifetime `'a` still covers this call.
+        unsafe { &*ptr.cast() }
+    }
+
+    unsafe fn borrow<'b>(ptr: *mut core::ffi::c_void) -> &'b T {
+        // SAFETY: See `from_foreign`.
+        unsafe { &*ptr.cast() }
+    }
+
+    unsafe fn borrow_mut<'b>(ptr: *mut core::ffi::c_void) -> &'b T {
+        // A borrowed entry can never be handed out mutably; the shared
+        // form is returned for both.
+        // SAFETY: See `from_foreign`.
+        unsafe { &*ptr.cast() }
+    }
+}
@@ -0,0 +1,618 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! User pointers.
+//!
+//! C header: [`include/linux/uaccess.h`](srctree/include/linux/uaccess.h)
+
+use crate::{
+    bindings,
+    error::{code::*, Result},
+};
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+/// A reference to an area in userspace memory, which can be either read-only
+/// or read-write.
+///
+/// All methods on this struct are safe: invalid pointers return `EFAULT`.
+/// Concurrent access, *including data races to/from userspace memory*, is
+/// permitted, because fundamentally another userspace thread/process could
+/// always be modifying memory at the same time (in the same way that userspace
+/// Rust's [`std::io`] permits data races with the contents of files on disk).
+///
+/// All APIs enforce the invariant that a given byte of memory from userspace
+/// may only be read once. By preventing double-fetches we avoid TOCTOU
+/// vulnerabilities.
+///
+/// Constructing a [`UserSlicePtr`] performs no checks on the provided
+/// address and length; it can safely be constructed inside the kernel aspace
+/// with no intention to ever use it. The checks are done only when the data
+/// is actually copied.
+pub struct UserSlicePtr(pub(crate) *mut c_void, pub(crate) usize);
+
+impl UserSlicePtr {
+    /// Constructs a user slice from a raw pointer and a length in bytes.
+    ///
+    /// # Safety
+    ///
+    /// Callers must be careful to avoid time-of-check-to-time-of-use
+    /// (TOCTOU) issues. The simplest way is to create a single instance of
+    /// [`UserSlicePtr`] per user memory block as it reads each byte at
+    /// most once.
+    pub unsafe fn new(ptr: *mut c_void, length: usize) -> Self {
+        UserSlicePtr(ptr, length)
+    }
+
+    /// Returns the length (in bytes) of the user slice.
+    pub fn len(&self) -> usize {
+        self.1
+    }
+
+    /// Returns `true` if the user slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.1 == 0
+    }
+
+    /// Constructs a [`UserSlicePtrReader`].
+    pub fn reader(self) -> UserSlicePtrReader {
+        UserSlicePtrReader(self.0, self.1)
+    }
+
+    /// Constructs a [`UserSlicePtrWriter`].
+    pub fn writer(self) -> UserSlicePtrWriter {
+        UserSlicePtrWriter(self.0, self.1)
+    }
+
+    /// Checks that the slice plausibly lies in user address space.
+    ///
+    /// Wraps `access_ok` over the whole range, failing with `EFAULT`
+    /// when it cannot be user memory (e.g. a kernel-half address). The
+    /// answer is advisory, like [`MapleTreeAlloc::first_free`]'s
+    /// (same TOCTOU class): it only checks the range against the
+    /// address-space limit -- not that mappings exist, which another
+    /// thread could change before any copy -- so it is a fail-fast
+    /// courtesy before long operations, never a security boundary.
+    /// Every `copy_*_user` still performs the authoritative check at
+    /// copy time.
+    ///
+    /// [`MapleTreeAlloc::first_free`]: crate::maple_tree::MapleTreeAlloc::first_free
+    pub fn check_access(&self) -> Result {
+        // SAFETY: `access_ok` only inspects the pointer value and
+        // length; it performs no access.
+        if unsafe { bindings::access_ok(self.0, self.1 as _) } {
+            Ok(())
+        } else {
+            Err(EFAULT)
+        }
+    }
+
+    /// Splits the slice into two non-overlapping slices at `mid`.
+    ///
+    /// Unlike [`UserSlicePtr::reader_writer`], whose reader and writer
+    /// cover the same (overlapping) full range, the two halves returned
+    /// here cannot alias, so a reader over one and a writer over the
+    /// other compose safely -- e.g. parsing the first `mid` bytes of an
+    /// ioctl payload while using the remainder as the reply buffer.
+    ///
+    /// Returns `EINVAL` if `mid > len`: that is caller arithmetic gone
+    /// wrong, not a userspace fault.
+    pub fn split_at(self, mid: usize) -> Result<(UserSlicePtr, UserSlicePtr)> {
+        if mid > self.1 {
+            return Err(EINVAL);
+        }
+        // The two halves are subranges of a range the caller already
+        // vouched for in `new`; no further checks are needed until copy
+        // time, as usual.
+        Ok((
+            UserSlicePtr(self.0, mid),
+            UserSlicePtr(self.0.wrapping_add(mid), self.1 - mid),
+        ))
+    }
+
+    /// Constructs both a [`UserSlicePtrReader`] and a
+    /// [`UserSlicePtrWriter`] to the same (overlapping) area.
+    pub fn reader_writer(self) -> (UserSlicePtrReader, UserSlicePtrWriter) {
+        (
+            UserSlicePtrReader(self.0, self.1),
+            UserSlicePtrWriter(self.0, self.1),
+        )
+    }
+}
+
+/// Specifies that a type is safely readable from bytes.
+///
+/// # Safety
+///
+/// Implementers must ensure that all initialised bit patterns are valid for
+/// the type and that the type has no padding.
+pub unsafe trait ReadableFromBytes {}
+
+// SAFETY: All bit patterns are acceptable values of the types below.
+unsafe impl ReadableFromBytes for u8 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for u16 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for u32 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for u64 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for usize {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for i8 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for i16 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for i32 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for i64 {}
+// SAFETY: See above.
+unsafe impl ReadableFromBytes for isize {}
+
+/// Specifies that a type is safely writable to bytes.
+///
+/// # Safety
+///
+/// Implementers must ensure that the type contains no padding or other
+/// uninitialised bytes, so that writing its object representation to
+/// userspace cannot leak kernel memory.
+pub unsafe trait WritableToBytes {}
+
+// SAFETY: The types below have no uninitialised bytes.
+unsafe impl WritableToBytes for u8 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for u16 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for u32 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for u64 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for usize {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for i8 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for i16 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for i32 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for i64 {}
+// SAFETY: See above.
+unsafe impl WritableToBytes for isize {}
+
+/// A reader for [`UserSlicePtr`].
+///
+/// Used to incrementally read from the user slice.
+pub struct UserSlicePtrReader(pub(crate) *mut c_void, pub(crate) usize);
+
+impl UserSlicePtrReader {
+    /// Returns the number of bytes left to be read from this reader.
+    ///
+    /// Note that even reading less than this number of bytes may fail.
+    pub fn len(&self) -> usize {
+        self.1
+    }
+
+    /// Returns `true` if no data is available in the io buffer.
+    pub fn is_empty(&self) -> bool {
+        self.1 == 0
+    }
+
+    /// Reads raw data from the user slice into a kernel buffer.
+    pub fn read_slice(&mut self, out: &mut [u8]) -> Result {
+        // SAFETY: The output buffer is valid for `out.len()` bytes.
+        unsafe { self.read_raw(out.as_mut_ptr(), out.len()) }
+    }
+
+    /// Reads raw data from the user slice into a raw kernel buffer.
+    ///
+    /// # Safety
+    ///
+    /// The output buffer must be valid for writes of `len` bytes.
+    pub unsafe fn read_raw(&mut self, out: *mut u8, len: usize) -> Result {
+        if len > self.1 {
+            return Err(EFAULT);
+        }
+        // SAFETY: The caller promises that `out` is valid for writing `len`
+        // bytes; `copy_from_user` handles any faults on the user side.
+        let res = unsafe { bindings::copy_from_user(out.cast(), self.0, len as _) };
+        if res != 0 {
+            return Err(EFAULT);
+        }
+        self.0 = self.0.wrapping_add(len);
+        self.1 -= len;
+        Ok(())
+    }
+
+    /// Reads a value of the given type from the user slice.
+    pub fn read<T: ReadableFromBytes>(&mut self) -> Result<T> {
+        let mut out = core::mem::MaybeUninit::<T>::uninit();
+        // SAFETY: `out` is valid for writing `size_of::<T>()` bytes.
+        unsafe { self.read_raw(out.as_mut_ptr().cast(), core::mem::size_of::<T>())? };
+        // SAFETY: The read above fully initialised `out`, and all bit
+        // patterns are valid for `T` per `ReadableFromBytes`.
+        Ok(unsafe { out.assume_init() })
+    }
+
+    /// Reads a struct whose userspace size may differ from the kernel's,
+    /// with `copy_struct_from_user` semantics.
+    ///
+    /// Case by case: `user_size` equal to `size_of::<T>()` is a plain
+    /// read. Smaller copies the user's bytes over a `T::default()`, so
+    /// trailing kernel-side fields take the type's defaults -- note this
+    /// is `Default`, the Rust spelling of C's zeroing; for the
+    /// `repr(C)` UAPI structs this is used with, defaults are zero, but
+    /// a type whose default is not all-zeroes makes this subtly
+    /// different from the C helper. Larger reads our size and then
+    /// requires every surplus byte to be zero, failing with `E2BIG`
+    /// exactly as C does, which is how newer userspace learns the
+    /// kernel is too old for the fields it set. The whole `user_size`
+    /// span is consumed on success, regardless of case.
+    pub fn read_struct_versioned<T: ReadableFromBytes + Default>(
+        &mut self,
+        user_size: usize,
+    ) -> Result<T> {
+        let kernel_size = core::mem::size_of::<T>();
+        if user_size == kernel_size {
+            return self.read::<T>();
+        }
+        if user_size < kernel_size {
+            let mut value = T::default();
+            // SAFETY: The destination is the first `user_size` bytes of
+            // a live `T`, and `ReadableFromBytes` makes any resulting
+            // byte pattern a valid `T`.
+            unsafe { self.read_raw(&mut value as *mut T as *mut u8, user_size)? };
+            return Ok(value);
+        }
+        let value = self.read::<T>()?;
+        let mut surplus = user_size - kernel_size;
+        let mut buf = [0u8; 64];
+        while surplus > 0 {
+            let n = surplus.min(buf.len());
+            self.read_slice(&mut buf[..n])?;
+            if buf[..n].iter().any(|&b| b != 0) {
+                return Err(E2BIG);
+            }
+            surplus -= n;
+        }
+        Ok(value)
+    }
+
+    /// Streams the remainder of the slice through `f` in chunks of at
+    /// most `chunk` bytes.
+    ///
+    /// Unlike [`read_all`](Self::read_all), memory use is bounded by the
+    /// chunk size however large the user buffer is. The bounce buffer is
+    /// heap-allocated once up front (`chunk` is caller-chosen and kernel
+    /// stacks are small; a page is a sensible choice). A callback error
+    /// stops the loop and propagates as-is, with the reader left after
+    /// the last fully-delivered chunk.
+    pub fn read_chunked(
+        &mut self,
+        chunk: usize,
+        mut f: impl FnMut(&[u8]) -> Result,
+    ) -> Result {
+        if chunk == 0 {
+            return Err(EINVAL);
+        }
+        let buf_len = chunk.min(self.len());
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(buf_len).map_err(|_| ENOMEM)?;
+        buf.resize(buf_len, 0);
+        while !self.is_empty() {
+            let n = chunk.min(self.len());
+            self.read_slice(&mut buf[..n])?;
+            f(&buf[..n])?;
+        }
+        Ok(())
+    }
+
+    /// Reads a `{ u32 len; u8 data[len]; }` blob, as used by many ioctl
+    /// payloads.
+    ///
+    /// The checks are ordered so each failure gets the right errno:
+    /// a length above `max` is a protocol violation (`EINVAL`); a length
+    /// larger than the remaining slice means the buffer lied about its
+    /// size (`EFAULT`, the same class as any short user buffer); and the
+    /// fallible allocation reports `ENOMEM`. On success the reader has
+    /// advanced past both the prefix and the payload. A failure after
+    /// the prefix was read leaves the prefix consumed -- un-reading is
+    /// not possible -- which is documented so callers treat the reader
+    /// as positioned, not rewound.
+    pub fn read_len_prefixed(&mut self, max: usize) -> Result<Vec<u8>> {
+        let len = self.read::<u32>()? as usize;
+        if len > max {
+            return Err(EINVAL);
+        }
+        if len > self.1 {
+            return Err(EFAULT);
+        }
+        let mut data = Vec::new();
+        data.try_reserve_exact(len).map_err(|_| ENOMEM)?;
+        data.resize(len, 0);
+        self.read_slice(&mut data)?;
+        Ok(data)
+    }
+
+    /// Reads all remaining data in the buffer into a vector.
+    pub fn read_all(&mut self) -> Result<Vec<u8>> {
+        let len = self.len();
+        let mut data = Vec::new();
+        data.try_reserve_exact(len).map_err(|_| ENOMEM)?;
+        data.resize(len, 0);
+        self.read_slice(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// A saved [`UserSlicePtrWriter`] position; see
+/// [`UserSlicePtrWriter::checkpoint`].
+#[derive(Clone, Copy)]
+pub struct WriterCheckpoint {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+/// A kernel `iov_iter` importing a user buffer.
+///
+/// Importing once and copying page by page (`copy_page_from_iter`) batches
+/// the access checks and fault handling that per-chunk `copy_from_user`
+/// calls repeat, which is what makes it worthwhile for multi-page
+/// payloads.
+///
+/// # Invariants
+///
+/// `iter` is a successfully-imported `ITER_SOURCE` iterator over the user
+/// range that `reader` covered at construction time, and `reader`'s
+/// cursor has been advanced past that range (the iterator now owns it, in
+/// keeping with the read-each-byte-once rule).
+pub struct IovIter<'a> {
+    iter: bindings::iov_iter,
+    _p: core::marker::PhantomData<&'a mut UserSlicePtrReader>,
+}
+
+impl UserSlicePtrReader {
+    /// Imports the next `len` bytes of this reader as an [`IovIter`],
+    /// consuming exactly that much of the reader's range.
+    ///
+    /// Anything after `len` stays readable through the reader -- a
+    /// caller importing a payload's data region must not lose the
+    /// regions that follow it.
+    pub fn as_iov_iter(&mut self, len: usize) -> Result<IovIter<'_>> {
+        if len > self.1 {
+            return Err(EFAULT);
+        }
+        let mut iter = IovIter {
+            // SAFETY: Fully written by `import_ubuf` below before any
+            // read.
+            iter: unsafe { core::mem::zeroed() },
+            _p: core::marker::PhantomData,
+        };
+        // SAFETY: The pointer refers to `iter`'s own field, which the
+        // borrow on the returned value keeps alive and unmoved.
+        let ret = unsafe {
+            bindings::import_ubuf(
+                bindings::ITER_SOURCE,
+                self.0,
+                len,
+                &mut iter.iter,
+            )
+        };
+        if ret != 0 {
+            return Err(EFAULT);
+        }
+        // INVARIANT: The import succeeded; consume only the imported
+        // prefix (the iterator now owns those bytes, keeping the
+        // read-once rule).
+        self.0 = self.0.wrapping_add(len);
+        self.1 -= len;
+        Ok(iter)
+    }
+}
+
+impl IovIter<'_> {
+    /// Returns the raw iterator for C helpers.
+    pub(crate) fn as_raw(&mut self) -> *mut bindings::iov_iter {
+        &mut self.iter
+    }
+}
+
+/// Unsigned primitive integers that can be byte-swapped after a raw read.
+///
+/// Sealed to the widths wire formats actually use; the swap goes through
+/// the primitive `from_be`/`from_le`, which compile to a single bswap
+/// where needed and to nothing on matching endianness -- no `cfg` in
+/// this code.
+pub trait PrimInt: ReadableFromBytes + private::Sealed {
+    /// Interprets `self`'s bytes as big-endian.
+    fn from_be(self) -> Self;
+    /// Interprets `self`'s bytes as little-endian.
+    fn from_le(self) -> Self;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+}
+
+macro_rules! impl_prim_int {
+    ($($t:ty),+) => {
+        $(impl PrimInt for $t {
+            fn from_be(self) -> Self {
+                <$t>::from_be(self)
+            }
+            fn from_le(self) -> Self {
+                <$t>::from_le(self)
+            }
+        })+
+    };
+}
+
+impl_prim_int!(u16, u32, u64);
+
+impl UserSlicePtrReader {
+    /// Reads a big-endian (network-order) value, returning it in host
+    /// order.
+    pub fn read_be<T: PrimInt>(&mut self) -> Result<T> {
+        self.read::<T>().map(T::from_be)
+    }
+
+    /// Reads a little-endian value, returning it in host order.
+    pub fn read_le<T: PrimInt>(&mut self) -> Result<T> {
+        self.read::<T>().map(T::from_le)
+    }
+}
+
+/// A writer for [`UserSlicePtr`].
+///
+/// Used to incrementally write into the user slice.
+pub struct UserSlicePtrWriter(pub(crate) *mut c_void, pub(crate) usize);
+
+impl UserSlicePtrWriter {
+    /// Returns the number of bytes left to be written from this writer.
+    ///
+    /// Note that even writing less than this number of bytes may fail.
+    pub fn len(&self) -> usize {
+        self.1
+    }
+
+    /// Returns `true` if the io buffer cannot hold any additional data.
+    pub fn is_empty(&self) -> bool {
+        self.1 == 0
+    }
+
+    /// Writes raw data to the user slice from a kernel buffer.
+    pub fn write_slice(&mut self, data: &[u8]) -> Result {
+        // SAFETY: The input buffer is valid for `data.len()` bytes.
+        unsafe { self.write_raw(data.as_ptr(), data.len()) }
+    }
+
+    /// Writes raw data to the user slice from a raw kernel buffer.
+    ///
+    /// # Safety
+    ///
+    /// The input buffer must be valid for reads of `len` bytes.
+    pub unsafe fn write_raw(&mut self, data: *const u8, len: usize) -> Result {
+        if len > self.1 {
+            return Err(EFAULT);
+        }
+        // SAFETY: The caller promises that `data` is valid for reading
+        // `len` bytes; `copy_to_user` handles any faults on the user side.
+        let res = unsafe { bindings::copy_to_user(self.0, data.cast(), len as _) };
+        if res != 0 {
+            return Err(EFAULT);
+        }
+        self.0 = self.0.wrapping_add(len);
+        self.1 -= len;
+        Ok(())
+    }
+
+    /// Writes the provided value to the user slice.
+    pub fn write<T: WritableToBytes>(&mut self, value: &T) -> Result {
+        // SAFETY: `value` is valid for reading `size_of::<T>()` bytes, and
+        // `WritableToBytes` guarantees there are no uninitialised bytes.
+        unsafe { self.write_raw(value as *const T as _, core::mem::size_of::<T>()) }
+    }
+
+    /// Captures the writer's current position for a later
+    /// [`rollback`](Self::rollback).
+    pub fn checkpoint(&self) -> WriterCheckpoint {
+        WriterCheckpoint {
+            ptr: self.0,
+            len: self.1,
+        }
+    }
+
+    /// Restores the cursor to a previously-captured checkpoint.
+    ///
+    /// This only rewinds the cursor: bytes already copied to userspace
+    /// between the checkpoint and the rollback remain visible there, and
+    /// cannot be un-written. The pattern this supports is binder-style
+    /// delivery, where the consumer does not look at the buffer until
+    /// the consumed count says so -- a retry after rollback simply
+    /// overwrites the abandoned bytes. (A staging buffer flushed
+    /// atomically was considered and rejected: it would put an
+    /// allocation in the delivery path.)
+    pub fn rollback(&mut self, cp: WriterCheckpoint) {
+        // A checkpoint from another writer is a logic bug, not a fault;
+        // catch it in debug builds.
+        debug_assert!(cp.len >= self.1);
+        debug_assert!(cp.ptr as usize + cp.len == self.0 as usize + self.1);
+        self.0 = cp.ptr;
+        self.1 = cp.len;
+    }
+
+    /// Zeroes the next `len` bytes of the user slice and advances past
+    /// them.
+    ///
+    /// This is the primitive behind binder's clear-on-free handling: a
+    /// buffer region is pre-zeroed (or cleared on free) without bouncing
+    /// through a kernel buffer, using `clear_user` which exists on every
+    /// architecture.
+    ///
+    /// Returns `EFAULT` if `len` exceeds the remaining space or if
+    /// userspace memory faults part-way; in the latter case the writer is
+    /// not advanced, like the other raw-copy wrappers here.
+    pub fn write_zeroed(&mut self, len: usize) -> Result {
+        if len > self.1 {
+            return Err(EFAULT);
+        }
+        // SAFETY: `self.0` points at the remaining user range, which is at
+        // least `len` bytes by the check above; `clear_user` performs the
+        // access checks itself.
+        let res = unsafe { bindings::clear_user(self.0, len as _) };
+        if res != 0 {
+            return Err(EFAULT);
+        }
+        self.0 = self.0.wrapping_add(len);
+        self.1 -= len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_at_geometry() {
+        // SAFETY: The slice is never dereferenced by these assertions.
+        let slice = unsafe { UserSlicePtr::new(0x1000 as *mut c_void, 16) };
+        let (a, b) = slice.split_at(8).unwrap();
+        assert_eq!(a.0 as usize, 0x1000);
+        assert_eq!(a.1, 8);
+        assert_eq!(b.0 as usize, 0x1008);
+        assert_eq!(b.1, 8);
+
+        // SAFETY: As above.
+        let slice = unsafe { UserSlicePtr::new(0x1000 as *mut c_void, 16) };
+        assert!(slice.split_at(17).is_err());
+    }
+
+    #[test]
+    fn checkpoint_restores_cursor() {
+        // Cursor bookkeeping only; no copy is performed.
+        let mut writer = UserSlicePtrWriter(0x2000 as *mut c_void, 32);
+        let cp = writer.checkpoint();
+        writer.0 = writer.0.wrapping_add(8);
+        writer.1 -= 8;
+        assert_eq!(writer.len(), 24);
+        writer.rollback(cp);
+        assert_eq!(writer.0 as usize, 0x2000);
+        assert_eq!(writer.len(), 32);
+    }
+
+    #[test]
+    fn endian_conversions() {
+        // The PrimInt plumbing must match the primitive conversions.
+        assert_eq!(
+            <u32 as PrimInt>::from_be(u32::from_ne_bytes([0xde, 0xad, 0xbe, 0xef])),
+            0xdeadbeef,
+        );
+        assert_eq!(
+            <u32 as PrimInt>::from_le(u32::from_ne_bytes([0xef, 0xbe, 0xad, 0xde])),
+            0xdeadbeef,
+        );
+        assert_eq!(<u16 as PrimInt>::from_be(u16::from_ne_bytes([0x12, 0x34])), 0x1234);
+    }
+}